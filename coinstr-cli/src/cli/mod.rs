@@ -1,13 +1,21 @@
 // Copyright (c) 2022-2023 Coinstr
 // Distributed under the MIT software license
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
-use coinstr_core::bitcoin::Address;
+use coinstr_core::bitcoin::hashes::sha256;
+use coinstr_core::bitcoin::{Address, OutPoint, Txid};
+use coinstr_core::constants::DEFAULT_HEARTBEAT_THRESHOLD;
+use coinstr_core::nostr_sdk::prelude::XOnlyPublicKey;
 use coinstr_core::nostr_sdk::EventId;
+use coinstr_core::proposal::Recipient;
 
 pub mod io;
+pub mod parser;
 mod types;
 
+pub use self::types::CliTemplate;
 use self::types::{CliNetwork, CliWordCount};
 use crate::DEFAULT_RELAY;
 
@@ -69,9 +77,53 @@ pub enum Command {
         /// Policy description
         #[arg(required = true)]
         policy_description: String,
-        /// Policy descriptor
-        #[arg(required = true)]
-        policy_descriptor: String,
+        /// Policy descriptor (or miniscript policy)
+        #[arg(required_unless_present = "template")]
+        policy_descriptor: Option<String>,
+        /// Build the policy from a template, with the `--key` keys
+        #[arg(
+            long,
+            value_enum,
+            conflicts_with = "policy_descriptor",
+            requires = "keys"
+        )]
+        template: Option<CliTemplate>,
+        /// Template key, in the order of the template roles (repeat for each key)
+        #[arg(long = "key")]
+        keys: Vec<String>,
+        /// Signatures required (of the owners for `inheritance`, of the guardians for `social-recovery`)
+        #[arg(long, default_value_t = 1)]
+        threshold: usize,
+        /// Relative timelock, in blocks (repeat for `decaying`)
+        #[arg(long, value_name = "BLOCKS", conflicts_with = "after")]
+        older: Vec<u32>,
+        /// Absolute timelock, as block height or timestamp (repeat for `decaying`)
+        #[arg(long)]
+        after: Vec<u32>,
+        /// Number of heirs (the last keys) for `inheritance`
+        #[arg(long, default_value_t = 1)]
+        heirs: usize,
+        /// Signatures required of the heirs for `inheritance`
+        #[arg(long, default_value_t = 1)]
+        heirs_threshold: usize,
+        /// SHA256 hash of the recovery secret for `social-recovery`
+        #[arg(long)]
+        hash: Option<sha256::Hash>,
+        /// Tweak the NUMS internal key with a random value, shared with the members
+        /// (for miniscript policies and templates)
+        #[arg(long)]
+        tweak_internal_key: bool,
+        /// Use the MuSig2 aggregate of all the keys as internal key, to spend together
+        /// with the key path (for miniscript policies and templates with the nostr public keys
+        /// of the members, replaced with their announced BIP-86 signer keys)
+        #[arg(long, conflicts_with_all = ["tweak_internal_key", "members"])]
+        musig2: bool,
+        /// Nostr public key of a member (repeat for each member)
+        ///
+        /// If not specified, the members of policies with extended keys are resolved
+        /// from the signer keys announced by the contacts
+        #[arg(long = "member")]
+        members: Vec<XOnlyPublicKey>,
     },
     /// Create a spending proposal
     Spend {
@@ -81,15 +133,117 @@ pub enum Command {
         /// Policy id
         #[arg(required = true)]
         policy_id: EventId,
-        /// To address
+        /// Memo
         #[arg(required = true)]
-        to_address: Address,
-        /// Amount in sats
+        memo: String,
+        /// Recipient, as `<address>:<amount>[:<label>]` (amount in sats)
+        #[arg(long = "to", value_parser = parser::parse_recipient, required_unless_present_any = ["csv", "drain_to"])]
+        recipients: Vec<Recipient>,
+        /// CSV file with a recipient per line, as `<address>,<amount>[,<label>]` (amount in sats)
+        #[arg(long)]
+        csv: Option<PathBuf>,
+        /// Send all the remaining funds to this address
+        #[arg(long = "all", value_name = "ADDRESS")]
+        drain_to: Option<Address>,
+        /// Spending path index (check `get policy`)
+        ///
+        /// If not specified, the cheapest spending path that can be satisfied now is used
+        #[arg(long)]
+        path: Option<usize>,
+        /// Fee rate in sat/vB
+        #[arg(long, conflicts_with_all = ["fee", "target_blocks"])]
+        fee_rate: Option<f32>,
+        /// Absolute fee in sats
+        #[arg(long, conflicts_with_all = ["fee_rate", "target_blocks"])]
+        fee: Option<u64>,
+        /// Confirmation target in blocks, used to estimate the fee rate (default: 6)
+        #[arg(long, conflicts_with_all = ["fee_rate", "fee"])]
+        target_blocks: Option<usize>,
+        /// Signal RBF (BIP125)
+        #[arg(long)]
+        rbf: bool,
+        /// UTXO that must be spent (check `get utxos`)
+        #[arg(long = "utxo")]
+        must_spend: Vec<OutPoint>,
+        /// UTXO that must not be spent
+        #[arg(long = "exclude")]
+        do_not_spend: Vec<OutPoint>,
+        /// Spend only the UTXOs selected with `--utxo`
+        #[arg(long, requires = "must_spend")]
+        only_selected: bool,
+        /// Block height after which the proposal expires
+        #[arg(long, conflicts_with = "expiry_time")]
+        expiry_height: Option<u32>,
+        /// Unix timestamp after which the proposal expires
+        #[arg(long, conflicts_with = "expiry_height")]
+        expiry_time: Option<u64>,
+    },
+    /// Create a proposal to bump the fee of a stuck transaction (RBF or CPFP)
+    BumpFee {
+        /// Keychain name
         #[arg(required = true)]
-        amount: u64,
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
+        /// Txid of the stuck transaction
+        #[arg(required = true)]
+        txid: Txid,
         /// Memo
         #[arg(required = true)]
         memo: String,
+        /// Spending path index (check `get policy`)
+        #[arg(long)]
+        path: Option<usize>,
+        /// Fee rate in sat/vB
+        #[arg(long, conflicts_with_all = ["fee", "target_blocks"])]
+        fee_rate: Option<f32>,
+        /// Absolute fee in sats
+        #[arg(long, conflicts_with_all = ["fee_rate", "target_blocks"])]
+        fee: Option<u64>,
+        /// Confirmation target in blocks, used to estimate the fee rate (default: 6)
+        #[arg(long, conflicts_with_all = ["fee_rate", "fee"])]
+        target_blocks: Option<usize>,
+    },
+    /// Refresh the UTXOs close to the activation of a relative timelock path,
+    /// proposing to send them back to the policy (block based timelocks only)
+    Heartbeat {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
+        /// Refresh the UTXOs with less than this number of blocks left
+        #[arg(long, default_value_t = DEFAULT_HEARTBEAT_THRESHOLD)]
+        threshold: u32,
+        /// Fee rate in sat/vB
+        #[arg(long, conflicts_with = "target_blocks")]
+        fee_rate: Option<f32>,
+        /// Confirmation target in blocks, used to estimate the fee rate (default: 6)
+        #[arg(long, conflicts_with = "fee_rate")]
+        target_blocks: Option<usize>,
+        /// UTXO that must not be refreshed, like the ones frozen in the app
+        #[arg(long = "exclude")]
+        do_not_spend: Vec<OutPoint>,
+        /// Keep checking, every 10 minutes
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Label a policy UTXO
+    Label {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
+        /// UTXO
+        #[arg(required = true)]
+        outpoint: OutPoint,
+        /// Label (empty to remove it)
+        #[arg(required = true)]
+        label: String,
     },
     /// Approve a spending proposal
     Approve {
@@ -100,6 +254,27 @@ pub enum Command {
         #[arg(required = true)]
         proposal_id: EventId,
     },
+    /// Sign a spending proposal with the MuSig2 key path, waiting for the nonces of the other members
+    Musig2Sign {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Proposal id
+        #[arg(required = true)]
+        proposal_id: EventId,
+    },
+    /// Reject a spending proposal
+    Reject {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Proposal id
+        #[arg(required = true)]
+        proposal_id: EventId,
+        /// Reason
+        #[arg(required = true)]
+        reason: String,
+    },
     /// Combine and broadcast the transaction
     Broadcast {
         /// Keychain name
@@ -115,6 +290,54 @@ pub enum Command {
         #[command(subcommand)]
         command: GetCommand,
     },
+    /// Re-encrypt a NIP-04 policy with NIP-44 (the policy gets a new id)
+    Migrate {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
+    },
+    /// Replace the shared key of a policy (the policy id doesn't change)
+    RotateKey {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
+        /// Nostr public key of a member that won't receive the new shared key (repeat for each member)
+        #[arg(long = "exclude")]
+        excluded: Vec<XOnlyPublicKey>,
+    },
+    /// Announce the signer key derived from the seed (BIP-86, or BIP-48 with `--bip48`)
+    AnnounceSigner {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Use the BIP-48 derivation path
+        #[arg(long)]
+        bip48: bool,
+        /// Account index
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+        /// Nostr public key to announce the signer to (repeat for each recipient, default: the contacts)
+        #[arg(long = "to")]
+        recipients: Vec<XOnlyPublicKey>,
+    },
+    /// Create a policy together with other members
+    #[command(arg_required_else_help = true)]
+    Draft {
+        #[command(subcommand)]
+        command: DraftCommand,
+    },
+    /// Create a policy with a FROST threshold key as key path, and spend with it
+    #[command(arg_required_else_help = true)]
+    Frost {
+        #[command(subcommand)]
+        command: FrostCommand,
+    },
     /// Delete
     #[command(arg_required_else_help = true)]
     Delete {
@@ -128,6 +351,144 @@ pub enum Command {
     },
 }
 
+#[derive(Debug, Subcommand)]
+pub enum DraftCommand {
+    /// Invite the members to a policy draft
+    Create {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy name
+        #[arg(required = true)]
+        policy_name: String,
+        /// Policy description
+        #[arg(required = true)]
+        policy_description: String,
+        /// Member public key, in the order of the template keys (repeat for each member)
+        #[arg(long = "member", required = true)]
+        members: Vec<XOnlyPublicKey>,
+        /// Number of signatures required
+        #[arg(long, required = true)]
+        threshold: usize,
+        /// Let the last member spend alone after this number of blocks
+        #[arg(long, value_name = "BLOCKS")]
+        recovery_after: Option<u32>,
+    },
+    /// Join a policy draft with the signer key of the seed
+    Join {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Draft id
+        #[arg(required = true)]
+        draft_id: EventId,
+        /// Use the BIP-48 derivation path
+        #[arg(long)]
+        bip48: bool,
+        /// Account index
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+    },
+    /// Show the members that joined and confirmed a draft (coordinator only)
+    Status {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Draft id
+        #[arg(required = true)]
+        draft_id: EventId,
+    },
+    /// Assemble the descriptor and send it to the members to confirm (coordinator only)
+    Assemble {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Draft id
+        #[arg(required = true)]
+        draft_id: EventId,
+    },
+    /// Confirm the assembled descriptor of a draft
+    Confirm {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Draft id
+        #[arg(required = true)]
+        draft_id: EventId,
+    },
+    /// Save the policy, once every member confirmed it (coordinator only)
+    Finalize {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Draft id
+        #[arg(required = true)]
+        draft_id: EventId,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FrostCommand {
+    /// Invite the members to a FROST key generation session
+    KeyGen {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy name
+        #[arg(required = true)]
+        policy_name: String,
+        /// Policy description
+        #[arg(required = true)]
+        policy_description: String,
+        /// Member public key, own key included, in the order of the indexes (repeat for each member)
+        #[arg(long = "member", required = true)]
+        members: Vec<XOnlyPublicKey>,
+        /// Number of signers required
+        #[arg(long, required = true)]
+        threshold: usize,
+    },
+    /// Send the key generation round to the other members
+    Join {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Session id
+        #[arg(required = true)]
+        session_id: EventId,
+    },
+    /// Verify the rounds of the other members, compare the commitments and save the key package
+    Finalize {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Session id
+        #[arg(required = true)]
+        session_id: EventId,
+    },
+    /// Save the policy of a finalized session (coordinator only)
+    SavePolicy {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Session id
+        #[arg(required = true)]
+        session_id: EventId,
+    },
+    /// Sign a spending proposal with the FROST key path, waiting for the nonces of the other signers
+    Sign {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Proposal id
+        #[arg(required = true)]
+        proposal_id: EventId,
+        /// Public key of a signer of the signing set, own key included (repeat for each signer):
+        /// all the signers must use the same set
+        #[arg(long = "signer", required = true)]
+        signers: Vec<XOnlyPublicKey>,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 pub enum GetCommand {
     /// Get contacts list from nostr
@@ -136,6 +497,21 @@ pub enum GetCommand {
         #[arg(required = true)]
         name: String,
     },
+    /// Get the policy drafts I'm invited to
+    Drafts {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+    },
+    /// Get the signer keys announced by a public key
+    Signers {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Public key
+        #[arg(required = true)]
+        public_key: XOnlyPublicKey,
+    },
     /// Get policies list from nostr
     Policies {
         /// Keychain name
@@ -153,12 +529,39 @@ pub enum GetCommand {
         /// Export descriptor
         #[arg(long)]
         export: bool,
+        /// Fee rate in sat/vB, to estimate the fee of each spending path
+        #[arg(long, conflicts_with = "target_blocks")]
+        fee_rate: Option<f32>,
+        /// Confirmation target in blocks, to estimate the fee of each spending path (default: 6)
+        #[arg(long, conflicts_with = "fee_rate")]
+        target_blocks: Option<usize>,
+    },
+    /// Get the UTXOs of a policy
+    Utxos {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
+    },
+    /// Get when the timelocked spending paths of a policy mature, for each UTXO
+    Maturity {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+        /// Policy id
+        #[arg(required = true)]
+        policy_id: EventId,
     },
     /// Get proposals list from nostr
     Proposals {
         /// Keychain name
         #[arg(required = true)]
         name: String,
+        /// Include the expired proposals and the ones spending UTXOs already spent
+        #[arg(long)]
+        all: bool,
     },
     /// Get proposal by id
     Proposal {
@@ -191,6 +594,18 @@ pub enum DeleteCommand {
         #[arg(required = true)]
         proposal_id: EventId,
     },
+    /// Delete the own approvals of the proposals deleted by other members
+    OrphanEvents {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+    },
+    /// Delete the expired proposals and the ones spending UTXOs already spent
+    StaleProposals {
+        /// Keychain name
+        #[arg(required = true)]
+        name: String,
+    },
 }
 
 #[derive(Debug, Subcommand)]
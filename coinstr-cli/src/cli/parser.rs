@@ -0,0 +1,53 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use coinstr_core::bitcoin::Address;
+use coinstr_core::proposal::Recipient;
+use coinstr_core::Result;
+
+fn parse_recipient_fields<'a, I>(mut fields: I) -> Result<Recipient, String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let address = fields.next().ok_or("Missing address")?.trim();
+    let address = Address::from_str(address).map_err(|e| format!("Invalid address: {e}"))?;
+    let amount = fields.next().ok_or("Missing amount")?.trim();
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|e| format!("Invalid amount: {e}"))?;
+    let recipient = Recipient::new(address, amount);
+    match fields.next().map(|label| label.trim()) {
+        Some(label) if !label.is_empty() => Ok(recipient.label(label)),
+        _ => Ok(recipient),
+    }
+}
+
+/// Parse a `<address>:<amount>[:<label>]` recipient
+pub fn parse_recipient(s: &str) -> Result<Recipient, String> {
+    parse_recipient_fields(s.splitn(3, ':'))
+}
+
+/// Read the recipients from a CSV file with `<address>,<amount>[,<label>]` lines
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_recipients_csv<P>(path: P) -> Result<Vec<Recipient>>
+where
+    P: AsRef<Path>,
+{
+    let content = fs::read_to_string(path)?;
+    let mut recipients = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let recipient = parse_recipient_fields(line.splitn(3, ','))
+            .map_err(|e| format!("Line {}: {e}", index + 1))?;
+        recipients.push(recipient);
+    }
+    Ok(recipients)
+}
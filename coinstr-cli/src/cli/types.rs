@@ -24,6 +24,18 @@ impl From<CliNetwork> for Network {
     }
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliTemplate {
+    /// m-of-n multisig
+    Multisig,
+    /// m-of-n multisig, with a threshold decreasing at each timelock
+    Decaying,
+    /// Owners multisig, with the heirs that can spend after a timelock
+    Inheritance,
+    /// Owner key, with the guardians that can spend with the recovery secret
+    SocialRecovery,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum CliWordCount {
     #[clap(name = "12")]
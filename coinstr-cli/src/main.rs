@@ -6,21 +6,29 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use clap::Parser;
-use cli::{DeleteCommand, GetCommand};
-use coinstr_core::bdk::blockchain::ElectrumBlockchain;
+use cli::{CliTemplate, DeleteCommand, DraftCommand, FrostCommand, GetCommand};
+use coinstr_core::bdk::blockchain::{ElectrumBlockchain, GetHeight};
 use coinstr_core::bdk::electrum_client::Client as ElectrumClient;
+use coinstr_core::bdk::SyncOptions;
 use coinstr_core::bip39::Mnemonic;
 use coinstr_core::bitcoin::Network;
+use coinstr_core::fee::Fee;
+use coinstr_core::policy::{Locktime, Policy, PolicyTemplate};
+use coinstr_core::proposal::Expiry;
+use coinstr_core::signer::SignerPurpose;
 use coinstr_core::util::dir::{get_keychain_file, get_keychains_list};
+use coinstr_core::utxo::CoinControl;
 use coinstr_core::{Coinstr, Keychain, Result};
 
 mod cli;
 mod util;
 
-use self::cli::{io, Cli, Command, SettingCommand};
+use self::cli::{io, parser, Cli, Command, SettingCommand};
 
 const DEFAULT_RELAY: &str = "wss://relay.rip";
 const TIMEOUT: Option<Duration> = Some(Duration::from_secs(300));
+const NONCES_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(600);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -113,33 +121,253 @@ async fn main() -> Result<()> {
             policy_name,
             policy_description,
             policy_descriptor,
+            template,
+            keys,
+            threshold,
+            older,
+            after,
+            heirs,
+            heirs_threshold,
+            hash,
+            tweak_internal_key,
+            musig2,
+            members,
         } => {
+            let policy_descriptor = match template {
+                Some(template) => {
+                    let locktimes: Vec<Locktime> = older
+                        .into_iter()
+                        .map(Locktime::Older)
+                        .chain(after.into_iter().map(Locktime::After))
+                        .collect();
+                    let template = match template {
+                        CliTemplate::Multisig => PolicyTemplate::Multisig { threshold },
+                        CliTemplate::Decaying => PolicyTemplate::Decaying {
+                            threshold,
+                            locktimes,
+                        },
+                        CliTemplate::Inheritance => PolicyTemplate::Inheritance {
+                            threshold,
+                            heirs,
+                            heirs_threshold,
+                            locktime: locktimes
+                                .first()
+                                .copied()
+                                .ok_or("missing timelock (`--older` or `--after`)")?,
+                        },
+                        CliTemplate::SocialRecovery => PolicyTemplate::SocialRecovery {
+                            guardians_threshold: threshold,
+                            hash: hash.ok_or("missing `--hash`")?,
+                        },
+                    };
+                    template.build(&keys)?.to_string()
+                }
+                None => policy_descriptor.ok_or("missing policy descriptor")?,
+            };
             let path = get_keychain_file(keychains, name)?;
             let coinstr = Coinstr::open(path, io::get_password, network)?;
             let client = coinstr.client(relays).await?;
-            let policy_id = client
-                .save_policy(policy_name, policy_description, policy_descriptor)
-                .await?;
+            let policy_id = if musig2 {
+                client
+                    .save_musig2_policy(policy_name, policy_description, policy_descriptor, TIMEOUT)
+                    .await?
+            } else {
+                let policy = if tweak_internal_key {
+                    Policy::from_miniscript_policy_with_tweak(
+                        policy_name,
+                        policy_description,
+                        policy_descriptor,
+                        coinstr_core::util::random_nums_tweak(),
+                    )?
+                } else {
+                    Policy::from_desc_or_policy(policy_name, policy_description, policy_descriptor)?
+                }
+                .with_members(members);
+                client.save_compiled_policy(policy, TIMEOUT).await?
+            };
             println!("Policy saved: {policy_id}");
             Ok(())
         }
         Command::Spend {
             name,
             policy_id,
-            to_address,
-            amount,
             memo,
+            recipients,
+            csv,
+            drain_to,
+            path: branch_index,
+            fee_rate,
+            fee,
+            target_blocks,
+            rbf,
+            must_spend,
+            do_not_spend,
+            only_selected,
+            expiry_height,
+            expiry_time,
         } => {
             let path = get_keychain_file(keychains, name)?;
             let coinstr = Coinstr::open(path, io::get_password, network)?;
             let client = coinstr.client(relays).await?;
+
+            let mut recipients = recipients;
+            if let Some(csv) = csv {
+                recipients.extend(parser::read_recipients_csv(csv)?);
+            }
+
+            let fee = match (fee_rate, fee, target_blocks) {
+                (Some(rate), _, _) => Fee::Rate(rate),
+                (_, Some(fee), _) => Fee::Absolute(fee),
+                (_, _, Some(target)) => Fee::Target(target),
+                _ => Fee::default(),
+            };
+
             let blockchain = ElectrumBlockchain::from(ElectrumClient::new(bitcoin_endpoint)?);
+
+            // Get the selected spending path, with the estimated fee of a typical spend
+            let policy_path = match branch_index {
+                Some(index) => {
+                    let (policy, _shared_keys) =
+                        client.get_policy_by_id(policy_id, TIMEOUT).await?;
+                    let paths = client.analyze_policy(&policy, TIMEOUT).await?;
+                    let path = index
+                        .checked_sub(1)
+                        .and_then(|index| paths.get(index))
+                        .ok_or("Invalid spending path index")?;
+                    let fee_rate = util::get_fee_rate(fee, &blockchain)?;
+                    util::print_spending_path(index, path, fee_rate.as_ref());
+                    Some(path.branch.path.clone())
+                }
+                None => None,
+            };
+
+            let coin_control = CoinControl {
+                must_spend,
+                do_not_spend,
+                manually_selected_only: only_selected,
+            };
+
+            let expiry = match (expiry_height, expiry_time) {
+                (Some(height), _) => Some(Expiry::Height(height)),
+                (_, Some(timestamp)) => Some(Expiry::Time(timestamp)),
+                _ => None,
+            };
+
             let proposal_id = client
-                .spend(policy_id, to_address, amount, memo, blockchain, TIMEOUT)
+                .spend(
+                    policy_id,
+                    recipients,
+                    drain_to,
+                    memo,
+                    fee,
+                    rbf,
+                    coin_control,
+                    policy_path,
+                    expiry,
+                    blockchain,
+                    TIMEOUT,
+                )
                 .await?;
             println!("Spending proposal {proposal_id} sent");
             Ok(())
         }
+        Command::BumpFee {
+            name,
+            policy_id,
+            txid,
+            memo,
+            path: branch_index,
+            fee_rate,
+            fee,
+            target_blocks,
+        } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+
+            // Get the selected spending path
+            let policy_path = match branch_index {
+                Some(index) => {
+                    let (policy, _shared_keys) =
+                        client.get_policy_by_id(policy_id, TIMEOUT).await?;
+                    let branches = client.policy_branches(&policy)?;
+                    let branch = index
+                        .checked_sub(1)
+                        .and_then(|index| branches.get(index))
+                        .ok_or("Invalid spending path index")?;
+                    Some(branch.path.clone())
+                }
+                None => None,
+            };
+
+            let fee = match (fee_rate, fee, target_blocks) {
+                (Some(rate), _, _) => Fee::Rate(rate),
+                (_, Some(fee), _) => Fee::Absolute(fee),
+                (_, _, Some(target)) => Fee::Target(target),
+                _ => Fee::default(),
+            };
+
+            let blockchain = ElectrumBlockchain::from(ElectrumClient::new(bitcoin_endpoint)?);
+            let proposal_id = client
+                .bump_fee(policy_id, txid, memo, fee, policy_path, blockchain, TIMEOUT)
+                .await?;
+            println!("Fee bump proposal {proposal_id} sent");
+            Ok(())
+        }
+        Command::Heartbeat {
+            name,
+            policy_id,
+            threshold,
+            fee_rate,
+            target_blocks,
+            do_not_spend,
+            watch,
+        } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+
+            let fee = match (fee_rate, target_blocks) {
+                (Some(rate), _) => Fee::Rate(rate),
+                (_, Some(target)) => Fee::Target(target),
+                _ => Fee::default(),
+            };
+
+            loop {
+                let blockchain = ElectrumBlockchain::from(ElectrumClient::new(bitcoin_endpoint)?);
+                let heartbeat = client
+                    .heartbeat(
+                        policy_id,
+                        threshold,
+                        fee,
+                        do_not_spend.clone(),
+                        blockchain,
+                        TIMEOUT,
+                    )
+                    .await?;
+                util::print_heartbeat(&heartbeat, threshold);
+
+                if !watch {
+                    break Ok(());
+                }
+                tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            }
+        }
+        Command::Label {
+            name,
+            policy_id,
+            outpoint,
+            label,
+        } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+            let event_id = client
+                .save_utxo_label(policy_id, outpoint, label, TIMEOUT)
+                .await?;
+            println!("UTXO {outpoint} labeled: {event_id}");
+            Ok(())
+        }
         Command::Approve { name, proposal_id } => {
             let path = get_keychain_file(keychains, name)?;
             let coinstr = Coinstr::open(path, io::get_password, network)?;
@@ -148,6 +376,39 @@ async fn main() -> Result<()> {
             println!("Spending proposal {proposal_id} approved: {event_id}");
             Ok(())
         }
+        Command::Musig2Sign { name, proposal_id } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+            let event_id = client.musig2_commit_nonces(proposal_id, TIMEOUT).await?;
+            println!("MuSig2 nonces committed: {event_id}");
+            // Wait here for the other members
+            loop {
+                match client.musig2_sign(proposal_id, TIMEOUT).await {
+                    Ok(event_id) => {
+                        println!("Spending proposal {proposal_id} signed with MuSig2: {event_id}");
+                        break Ok(());
+                    }
+                    Err(coinstr_core::client::Error::MissingMuSig2Nonces(missing)) => {
+                        println!("Waiting for the nonces of {} members...", missing.len());
+                        tokio::time::sleep(NONCES_POLL_INTERVAL).await;
+                    }
+                    Err(e) => break Err(e.into()),
+                }
+            }
+        }
+        Command::Reject {
+            name,
+            proposal_id,
+            reason,
+        } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+            let event_id = client.reject(proposal_id, reason, TIMEOUT).await?;
+            println!("Spending proposal {proposal_id} rejected: {event_id}");
+            Ok(())
+        }
         Command::Broadcast { name, proposal_id } => {
             let path = get_keychain_file(keychains, name)?;
             let coinstr = Coinstr::open(path, io::get_password, network)?;
@@ -177,6 +438,22 @@ async fn main() -> Result<()> {
                 util::print_contacts(contacts);
                 Ok(())
             }
+            GetCommand::Drafts { name } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let drafts = client.get_policy_drafts(TIMEOUT).await?;
+                util::print_drafts(drafts);
+                Ok(())
+            }
+            GetCommand::Signers { name, public_key } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let signers = client.get_signer_keys(public_key, TIMEOUT).await?;
+                util::print_signers(signers);
+                Ok(())
+            }
             GetCommand::Policies { name } => {
                 let path = get_keychain_file(keychains, name)?;
                 let coinstr = Coinstr::open(path, io::get_password, network)?;
@@ -189,6 +466,8 @@ async fn main() -> Result<()> {
                 name,
                 policy_id,
                 export,
+                fee_rate,
+                target_blocks,
             } => {
                 let path = get_keychain_file(keychains, name)?;
                 let coinstr = Coinstr::open(path, io::get_password, network)?;
@@ -205,14 +484,58 @@ async fn main() -> Result<()> {
                     println!("\n{}\n", policy.descriptor);
                     Ok(())
                 } else {
-                    util::print_policy(policy, policy_id, wallet, bitcoin_endpoint)
+                    let paths = client.analyze_policy(&policy, TIMEOUT).await?;
+                    let fee = match (fee_rate, target_blocks) {
+                        (Some(rate), _) => Fee::Rate(rate),
+                        (_, Some(target)) => Fee::Target(target),
+                        _ => Fee::default(),
+                    };
+                    util::print_policy(policy, policy_id, wallet, paths, fee, bitcoin_endpoint)
                 }
             }
-            GetCommand::Proposals { name } => {
+            GetCommand::Utxos { name, policy_id } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+
+                // Get policy
+                let (policy, _shared_keys) = client.get_policy_by_id(policy_id, TIMEOUT).await?;
+
+                // Open wallet
+                let wallet = client.wallet(policy.descriptor.to_string())?;
+
+                let labels = client.get_utxo_labels(policy_id, TIMEOUT).await?;
+                util::print_utxos(wallet, labels, bitcoin_endpoint)
+            }
+            GetCommand::Maturity { name, policy_id } => {
                 let path = get_keychain_file(keychains, name)?;
                 let coinstr = Coinstr::open(path, io::get_password, network)?;
                 let client = coinstr.client(relays).await?;
-                let proposals = client.get_proposals(TIMEOUT).await?;
+
+                // Get policy
+                let (policy, _shared_keys) = client.get_policy_by_id(policy_id, TIMEOUT).await?;
+
+                // Sync wallet
+                let wallet = client.wallet(policy.descriptor.to_string())?;
+                let blockchain = ElectrumBlockchain::from(ElectrumClient::new(bitcoin_endpoint)?);
+                wallet.sync(&blockchain, SyncOptions::default())?;
+                let height: u32 = blockchain.get_height()?;
+
+                let utxos = client.get_maturity(&policy, &wallet)?;
+                util::print_maturity(utxos, height);
+                Ok(())
+            }
+            GetCommand::Proposals { name, all } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let proposals = if all {
+                    client.get_proposals(TIMEOUT).await?
+                } else {
+                    let blockchain =
+                        ElectrumBlockchain::from(ElectrumClient::new(bitcoin_endpoint)?);
+                    client.get_active_proposals(blockchain, TIMEOUT).await?
+                };
                 util::print_proposals(proposals);
                 Ok(())
             }
@@ -222,10 +545,233 @@ async fn main() -> Result<()> {
                 let client = coinstr.client(relays).await?;
                 let (proposal, policy_id, _shared_keys) =
                     client.get_proposal_by_id(proposal_id, TIMEOUT).await?;
-                util::print_proposal(proposal_id, proposal, policy_id);
+                let status = client.proposal_status(proposal_id, TIMEOUT).await?;
+                let rejections = client
+                    .get_rejections_by_proposal_id(proposal_id, TIMEOUT)
+                    .await?;
+                let contacts = client.get_contacts(TIMEOUT).await?;
+                util::print_proposal(
+                    proposal_id,
+                    proposal,
+                    policy_id,
+                    status,
+                    rejections,
+                    contacts,
+                );
                 Ok(())
             }
         },
+        Command::Migrate { name, policy_id } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+            let new_policy_id = client.migrate_policy(policy_id, TIMEOUT).await?;
+            if new_policy_id == policy_id {
+                println!("Policy {policy_id} already encrypted with NIP-44");
+            } else {
+                println!("Policy {policy_id} migrated: {new_policy_id}");
+            }
+            Ok(())
+        }
+        Command::RotateKey {
+            name,
+            policy_id,
+            excluded,
+        } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+            client
+                .rotate_shared_key(policy_id, excluded, TIMEOUT)
+                .await?;
+            println!("Shared key of policy {policy_id} rotated");
+            Ok(())
+        }
+        Command::AnnounceSigner {
+            name,
+            bip48,
+            account,
+            recipients,
+        } => {
+            let path = get_keychain_file(keychains, name)?;
+            let coinstr = Coinstr::open(path, io::get_password, network)?;
+            let client = coinstr.client(relays).await?;
+            let purpose = if bip48 {
+                SignerPurpose::Bip48
+            } else {
+                SignerPurpose::Bip86
+            };
+            let recipients = if recipients.is_empty() {
+                client.get_contacts(TIMEOUT).await?.into_keys().collect()
+            } else {
+                recipients
+            };
+            let (signer, event_id) = client.announce_signer(purpose, account, recipients).await?;
+            println!("Signer {} announced: {event_id}", signer.descriptor_key());
+            Ok(())
+        }
+        Command::Draft { command } => match command {
+            DraftCommand::Create {
+                name,
+                policy_name,
+                policy_description,
+                members,
+                threshold,
+                recovery_after,
+            } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let template = match recovery_after {
+                    Some(blocks) => PolicyTemplate::Recovery {
+                        threshold,
+                        locktime: Locktime::Older(blocks),
+                    },
+                    None => PolicyTemplate::Multisig { threshold },
+                };
+                let draft_id = client
+                    .create_policy_draft(policy_name, policy_description, template, members)
+                    .await?;
+                println!("Policy draft created: {draft_id}");
+                Ok(())
+            }
+            DraftCommand::Join {
+                name,
+                draft_id,
+                bip48,
+                account,
+            } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let purpose = if bip48 {
+                    SignerPurpose::Bip48
+                } else {
+                    SignerPurpose::Bip86
+                };
+                client
+                    .join_policy_draft(draft_id, purpose, account, TIMEOUT)
+                    .await?;
+                println!("Joined policy draft {draft_id}");
+                Ok(())
+            }
+            DraftCommand::Status { name, draft_id } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let status = client.get_policy_draft_status(draft_id, TIMEOUT).await?;
+                util::print_draft_status(draft_id, status);
+                Ok(())
+            }
+            DraftCommand::Assemble { name, draft_id } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let policy = client.assemble_policy_draft(draft_id, TIMEOUT).await?;
+                println!("Descriptor sent to the members: {}", policy.descriptor);
+                Ok(())
+            }
+            DraftCommand::Confirm { name, draft_id } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                client.confirm_policy_draft(draft_id, TIMEOUT).await?;
+                println!("Policy draft {draft_id} confirmed");
+                Ok(())
+            }
+            DraftCommand::Finalize { name, draft_id } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let policy_id = client.finalize_policy_draft(draft_id, TIMEOUT).await?;
+                println!("Policy saved: {policy_id}");
+                Ok(())
+            }
+        },
+        Command::Frost { command } => {
+            match command {
+                FrostCommand::KeyGen {
+                    name,
+                    policy_name,
+                    policy_description,
+                    members,
+                    threshold,
+                } => {
+                    let path = get_keychain_file(keychains, name)?;
+                    let coinstr = Coinstr::open(path, io::get_password, network)?;
+                    let client = coinstr.client(relays).await?;
+                    let session_id = client
+                        .create_frost_keygen(policy_name, policy_description, threshold, members)
+                        .await?;
+                    println!("FROST key generation session created: {session_id}");
+                    Ok(())
+                }
+                FrostCommand::Join { name, session_id } => {
+                    let path = get_keychain_file(keychains, name)?;
+                    let coinstr = Coinstr::open(path, io::get_password, network)?;
+                    let client = coinstr.client(relays).await?;
+                    client.frost_keygen_join(session_id, TIMEOUT).await?;
+                    println!("Key generation round sent for session {session_id}");
+                    Ok(())
+                }
+                FrostCommand::Finalize { name, session_id } => {
+                    let path = get_keychain_file(keychains, name)?;
+                    let coinstr = Coinstr::open(path, io::get_password, network)?;
+                    let client = coinstr.client(relays).await?;
+                    // Wait here for the echoes of the other members
+                    loop {
+                        match client.finalize_frost_keygen(session_id, TIMEOUT).await {
+                            Ok(group) => {
+                                println!("FROST group key: {}", group.x_only_public_key());
+                                break Ok(());
+                            }
+                            Err(coinstr_core::client::Error::PendingFrostEchoes(pending)) => {
+                                println!("Waiting for the echoes of {} members...", pending.len());
+                                tokio::time::sleep(NONCES_POLL_INTERVAL).await;
+                            }
+                            Err(e) => break Err(e.into()),
+                        }
+                    }
+                }
+                FrostCommand::SavePolicy { name, session_id } => {
+                    let path = get_keychain_file(keychains, name)?;
+                    let coinstr = Coinstr::open(path, io::get_password, network)?;
+                    let client = coinstr.client(relays).await?;
+                    let policy_id = client.save_frost_policy(session_id, TIMEOUT).await?;
+                    println!("Policy saved: {policy_id}");
+                    Ok(())
+                }
+                FrostCommand::Sign {
+                    name,
+                    proposal_id,
+                    signers,
+                } => {
+                    let path = get_keychain_file(keychains, name)?;
+                    let coinstr = Coinstr::open(path, io::get_password, network)?;
+                    let client = coinstr.client(relays).await?;
+                    let event_id = client
+                        .frost_commit_nonces(proposal_id, signers, TIMEOUT)
+                        .await?;
+                    println!("FROST nonces committed: {event_id}");
+                    // Wait here for the other signers
+                    loop {
+                        match client.frost_sign(proposal_id, TIMEOUT).await {
+                            Ok(event_id) => {
+                                println!(
+                                    "Spending proposal {proposal_id} signed with FROST: {event_id}"
+                                );
+                                break Ok(());
+                            }
+                            Err(coinstr_core::client::Error::MissingFrostNonces(missing)) => {
+                                println!("Waiting for the nonces of {} signers...", missing.len());
+                                tokio::time::sleep(NONCES_POLL_INTERVAL).await;
+                            }
+                            Err(e) => break Err(e.into()),
+                        }
+                    }
+                }
+            }
+        }
         Command::Delete { command } => match command {
             DeleteCommand::Policy { name, policy_id } => {
                 let path = get_keychain_file(keychains, name)?;
@@ -239,6 +785,23 @@ async fn main() -> Result<()> {
                 let client = coinstr.client(relays).await?;
                 Ok(client.delete_proposal_by_id(proposal_id, TIMEOUT).await?)
             }
+            DeleteCommand::OrphanEvents { name } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let deleted = client.delete_orphan_events(TIMEOUT).await?;
+                println!("{} orphan events deleted", deleted.len());
+                Ok(())
+            }
+            DeleteCommand::StaleProposals { name } => {
+                let path = get_keychain_file(keychains, name)?;
+                let coinstr = Coinstr::open(path, io::get_password, network)?;
+                let client = coinstr.client(relays).await?;
+                let blockchain = ElectrumBlockchain::from(ElectrumClient::new(bitcoin_endpoint)?);
+                let deleted = client.delete_stale_proposals(blockchain, TIMEOUT).await?;
+                println!("{} stale proposals deleted", deleted.len());
+                Ok(())
+            }
         },
         Command::Setting { command } => match command {
             SettingCommand::Rename { name, new_name } => {
@@ -3,28 +3,29 @@
 
 use std::collections::HashMap;
 
-use coinstr_core::bdk::blockchain::ElectrumBlockchain;
+use coinstr_core::bdk::blockchain::{Blockchain, ElectrumBlockchain, GetHeight};
 use coinstr_core::bdk::database::MemoryDatabase;
-use coinstr_core::bdk::descriptor::policy::{PkOrF, SatisfiableItem};
 use coinstr_core::bdk::electrum_client::Client as ElectrumClient;
 use coinstr_core::bdk::wallet::AddressIndex;
-use coinstr_core::bdk::{KeychainKind, SyncOptions, Wallet};
+use coinstr_core::bdk::{FeeRate, SyncOptions, Wallet};
 use coinstr_core::bitcoin::util::bip32::ExtendedPubKey;
-use coinstr_core::bitcoin::Network;
+use coinstr_core::bitcoin::{Network, OutPoint};
+use coinstr_core::draft::{DraftStatus, PolicyDraft};
+use coinstr_core::fee::Fee;
 use coinstr_core::nostr_sdk::prelude::{ToBech32, XOnlyPublicKey};
-use coinstr_core::nostr_sdk::{EventId, Metadata, SECP256K1};
-use coinstr_core::policy::Policy;
-use coinstr_core::proposal::SpendingProposal;
+use coinstr_core::nostr_sdk::{EventId, Metadata, Timestamp, SECP256K1};
+use coinstr_core::policy::{Policy, SpendingPath, UtxoMaturity};
+use coinstr_core::proposal::{ApprovalStatus, Rejection, SpendingProposal};
+use coinstr_core::signer::SignerKey;
 use coinstr_core::types::Purpose;
 use coinstr_core::util::bip::bip32::Bip32RootKey;
 use coinstr_core::util::format;
+use coinstr_core::utxo::Heartbeat;
 use coinstr_core::{Keychain, Result};
-use owo_colors::colors::css::Lime;
-use owo_colors::colors::xterm::{BlazeOrange, BrightElectricViolet, Pistachio};
-use owo_colors::colors::{BrightCyan, Magenta};
+use owo_colors::colors::xterm::BlazeOrange;
+use owo_colors::colors::BrightCyan;
 use owo_colors::OwoColorize;
 use prettytable::{row, Table};
-use termtree::Tree;
 
 pub fn print_secrets(keychain: Keychain, network: Network) -> Result<()> {
     let mnemonic = keychain.seed.mnemonic();
@@ -111,10 +112,76 @@ pub fn print_contacts(contacts: HashMap<XOnlyPublicKey, Metadata>) {
     table.printstd();
 }
 
+pub fn print_signers(signers: Vec<SignerKey>) {
+    let mut table = Table::new();
+
+    table.set_titles(row!["#", "Fingerprint", "Descriptor key"]);
+
+    for (index, signer) in signers.into_iter().enumerate() {
+        table.add_row(row![index + 1, signer.fingerprint, signer.descriptor_key()]);
+    }
+
+    table.printstd();
+}
+
+pub fn print_drafts(drafts: HashMap<EventId, (PolicyDraft, XOnlyPublicKey)>) {
+    let mut table = Table::new();
+
+    table.set_titles(row![
+        "#",
+        "ID",
+        "Name",
+        "Coordinator",
+        "Members",
+        "Descriptor"
+    ]);
+
+    for (index, (draft_id, (draft, coordinator))) in drafts.into_iter().enumerate() {
+        table.add_row(row![
+            index + 1,
+            draft_id,
+            draft.name,
+            coordinator,
+            draft.members.len(),
+            draft.descriptor.unwrap_or_else(|| String::from("-"))
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub fn print_draft_status(draft_id: EventId, status: DraftStatus) {
+    println!("{}", "\nPolicy draft".fg::<BrightCyan>().underline());
+    println!("- ID: {draft_id}");
+    println!("- Name: {}", &status.draft.name);
+    println!("- Description: {}", status.draft.description);
+    if let Some(descriptor) = &status.draft.descriptor {
+        println!("- Descriptor: {descriptor}");
+    }
+
+    let mut table = Table::new();
+    table.set_titles(row!["#", "Member", "Signer key", "Confirmed"]);
+    for (index, member) in status.draft.members.iter().enumerate() {
+        table.add_row(row![
+            index + 1,
+            member,
+            status
+                .signers
+                .get(member)
+                .map(|s| s.descriptor_key().to_string())
+                .unwrap_or_else(|| String::from("-")),
+            status.confirmed_by.contains(member)
+        ]);
+    }
+    table.printstd();
+}
+
 pub fn print_policy<S>(
     policy: Policy,
     policy_id: EventId,
     wallet: Wallet<MemoryDatabase>,
+    paths: Vec<SpendingPath>,
+    fee: Fee,
     endpoint: S,
 ) -> Result<()>
 where
@@ -124,14 +191,34 @@ where
     println!("- ID: {policy_id}");
     println!("- Name: {}", &policy.name);
     println!("- Description: {}", policy.description);
+    if policy.has_unspendable_internal_key() {
+        println!("- Internal key: provably unspendable (NUMS)");
+    } else if policy.has_musig2_internal_key() {
+        println!(
+            "- Internal key: MuSig2 aggregate of {} keys",
+            policy.musig2_keys.len()
+        );
+    } else if let (true, Some(group)) = (policy.has_frost_internal_key(), &policy.frost) {
+        println!(
+            "- Internal key: FROST group key ({}-of-{})",
+            group.threshold,
+            policy.members.len()
+        );
+    } else {
+        println!("- Internal key: not provably unspendable");
+    }
 
-    let spending_policy = wallet.policies(KeychainKind::External)?.unwrap();
-
-    let mut tree: Tree<String> = Tree::new("- Descriptor".to_string());
-    tree.push(add_node(&spending_policy.item));
-    println!("{tree}");
+    println!();
 
     let blockchain = ElectrumBlockchain::from(ElectrumClient::new(&endpoint.into())?);
+    let fee_rate = get_fee_rate(fee, &blockchain)?;
+
+    println!("{}", "Spending paths".fg::<BlazeOrange>().underline());
+    for (index, path) in paths.iter().enumerate() {
+        print_spending_path(index + 1, path, fee_rate.as_ref());
+    }
+    println!();
+
     wallet.sync(&blockchain, SyncOptions::default())?;
 
     let balance = wallet.get_balance()?;
@@ -156,7 +243,7 @@ where
     println!(
         "\n{}: {}\n",
         "Deposit address".fg::<BlazeOrange>().underline(),
-        wallet.get_address(AddressIndex::New)?
+        wallet.get_address(AddressIndex::LastUnused)?
     );
 
     let mut txs = wallet.list_transactions(false)?;
@@ -176,89 +263,150 @@ where
     Ok(())
 }
 
-fn display_key(key: &PkOrF) -> String {
-    match key {
-        PkOrF::Pubkey(pk) => format!("<pk:{pk}>"),
-        PkOrF::XOnlyPubkey(pk) => format!("<xonly-pk:{pk}>"),
-        PkOrF::Fingerprint(f) => format!("<fingerprint:{f}>"),
-    }
+/// Fee rate of `fee`, if not an absolute fee
+pub fn get_fee_rate(fee: Fee, blockchain: &ElectrumBlockchain) -> Result<Option<FeeRate>> {
+    Ok(match fee {
+        Fee::Rate(rate) => Some(FeeRate::from_sat_per_vb(rate)),
+        Fee::Absolute(..) => None,
+        Fee::Target(target) => Some(blockchain.estimate_fee(target)?),
+    })
 }
 
-fn add_node(item: &SatisfiableItem) -> Tree<String> {
-    let mut si_tree: Tree<String> = Tree::new(format!(
-        "{}{}",
-        "id -> ".fg::<Pistachio>(),
-        item.id().fg::<Pistachio>()
-    ));
-
-    match &item {
-        SatisfiableItem::EcdsaSignature(key) => {
-            si_tree.push(format!(
-                "🗝️ {} {}",
-                "ECDSA Sig of ".fg::<BrightElectricViolet>(),
-                display_key(key)
-            ));
-        }
-        SatisfiableItem::SchnorrSignature(key) => {
-            si_tree.push(format!(
-                "🔑 {} {}",
-                "Schnorr Sig of ".fg::<Pistachio>(),
-                display_key(key)
-            ));
-        }
-        SatisfiableItem::Sha256Preimage { hash } => {
-            si_tree.push(format!("SHA256 Preimage of {hash}"));
-        }
-        SatisfiableItem::Hash256Preimage { hash } => {
-            si_tree.push(format!("Double-SHA256 Preimage of {hash}"));
-        }
-        SatisfiableItem::Ripemd160Preimage { hash } => {
-            si_tree.push(format!("RIPEMD160 Preimage of {hash}"));
-        }
-        SatisfiableItem::Hash160Preimage { hash } => {
-            si_tree.push(format!("Double-RIPEMD160 Preimage of {hash}"));
-        }
-        SatisfiableItem::AbsoluteTimelock { value } => {
-            si_tree.push(format!(
-                "⏰ {} {value}",
-                "Absolute Timelock of ".fg::<Lime>()
-            ));
-        }
-        SatisfiableItem::RelativeTimelock { value } => {
-            si_tree.push(format!(
-                "⏳ {} {value}",
-                "Relative Timelock of".fg::<Lime>(),
-            ));
+pub fn print_spending_path(index: usize, path: &SpendingPath, fee_rate: Option<&FeeRate>) {
+    println!("{index}. {path}");
+    if let Some(weight) = path.satisfaction_weight {
+        println!(
+            "   Satisfaction weight: {} WU (~{} vB per input)",
+            format::number(weight as u64),
+            format::number((weight as u64 + 3) / 4)
+        );
+    }
+    match (path.estimated_vsize(), fee_rate) {
+        (Some(vsize), Some(fee_rate)) => println!(
+            "   Estimated fee: {} sat ({vsize} vB at {:.1} sat/vB, with 1 input and 2 outputs)",
+            format::number(fee_rate.fee_vb(vsize)),
+            fee_rate.as_sat_per_vb()
+        ),
+        (Some(vsize), None) => {
+            println!("   Estimated size: {vsize} vB (with 1 input and 2 outputs)")
         }
-        SatisfiableItem::Multisig { keys, threshold } => {
-            // si_tree.push(format!("🎚️ {} of {} MultiSig:", threshold, keys.len()));
-            let mut child_tree: Tree<String> = Tree::new(format!(
-                "🤝 {}{} of {}",
-                "MultiSig  :  ".fg::<BrightCyan>(),
-                threshold,
-                keys.len()
-            ));
-
-            keys.iter().for_each(|x| {
-                child_tree.push(format!("🔑 {}", display_key(x).fg::<Magenta>()));
-            });
-            si_tree.push(child_tree);
+        _ => (),
+    }
+}
+
+pub fn print_utxos<S>(
+    wallet: Wallet<MemoryDatabase>,
+    labels: HashMap<OutPoint, String>,
+    endpoint: S,
+) -> Result<()>
+where
+    S: Into<String>,
+{
+    let blockchain = ElectrumBlockchain::from(ElectrumClient::new(&endpoint.into())?);
+    wallet.sync(&blockchain, SyncOptions::default())?;
+    let height: u32 = blockchain.get_height()?;
+
+    let mut table = Table::new();
+
+    table.set_titles(row!["#", "UTXO", "Value", "Confirmations", "Label"]);
+
+    for (index, utxo) in wallet.list_unspent()?.into_iter().enumerate() {
+        let confirmations: u32 = wallet
+            .get_tx(&utxo.outpoint.txid, false)?
+            .and_then(|tx| tx.confirmation_time)
+            .map(|confirmation| height.saturating_sub(confirmation.height) + 1)
+            .unwrap_or_default();
+        table.add_row(row![
+            index + 1,
+            utxo.outpoint,
+            format!("{} sats", format::number(utxo.txout.value)),
+            format::number(confirmations as u64),
+            labels.get(&utxo.outpoint).cloned().unwrap_or_default()
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+pub fn print_maturity(utxos: Vec<UtxoMaturity>, height: u32) {
+    let now: u64 = Timestamp::now().as_u64();
+
+    let mut table = Table::new();
+
+    table.set_titles(row![
+        "UTXO",
+        "Value",
+        "Spending path",
+        "Matures at",
+        "Status"
+    ]);
+
+    for utxo in utxos.into_iter() {
+        for (branch, maturity) in utxo.branches.into_iter() {
+            let (matures_at, status) = match maturity {
+                Some(maturity) => {
+                    let status = if maturity.is_mature(height, now) {
+                        String::from("mature")
+                    } else {
+                        match maturity.height {
+                            Some(h) if h > height => format!("{} blocks left", h - height),
+                            _ => String::from("waiting"),
+                        }
+                    };
+                    (maturity.to_string(), status)
+                }
+                None => (String::from("-"), String::from("unconfirmed")),
+            };
+            table.add_row(row![
+                utxo.outpoint,
+                format!("{} sats", format::number(utxo.value)),
+                branch,
+                matures_at,
+                status
+            ]);
         }
-        SatisfiableItem::Thresh { items, threshold } => {
-            let mut child_tree: Tree<String> = Tree::new(format!(
-                "👑{}{} of {} ",
-                " Threshold Condition   : ".fg::<BrightCyan>(),
-                threshold,
-                items.len()
-            ));
-
-            items.iter().for_each(|x| {
-                child_tree.push(add_node(&x.item));
-            });
-            si_tree.push(child_tree);
+    }
+
+    table.printstd();
+}
+
+pub fn print_heartbeat(heartbeat: &Heartbeat, threshold: u32) {
+    if heartbeat.blocks_left.is_empty() {
+        println!("No confirmed UTXOs with relative timelocks");
+    } else {
+        let mut table = Table::new();
+
+        table.set_titles(row!["UTXO", "Blocks left"]);
+
+        let mut utxos: Vec<(&OutPoint, &u32)> = heartbeat.blocks_left.iter().collect();
+        utxos.sort_by_key(|(_, blocks_left)| **blocks_left);
+        for (outpoint, blocks_left) in utxos.into_iter() {
+            let blocks_left = if *blocks_left < threshold {
+                format::number(*blocks_left as u64).red().to_string()
+            } else {
+                format::number(*blocks_left as u64)
+            };
+            table.add_row(row![outpoint, blocks_left]);
         }
+
+        table.printstd();
     }
-    si_tree
+
+    if let Some(proposal_id) = heartbeat.refresh {
+        println!("Refresh proposal {proposal_id} sent");
+    }
+}
+
+fn display_contact(
+    public_key: &XOnlyPublicKey,
+    contacts: &HashMap<XOnlyPublicKey, Metadata>,
+) -> String {
+    contacts
+        .get(public_key)
+        .and_then(|metadata| metadata.display_name.clone().or(metadata.name.clone()))
+        .unwrap_or_else(|| public_key.to_string())
 }
 
 pub fn print_policies(policies: Vec<(EventId, Policy)>) {
@@ -273,29 +421,98 @@ pub fn print_policies(policies: Vec<(EventId, Policy)>) {
     table.printstd();
 }
 
-pub fn print_proposal(proposal_id: EventId, proposal: SpendingProposal, policy_id: EventId) {
+pub fn print_proposal(
+    proposal_id: EventId,
+    proposal: SpendingProposal,
+    policy_id: EventId,
+    status: ApprovalStatus,
+    rejections: Vec<Rejection>,
+    contacts: HashMap<XOnlyPublicKey, Metadata>,
+) {
     println!();
     println!("- Proposal id: {proposal_id}");
     println!("- Policy id: {policy_id}");
     println!("- Memo: {}", proposal.memo);
-    println!("- To address: {}", proposal.to_address);
-    println!("- Amount: {}", proposal.amount);
+    if let Some(fee_bump) = proposal.fee_bump {
+        println!("- Fee bump: {fee_bump}");
+    }
+    if let Some(expiry) = proposal.expiry {
+        println!("- Expires {expiry}");
+    }
+    println!("- Recipients:");
+    for (index, recipient) in proposal.recipients.iter().enumerate() {
+        print!(
+            "  {}. {} sats to {}",
+            index + 1,
+            format::number(recipient.amount),
+            recipient.address
+        );
+        match &recipient.label {
+            Some(label) => println!(" ({label})"),
+            None => println!(),
+        }
+    }
+    println!("- Amount: {} sats", format::number(proposal.amount()));
+    match proposal.fee {
+        Some(fee) => println!("- Fee: {} sats", format::number(fee)),
+        None => println!("- Fee: unknown"),
+    }
+    match &status.branch {
+        Some(branch) => println!("- Spending path: {branch}"),
+        None => println!("- Spending path: unknown"),
+    }
+    print!("- Approvals: {status}");
+    if !status.is_satisfied && !status.missing.is_empty() {
+        let missing: Vec<String> = status
+            .missing
+            .iter()
+            .map(|public_key| display_contact(public_key, &contacts))
+            .collect();
+        print!(", missing: {}", missing.join(", "));
+    }
+    println!();
+    if !rejections.is_empty() {
+        println!("- Rejections:");
+        for rejection in rejections.iter() {
+            println!(
+                "  - {}: {}",
+                display_contact(&rejection.author, &contacts),
+                rejection.reason
+            );
+        }
+    }
     println!();
 }
 
 pub fn print_proposals(proposals: Vec<(EventId, SpendingProposal, EventId)>) {
     let mut table = Table::new();
 
-    table.set_titles(row!["#", "ID", "Policy ID", "Memo", "Address", "Amount"]);
+    table.set_titles(row![
+        "#",
+        "ID",
+        "Policy ID",
+        "Memo",
+        "Recipients",
+        "Amount",
+        "Fee"
+    ]);
 
     for (index, (proposal_id, proposal, policy_id)) in proposals.into_iter().enumerate() {
+        let recipients: String = match proposal.recipients.as_slice() {
+            [recipient] => recipient.address.to_string(),
+            recipients => format!("{} recipients", recipients.len()),
+        };
         table.add_row(row![
             index + 1,
             proposal_id,
             policy_id.to_hex()[..9],
             proposal.memo,
-            proposal.to_address,
-            format!("{} sats", format::number(proposal.amount))
+            recipients,
+            format!("{} sats", format::number(proposal.amount())),
+            proposal
+                .fee
+                .map(|fee| format!("{} sats", format::number(fee)))
+                .unwrap_or_default()
         ]);
     }
 
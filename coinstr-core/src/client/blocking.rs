@@ -7,15 +7,22 @@ use std::collections::HashMap;
 use std::time::Duration;
 
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
-use bdk::bitcoin::{Address, Network, Txid, XOnlyPublicKey};
+use bdk::bitcoin::util::bip32::ExtendedPrivKey;
+use bdk::bitcoin::{Address, Network, OutPoint, Txid, XOnlyPublicKey};
 use bdk::blockchain::Blockchain;
 use bdk::database::MemoryDatabase;
 use bdk::Wallet;
 use nostr_sdk::block_on;
 use nostr_sdk::{EventId, Keys, Metadata, Result};
 
-use crate::policy::Policy;
-use crate::proposal::SpendingProposal;
+use crate::draft::{DraftStatus, PolicyDraft};
+use crate::fee::Fee;
+use crate::frost::{FrostGroup, FrostKeyGen};
+use crate::policy::{Policy, PolicyBranch, PolicyPath, PolicyTemplate, SpendingPath, UtxoMaturity};
+use crate::proposal::{Approval, ApprovalStatus, Expiry, Recipient, Rejection, SpendingProposal};
+use crate::secrets::SecretStore;
+use crate::signer::{SignerKey, SignerPurpose};
+use crate::utxo::{CoinControl, Heartbeat};
 
 /// Blocking Coinstr Client
 #[derive(Debug, Clone)]
@@ -32,6 +39,21 @@ impl CoinstrClient {
         })
     }
 
+    pub fn with_signer(mut self, root_key: ExtendedPrivKey) -> Self {
+        self.client = self.client.with_signer(root_key);
+        self
+    }
+
+    pub fn with_secret_store(mut self, secrets: SecretStore) -> Self {
+        self.client = self.client.with_secret_store(secrets);
+        self
+    }
+
+    pub fn with_max_fee_rate(mut self, max_fee_rate: f32) -> Self {
+        self.client = self.client.with_max_fee_rate(max_fee_rate);
+        self
+    }
+
     pub fn wallet<S>(&self, descriptor: S) -> Result<Wallet<MemoryDatabase>>
     where
         S: Into<String>,
@@ -39,6 +61,26 @@ impl CoinstrClient {
         self.client.wallet(descriptor)
     }
 
+    pub fn policy_branches(&self, policy: &Policy) -> Result<Vec<PolicyBranch>> {
+        self.client.policy_branches(policy)
+    }
+
+    pub fn analyze_policy(
+        &self,
+        policy: &Policy,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<SpendingPath>> {
+        block_on(async { self.client.analyze_policy(policy, timeout).await })
+    }
+
+    pub fn get_maturity(
+        &self,
+        policy: &Policy,
+        wallet: &Wallet<MemoryDatabase>,
+    ) -> Result<Vec<UtxoMaturity>> {
+        self.client.get_maturity(policy, wallet)
+    }
+
     pub fn get_contacts(
         &self,
         timeout: Option<Duration>,
@@ -46,6 +88,27 @@ impl CoinstrClient {
         block_on(async { self.client.get_contacts(timeout).await })
     }
 
+    pub fn announce_signer(
+        &self,
+        purpose: SignerPurpose,
+        account: u32,
+        recipients: Vec<XOnlyPublicKey>,
+    ) -> Result<(SignerKey, EventId)> {
+        block_on(async {
+            self.client
+                .announce_signer(purpose, account, recipients)
+                .await
+        })
+    }
+
+    pub fn get_signer_keys(
+        &self,
+        public_key: XOnlyPublicKey,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<SignerKey>> {
+        block_on(async { self.client.get_signer_keys(public_key, timeout).await })
+    }
+
     pub fn get_policy_by_id(
         &self,
         policy_id: EventId,
@@ -66,7 +129,7 @@ impl CoinstrClient {
         &self,
         proposal_id: EventId,
         timeout: Option<Duration>,
-    ) -> Result<(PartiallySignedTransaction, Vec<PartiallySignedTransaction>)> {
+    ) -> Result<(PartiallySignedTransaction, Vec<Approval>)> {
         block_on(async {
             self.client
                 .get_signed_psbts_by_proposal_id(proposal_id, timeout)
@@ -74,6 +137,26 @@ impl CoinstrClient {
         })
     }
 
+    pub fn get_rejections_by_proposal_id(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Rejection>> {
+        block_on(async {
+            self.client
+                .get_rejections_by_proposal_id(proposal_id, timeout)
+                .await
+        })
+    }
+
+    pub fn proposal_status(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<ApprovalStatus> {
+        block_on(async { self.client.proposal_status(proposal_id, timeout).await })
+    }
+
     pub fn delete_policy_by_id(&self, policy_id: EventId, timeout: Option<Duration>) -> Result<()> {
         block_on(async { self.client.delete_policy_by_id(policy_id, timeout).await })
     }
@@ -90,6 +173,10 @@ impl CoinstrClient {
         })
     }
 
+    pub fn delete_orphan_events(&self, timeout: Option<Duration>) -> Result<Vec<EventId>> {
+        block_on(async { self.client.delete_orphan_events(timeout).await })
+    }
+
     pub fn get_policies(&self, timeout: Option<Duration>) -> Result<Vec<(EventId, Policy)>> {
         block_on(async { self.client.get_policies(timeout).await })
     }
@@ -101,20 +188,217 @@ impl CoinstrClient {
         block_on(async { self.client.get_proposals(timeout).await })
     }
 
-    pub fn save_policy<S>(&self, name: S, description: S, descriptor: S) -> Result<EventId>
+    pub fn get_active_proposals(
+        &self,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(EventId, SpendingProposal, EventId)>> {
+        block_on(async { self.client.get_active_proposals(blockchain, timeout).await })
+    }
+
+    pub fn get_stale_proposals(
+        &self,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(EventId, SpendingProposal, EventId)>> {
+        block_on(async { self.client.get_stale_proposals(blockchain, timeout).await })
+    }
+
+    pub fn delete_stale_proposals(
+        &self,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<EventId>> {
+        block_on(async {
+            self.client
+                .delete_stale_proposals(blockchain, timeout)
+                .await
+        })
+    }
+
+    pub fn get_utxo_labels(
+        &self,
+        policy_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<OutPoint, String>> {
+        block_on(async { self.client.get_utxo_labels(policy_id, timeout).await })
+    }
+
+    pub fn save_utxo_label<S>(
+        &self,
+        policy_id: EventId,
+        outpoint: OutPoint,
+        label: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId>
     where
         S: Into<String>,
     {
-        block_on(async { self.client.save_policy(name, description, descriptor).await })
+        block_on(async {
+            self.client
+                .save_utxo_label(policy_id, outpoint, label, timeout)
+                .await
+        })
+    }
+
+    pub fn save_policy<S>(
+        &self,
+        name: S,
+        description: S,
+        descriptor: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId>
+    where
+        S: Into<String>,
+    {
+        block_on(async {
+            self.client
+                .save_policy(name, description, descriptor, timeout)
+                .await
+        })
+    }
+
+    pub fn save_musig2_policy<S>(
+        &self,
+        name: S,
+        description: S,
+        policy: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId>
+    where
+        S: Into<String>,
+    {
+        block_on(async {
+            self.client
+                .save_musig2_policy(name, description, policy, timeout)
+                .await
+        })
+    }
+
+    pub fn save_compiled_policy(
+        &self,
+        policy: Policy,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async { self.client.save_compiled_policy(policy, timeout).await })
+    }
+
+    pub fn resolve_members(
+        &self,
+        policy: &Policy,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<XOnlyPublicKey>> {
+        block_on(async { self.client.resolve_members(policy, timeout).await })
+    }
+
+    pub fn create_policy_draft<S>(
+        &self,
+        name: S,
+        description: S,
+        template: PolicyTemplate,
+        members: Vec<XOnlyPublicKey>,
+    ) -> Result<EventId>
+    where
+        S: Into<String>,
+    {
+        block_on(async {
+            self.client
+                .create_policy_draft(name, description, template, members)
+                .await
+        })
+    }
+
+    pub fn get_policy_drafts(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<EventId, (PolicyDraft, XOnlyPublicKey)>> {
+        block_on(async { self.client.get_policy_drafts(timeout).await })
+    }
+
+    pub fn get_policy_draft_by_id(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<(PolicyDraft, XOnlyPublicKey)> {
+        block_on(async { self.client.get_policy_draft_by_id(draft_id, timeout).await })
+    }
+
+    pub fn get_policy_draft_status(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<DraftStatus> {
+        block_on(async { self.client.get_policy_draft_status(draft_id, timeout).await })
+    }
+
+    pub fn join_policy_draft(
+        &self,
+        draft_id: EventId,
+        purpose: SignerPurpose,
+        account: u32,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async {
+            self.client
+                .join_policy_draft(draft_id, purpose, account, timeout)
+                .await
+        })
+    }
+
+    pub fn assemble_policy_draft(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Policy> {
+        block_on(async { self.client.assemble_policy_draft(draft_id, timeout).await })
+    }
+
+    pub fn confirm_policy_draft(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async { self.client.confirm_policy_draft(draft_id, timeout).await })
+    }
+
+    pub fn finalize_policy_draft(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async { self.client.finalize_policy_draft(draft_id, timeout).await })
+    }
+
+    pub fn migrate_policy(&self, policy_id: EventId, timeout: Option<Duration>) -> Result<EventId> {
+        block_on(async { self.client.migrate_policy(policy_id, timeout).await })
+    }
+
+    pub fn rotate_shared_key(
+        &self,
+        policy_id: EventId,
+        excluded: Vec<XOnlyPublicKey>,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async {
+            self.client
+                .rotate_shared_key(policy_id, excluded, timeout)
+                .await
+        })
     }
 
     /// Make a spending proposal
+    #[allow(clippy::too_many_arguments)]
     pub fn spend<S>(
         &self,
         policy_id: EventId,
-        to_address: Address,
-        amount: u64,
+        recipients: Vec<Recipient>,
+        drain_to: Option<Address>,
         memo: S,
+        fee: Fee,
+        rbf: bool,
+        coin_control: CoinControl,
+        policy_path: Option<PolicyPath>,
+        expiry: Option<Expiry>,
         blockchain: impl Blockchain,
         timeout: Option<Duration>,
     ) -> Result<EventId>
@@ -123,7 +407,57 @@ impl CoinstrClient {
     {
         block_on(async {
             self.client
-                .spend(policy_id, to_address, amount, memo, blockchain, timeout)
+                .spend(
+                    policy_id,
+                    recipients,
+                    drain_to,
+                    memo,
+                    fee,
+                    rbf,
+                    coin_control,
+                    policy_path,
+                    expiry,
+                    blockchain,
+                    timeout,
+                )
+                .await
+        })
+    }
+
+    /// Make a proposal to bump the fee of a stuck transaction
+    #[allow(clippy::too_many_arguments)]
+    pub fn bump_fee<S>(
+        &self,
+        policy_id: EventId,
+        txid: Txid,
+        memo: S,
+        fee: Fee,
+        policy_path: Option<PolicyPath>,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<EventId>
+    where
+        S: Into<String>,
+    {
+        block_on(async {
+            self.client
+                .bump_fee(policy_id, txid, memo, fee, policy_path, blockchain, timeout)
+                .await
+        })
+    }
+
+    pub fn heartbeat(
+        &self,
+        policy_id: EventId,
+        threshold: u32,
+        fee: Fee,
+        do_not_spend: Vec<OutPoint>,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Heartbeat> {
+        block_on(async {
+            self.client
+                .heartbeat(policy_id, threshold, fee, do_not_spend, blockchain, timeout)
                 .await
         })
     }
@@ -132,6 +466,120 @@ impl CoinstrClient {
         block_on(async { self.client.approve(proposal_id, timeout).await })
     }
 
+    pub fn reject<S>(
+        &self,
+        proposal_id: EventId,
+        reason: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId>
+    where
+        S: Into<String>,
+    {
+        block_on(async { self.client.reject(proposal_id, reason, timeout).await })
+    }
+
+    pub fn musig2_commit_nonces(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async { self.client.musig2_commit_nonces(proposal_id, timeout).await })
+    }
+
+    pub fn musig2_sign(&self, proposal_id: EventId, timeout: Option<Duration>) -> Result<EventId> {
+        block_on(async { self.client.musig2_sign(proposal_id, timeout).await })
+    }
+
+    pub fn get_musig2_signed_psbt(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<PartiallySignedTransaction> {
+        block_on(async {
+            self.client
+                .get_musig2_signed_psbt(proposal_id, timeout)
+                .await
+        })
+    }
+
+    pub fn create_frost_keygen<S>(
+        &self,
+        name: S,
+        description: S,
+        threshold: usize,
+        members: Vec<XOnlyPublicKey>,
+    ) -> Result<EventId>
+    where
+        S: Into<String>,
+    {
+        block_on(async {
+            self.client
+                .create_frost_keygen(name, description, threshold, members)
+                .await
+        })
+    }
+
+    pub fn get_frost_keygen_by_id(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<(FrostKeyGen, XOnlyPublicKey)> {
+        block_on(async {
+            self.client
+                .get_frost_keygen_by_id(session_id, timeout)
+                .await
+        })
+    }
+
+    pub fn frost_keygen_join(&self, session_id: EventId, timeout: Option<Duration>) -> Result<()> {
+        block_on(async { self.client.frost_keygen_join(session_id, timeout).await })
+    }
+
+    pub fn finalize_frost_keygen(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<FrostGroup> {
+        block_on(async { self.client.finalize_frost_keygen(session_id, timeout).await })
+    }
+
+    pub fn save_frost_policy(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async { self.client.save_frost_policy(session_id, timeout).await })
+    }
+
+    pub fn frost_commit_nonces(
+        &self,
+        proposal_id: EventId,
+        signers: Vec<XOnlyPublicKey>,
+        timeout: Option<Duration>,
+    ) -> Result<EventId> {
+        block_on(async {
+            self.client
+                .frost_commit_nonces(proposal_id, signers, timeout)
+                .await
+        })
+    }
+
+    pub fn frost_sign(&self, proposal_id: EventId, timeout: Option<Duration>) -> Result<EventId> {
+        block_on(async { self.client.frost_sign(proposal_id, timeout).await })
+    }
+
+    pub fn get_frost_signed_psbt(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<PartiallySignedTransaction> {
+        block_on(async {
+            self.client
+                .get_frost_signed_psbt(proposal_id, timeout)
+                .await
+        })
+    }
+
     pub fn broadcast(
         &self,
         proposal_id: EventId,
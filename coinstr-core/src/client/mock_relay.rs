@@ -0,0 +1,144 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+//! In-memory nostr relay, to test the client rounds end to end
+//!
+//! Only the NIP-01 messages used by the client are supported: the subscriptions are
+//! answered with the stored events and closed after `EOSE`.
+//! The NIP-09 deletions remove the events of their author.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Clone, Default)]
+pub struct MockRelay {
+    events: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockRelay {
+    /// Listen on a random local port and return the url of the relay
+    pub async fn run() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let relay = Self::default();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let relay = relay.clone();
+                tokio::spawn(async move { relay.handle(stream).await });
+            }
+        });
+        url
+    }
+
+    async fn handle(&self, stream: TcpStream) {
+        let mut ws = match tokio_tungstenite::accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(_) => return,
+        };
+        while let Some(Ok(msg)) = ws.next().await {
+            let msg: Vec<Value> = match msg {
+                Message::Text(text) => match serde_json::from_str(&text) {
+                    Ok(msg) => msg,
+                    Err(_) => continue,
+                },
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            for reply in self.process(msg).into_iter() {
+                if ws.send(Message::Text(reply.to_string())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn process(&self, msg: Vec<Value>) -> Vec<Value> {
+        let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+        match msg.first().and_then(Value::as_str) {
+            Some("EVENT") => {
+                let event = msg[1].clone();
+                let id = event["id"].clone();
+                if event["kind"] == 5 {
+                    let deleted: Vec<Value> = event["tags"]
+                        .as_array()
+                        .map(|tags| {
+                            tags.iter()
+                                .filter(|t| t[0] == "e")
+                                .map(|t| t[1].clone())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    events.retain(|e| {
+                        !(deleted.contains(&e["id"]) && e["pubkey"] == event["pubkey"])
+                    });
+                }
+                if !events.iter().any(|e| e["id"] == id) {
+                    events.push(event);
+                }
+                vec![json!(["OK", id, true, ""])]
+            }
+            Some("REQ") => {
+                let sub_id = msg[1].clone();
+                let mut replies: Vec<Value> = Vec::new();
+                for filter in msg[2..].iter() {
+                    let mut matches: Vec<&Value> =
+                        events.iter().filter(|e| matches(filter, e)).collect();
+                    // The newest events first, as the limit keeps them
+                    matches.sort_by_key(|e| std::cmp::Reverse(e["created_at"].as_u64()));
+                    if let Some(limit) = filter["limit"].as_u64() {
+                        matches.truncate(limit as usize);
+                    }
+                    replies.extend(matches.into_iter().map(|e| json!(["EVENT", sub_id, e])));
+                }
+                replies.push(json!(["EOSE", sub_id]));
+                replies
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Check if an event matches a NIP-01 filter
+fn matches(filter: &Value, event: &Value) -> bool {
+    let filter = match filter.as_object() {
+        Some(filter) => filter,
+        None => return false,
+    };
+    filter.iter().all(|(key, values)| match key.as_str() {
+        "ids" => any_prefix(values, &event["id"]),
+        "authors" => any_prefix(values, &event["pubkey"]),
+        "kinds" => contains(values, &event["kind"]),
+        "since" => event["created_at"].as_u64() >= values.as_u64(),
+        "until" => event["created_at"].as_u64() <= values.as_u64(),
+        "limit" => true,
+        tag if tag.starts_with('#') => event["tags"]
+            .as_array()
+            .map(|tags| {
+                tags.iter()
+                    .any(|t| t[0].as_str() == Some(&tag[1..]) && contains(values, &t[1]))
+            })
+            .unwrap_or(false),
+        _ => true,
+    })
+}
+
+fn contains(values: &Value, value: &Value) -> bool {
+    values
+        .as_array()
+        .map(|values| values.contains(value))
+        .unwrap_or(false)
+}
+
+fn any_prefix(values: &Value, value: &Value) -> bool {
+    match (values.as_array(), value.as_str()) {
+        (Some(values), Some(value)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|prefix| value.starts_with(prefix)),
+        _ => false,
+    }
+}
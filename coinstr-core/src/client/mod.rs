@@ -1,32 +1,66 @@
 // Copyright (c) 2022-2023 Coinstr
 // Distributed under the MIT software license
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use bdk::bitcoin::hashes::{sha256, Hash};
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
-use bdk::bitcoin::{Address, Network, PrivateKey, Txid, XOnlyPublicKey};
-use bdk::blockchain::Blockchain;
+use bdk::bitcoin::util::bip32::{ChildNumber, ExtendedPrivKey, Fingerprint};
+use bdk::bitcoin::util::schnorr::SchnorrSig;
+use bdk::bitcoin::util::sighash::{Prevouts, SighashCache};
+use bdk::bitcoin::util::taproot::TapBranchHash;
+use bdk::bitcoin::{
+    Address, Network, OutPoint, PrivateKey, SchnorrSighashType, Script, TxOut, Txid, XOnlyPublicKey,
+};
+use bdk::blockchain::{Blockchain, GetHeight};
 use bdk::database::MemoryDatabase;
+use bdk::descriptor::policy::{Condition, PkOrF};
 use bdk::miniscript::psbt::PsbtExt;
 use bdk::signer::{SignerContext, SignerOrdering, SignerWrapper};
-use bdk::{KeychainKind, SignOptions, SyncOptions, Wallet};
+use bdk::wallet::AddressIndex;
+use bdk::{BlockTime, FeeRate, KeychainKind, SignOptions, SyncOptions, Wallet};
 use nostr_sdk::secp256k1::SecretKey;
 use nostr_sdk::{
-    nips, Client, EventBuilder, EventId, Filter, Keys, Metadata, Result, Tag, SECP256K1,
+    Client, Event, EventBuilder, EventId, Filter, Keys, Kind, Metadata, Result, Tag, TagKind,
+    Timestamp, SECP256K1,
 };
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
+#[cfg(test)]
+mod mock_relay;
 
 use crate::constants::{
-    APPROVED_PROPOSAL_KIND, POLICY_KIND, SHARED_KEY_KIND, SPENDING_PROPOSAL_KIND,
+    APPROVED_PROPOSAL_KIND, DEFAULT_MAX_FEE_RATE, FROST_KEYGEN_KIND, FROST_KEY_PACKAGE_KIND,
+    FROST_NONCE_KIND, FROST_SIGNATURE_SHARE_KIND, MUSIG2_NONCE_KIND, MUSIG2_PARTIAL_SIG_KIND,
+    POLICY_DRAFT_KIND, POLICY_DRAFT_RESPONSE_KIND, POLICY_KIND, REJECTED_PROPOSAL_KIND,
+    SHARED_KEY_KIND, SIGNER_KEY_KIND, SPENDING_PROPOSAL_KIND, UTXO_LABEL_KIND,
+};
+use crate::draft::{DraftResponse, DraftStatus, PolicyDraft};
+use crate::fee::Fee;
+use crate::frost::{
+    self, FrostGroup, FrostKeyGen, KeyGenCommitment, KeyGenMessage, KeyGenRound, KeyGenSecret,
+    KeyPackage, SignatureShare, SignatureShares, SigningCommitment, SigningCommitments,
+    SigningContext, SigningNonces,
+};
+use crate::musig2::{
+    self, KeyAggContext, PartialSignature, PartialSignatures, PublicNonce, SecretNonce,
+};
+use crate::policy::{
+    self, Policy, PolicyBranch, PolicyPath, PolicyTemplate, SpendingPath, UtxoMaturity,
 };
-use crate::policy::{self, Policy};
-use crate::proposal::SpendingProposal;
-use crate::util;
+use crate::proposal::{
+    Approval, ApprovalStatus, Expiry, FeeBump, Recipient, Rejection, SpendingProposal,
+};
+use crate::secrets::{self, SecretStore};
+use crate::signer::{self, SignerAnnouncement, SignerKey, SignerPurpose};
+use crate::util::{self, encryption, EncryptionVersion};
+use crate::utxo::{CoinControl, Heartbeat, UtxoLabel};
+
+const SUPERSEDED_TAG: &str = "superseded";
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -39,7 +73,7 @@ pub enum Error {
     #[error(transparent)]
     EventBuilder(#[from] nostr_sdk::event::builder::Error),
     #[error(transparent)]
-    NIP04(#[from] nostr_sdk::nips::nip04::Error),
+    Encryption(#[from] encryption::Error),
     #[error(transparent)]
     Policy(#[from] policy::Error),
     #[error(transparent)]
@@ -52,6 +86,18 @@ pub enum Error {
     PsbtParse(#[from] keechain_core::bitcoin::psbt::PsbtParseError),
     #[error(transparent)]
     Util(#[from] util::Error),
+    #[error(transparent)]
+    Signer(#[from] signer::Error),
+    #[error(transparent)]
+    Bip32(#[from] keechain_core::bitcoin::util::bip32::Error),
+    #[error(transparent)]
+    MuSig2(#[from] musig2::Error),
+    #[error(transparent)]
+    Frost(#[from] frost::Error),
+    #[error(transparent)]
+    Sighash(#[from] bdk::bitcoin::util::sighash::Error),
+    #[error(transparent)]
+    Secrets(#[from] secrets::Error),
     #[error("shared keys not found")]
     SharedKeysNotFound,
     #[error("policy not found")]
@@ -66,6 +112,102 @@ pub enum Error {
     PsbtNotSigned,
     #[error("wallet spending policy not found")]
     WalletSpendingPolicyNotFound,
+    #[error("no spending path can be satisfied now")]
+    NoSatisfiableBranch,
+    #[error("time based relative timelocks are not supported by the heartbeat")]
+    TimeBasedCsvNotSupported,
+    #[error("no recipients")]
+    NoRecipients,
+    #[error("transaction not found")]
+    TransactionNotFound,
+    #[error("transaction already confirmed")]
+    TransactionAlreadyConfirmed,
+    #[error("transaction doesn't signal RBF and has no outputs to spend with CPFP")]
+    CannotBumpFee,
+    #[error("PSBT doesn't pay {amount} sat to {address}")]
+    PsbtRecipientMismatch { address: Address, amount: u64 },
+    #[error("PSBT output {0} is neither a recipient nor a change of the policy")]
+    PsbtUnknownOutput(String),
+    #[error("PSBT input value not found")]
+    PsbtInputValueNotFound,
+    #[error("PSBT non-witness UTXO doesn't match the previous output of input {0}")]
+    PsbtNonWitnessUtxoMismatch(usize),
+    #[error("PSBT outputs ({output} sat) exceed the inputs ({input} sat)")]
+    PsbtNegativeFee { input: u64, output: u64 },
+    #[error("PSBT taproot merkle root doesn't match the script paths of the policy")]
+    PsbtTapMerkleRootMismatch,
+    #[error("PSBT nLockTime or nSequence don't satisfy the timelocks of the spending path")]
+    PsbtTimelockMismatch,
+    #[error("PSBT fee ({psbt} sat) doesn't match the proposal fee ({proposal} sat)")]
+    PsbtFeeMismatch { psbt: u64, proposal: u64 },
+    #[error("fee rate too high: {0:.2} sat/vByte")]
+    FeeRateTooHigh(f32),
+    #[error("policy has pending proposals")]
+    PendingProposals,
+    #[error("signer root key not set")]
+    SignerRootKeyNotFound,
+    #[error("signer key not found in the descriptor")]
+    SignerNotInDescriptor,
+    #[error("{0} is not a member of the policy")]
+    MemberNotFound(XOnlyPublicKey),
+    #[error("only the creator of the policy can rotate its shared key")]
+    NotPolicyCreator,
+    #[error("no contact announced the signer key {0}: set the members of the policy")]
+    SignerOwnerNotFound(Fingerprint),
+    #[error("signer key {0} claimed by more than one member: {1:?}")]
+    DuplicateSignerClaim(Fingerprint, Vec<XOnlyPublicKey>),
+    #[error("policy draft not found")]
+    PolicyDraftNotFound,
+    #[error("policy draft not assembled yet")]
+    PolicyDraftNotAssembled,
+    #[error("not the coordinator of the policy draft")]
+    NotDraftCoordinator,
+    #[error("not a member of the policy draft")]
+    NotDraftMember,
+    #[error("members without a signer key: {0:?}")]
+    PendingDraftSigners(Vec<XOnlyPublicKey>),
+    #[error("members that haven't confirmed the descriptor: {0:?}")]
+    PendingDraftConfirmations(Vec<XOnlyPublicKey>),
+    #[error("{0} hasn't announced a BIP-86 signer key")]
+    MissingSignerKey(XOnlyPublicKey),
+    #[error("the policy has no MuSig2 key path")]
+    NotMuSig2Policy,
+    #[error("not a participant of the MuSig2 key path")]
+    NotMuSig2Participant,
+    #[error("MuSig2 secret nonces not found: commit new nonces")]
+    MuSig2NoncesNotFound,
+    #[error("MuSig2 nonces of {0} don't match the proposal inputs")]
+    InvalidMuSig2Nonces(XOnlyPublicKey),
+    #[error("participants without MuSig2 nonces: {0:?}")]
+    MissingMuSig2Nonces(Vec<XOnlyPublicKey>),
+    #[error("participants without MuSig2 partial signatures: {0:?}")]
+    MissingMuSig2PartialSignatures(Vec<XOnlyPublicKey>),
+    #[error("FROST key generation session not found")]
+    FrostKeyGenNotFound,
+    #[error("not the coordinator of the FROST key generation")]
+    NotFrostCoordinator,
+    #[error("not a member of the FROST group")]
+    NotFrostMember,
+    #[error("members that haven't sent their key generation round: {0:?}")]
+    PendingFrostMembers(Vec<XOnlyPublicKey>),
+    #[error("members that haven't sent the hash of their commitments: {0:?}")]
+    PendingFrostEchoes(Vec<XOnlyPublicKey>),
+    #[error("{0} received different commitments: the key generation must be restarted")]
+    FrostCommitmentsMismatch(XOnlyPublicKey),
+    #[error("FROST key package not found")]
+    FrostKeyPackageNotFound,
+    #[error("the policy has no FROST key path")]
+    NotFrostPolicy,
+    #[error("the FROST group of the policy doesn't match the key package")]
+    FrostGroupMismatch,
+    #[error("FROST signing nonces not found: commit new nonces")]
+    FrostNoncesNotFound,
+    #[error("signers without FROST nonces for the signing set: {0:?}")]
+    MissingFrostNonces(Vec<XOnlyPublicKey>),
+    #[error("the FROST signing set must be {0} members of the group, own key included")]
+    InvalidFrostSigningSet(usize),
+    #[error("FROST signature shares not found")]
+    MissingFrostSignatureShares,
 }
 
 /// Coinstr Client
@@ -73,16 +215,49 @@ pub enum Error {
 pub struct CoinstrClient {
     network: Network,
     client: Client,
+    root_key: Option<ExtendedPrivKey>,
+    /// Max fee rate (sat/vByte) accepted when approving a spending proposal
+    max_fee_rate: f32,
+    /// Secrets of the key path sessions: the secret nonces waiting for the next round
+    /// and the FROST key packages
+    secrets: SecretStore,
 }
 
 impl CoinstrClient {
     pub async fn new(keys: Keys, relays: Vec<String>, network: Network) -> Result<Self, Error> {
         let client = Client::new(&keys);
+        let secrets = SecretStore::memory(keys.clone());
         #[cfg(not(target_arch = "wasm32"))]
         let relays = relays.iter().map(|url| (url, None)).collect();
         client.add_relays(relays).await?;
         client.connect().await;
-        Ok(Self { network, client })
+        Ok(Self {
+            network,
+            client,
+            root_key: None,
+            max_fee_rate: DEFAULT_MAX_FEE_RATE,
+            secrets,
+        })
+    }
+
+    /// Sign the transactions with the keys derived from the seed `root_key`,
+    /// instead of the nostr identity key
+    pub fn with_signer(mut self, root_key: ExtendedPrivKey) -> Self {
+        self.root_key = Some(root_key);
+        self
+    }
+
+    /// Keep the secrets of the key path sessions in `secrets`, instead of memory only,
+    /// so a session can be resumed after a restart
+    pub fn with_secret_store(mut self, secrets: SecretStore) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Set the max fee rate (sat/vByte) accepted when approving a spending proposal
+    pub fn with_max_fee_rate(mut self, max_fee_rate: f32) -> Self {
+        self.max_fee_rate = max_fee_rate;
+        self
     }
 
     pub fn network(&self) -> Network {
@@ -93,8 +268,94 @@ impl CoinstrClient {
     where
         S: Into<String>,
     {
+        let (external, internal) = util::split_multipath_descriptor(descriptor)?;
         let db = MemoryDatabase::new();
-        Ok(Wallet::new(&descriptor.into(), None, self.network, db)?)
+        Ok(Wallet::new(
+            &external,
+            internal.as_deref(),
+            self.network,
+            db,
+        )?)
+    }
+
+    /// Get the spending branches of a policy
+    ///
+    /// The unspendable key path is not included.
+    pub fn policy_branches(&self, policy: &Policy) -> Result<Vec<PolicyBranch>, Error> {
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        let wallet_policy = wallet
+            .policies(KeychainKind::External)?
+            .ok_or(Error::WalletSpendingPolicyNotFound)?;
+        Ok(policy::path::branches(&wallet_policy)
+            .into_iter()
+            .filter(|branch| !policy.is_unspendable_key_path(branch))
+            .collect())
+    }
+
+    /// Describe the spending paths of a policy, in the same order of [`CoinstrClient::policy_branches`]
+    ///
+    /// The signer keys are resolved to the members that announced them and to their nostr names.
+    pub async fn analyze_policy(
+        &self,
+        policy: &Policy,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<SpendingPath>, Error> {
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        let descriptor = wallet.get_descriptor_for_keychain(KeychainKind::External);
+        let mut paths =
+            policy::analysis::spending_paths(policy, descriptor, self.policy_branches(policy)?)?;
+
+        // Owners of the extended keys
+        let mut owners: HashMap<Fingerprint, XOnlyPublicKey> = HashMap::new();
+        let has_extended_keys: bool = paths.iter().any(|path| {
+            path.signers
+                .iter()
+                .any(|signer| matches!(signer.key, PkOrF::Fingerprint(..)))
+        });
+        if has_extended_keys {
+            for member in policy.member_pubkeys()?.into_iter() {
+                for signer in self.get_signer_keys(member, timeout).await?.into_iter() {
+                    owners.insert(signer.fingerprint, member);
+                }
+            }
+        }
+
+        let contacts = self.get_contacts(timeout).await?;
+        for path in paths.iter_mut() {
+            path.resolve(&owners, &contacts);
+        }
+
+        Ok(paths)
+    }
+
+    /// Get when the timelocked branches of a policy mature, for each UTXO of the synced `wallet`
+    pub fn get_maturity(
+        &self,
+        policy: &Policy,
+        wallet: &Wallet<MemoryDatabase>,
+    ) -> Result<Vec<UtxoMaturity>, Error> {
+        let branches: Vec<PolicyBranch> = self
+            .policy_branches(policy)?
+            .into_iter()
+            .filter(|branch| branch.has_timelock())
+            .collect();
+
+        let mut utxos: Vec<UtxoMaturity> = Vec::new();
+        for utxo in wallet.list_unspent()?.into_iter() {
+            let confirmation: Option<BlockTime> = wallet
+                .get_tx(&utxo.outpoint.txid, false)?
+                .and_then(|tx| tx.confirmation_time);
+            utxos.push(UtxoMaturity {
+                outpoint: utxo.outpoint,
+                value: utxo.txout.value,
+                branches: branches
+                    .iter()
+                    .map(|branch| (branch.clone(), branch.maturity(confirmation.as_ref())))
+                    .collect(),
+                confirmation,
+            });
+        }
+        Ok(utxos)
     }
 
     pub async fn get_contacts(
@@ -104,53 +365,281 @@ impl CoinstrClient {
         Ok(self.client.get_contact_list_metadata(timeout).await?)
     }
 
-    pub async fn get_shared_keys(
+    /// Announce the signer key derived from the seed to the `recipients`, linking it to the
+    /// nostr identity
+    ///
+    /// The announcement is signed with the signer key and encrypted to each recipient (and to
+    /// the own key). Return the id of the own copy.
+    pub async fn announce_signer(
         &self,
-        timeout: Option<Duration>,
-    ) -> Result<HashMap<EventId, Keys>, Error> {
+        purpose: SignerPurpose,
+        account: u32,
+        recipients: Vec<XOnlyPublicKey>,
+    ) -> Result<(SignerKey, EventId), Error> {
         let keys = self.client.keys();
+        let root_key = self.root_key.as_ref().ok_or(Error::SignerRootKeyNotFound)?;
+        let announcement = SignerAnnouncement::new(root_key, purpose, account, &keys.public_key())?;
+
+        let mut pubkeys: Vec<XOnlyPublicKey> = vec![keys.public_key()];
+        for recipient in recipients.into_iter() {
+            if !pubkeys.contains(&recipient) {
+                pubkeys.push(recipient);
+            }
+        }
 
+        let mut own_event_id: Option<EventId> = None;
+        for pubkey in pubkeys.into_iter() {
+            let content =
+                encryption::encrypt(&keys.secret_key()?, &pubkey, announcement.as_json())?;
+            let event = EventBuilder::new(
+                SIGNER_KEY_KIND,
+                content,
+                &[Tag::PubKey(pubkey, None), EncryptionVersion::Nip44.tag()],
+            )
+            .to_event(&keys)?;
+            let event_id = self.client.send_event(event).await?;
+            own_event_id.get_or_insert(event_id);
+        }
+
+        let event_id = own_event_id.expect("the own copy is always sent");
+        Ok((announcement.signer, event_id))
+    }
+
+    /// Get the signer keys that `public_key` announced to us
+    ///
+    /// Announcements that can't be decrypted or whose proof doesn't match `public_key` are
+    /// skipped.
+    pub async fn get_signer_keys(
+        &self,
+        public_key: XOnlyPublicKey,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<SignerKey>, Error> {
+        let keys = self.client.keys();
         let filter = Filter::new()
+            .author(public_key)
             .pubkey(keys.public_key())
-            .kind(SHARED_KEY_KIND);
-        let shared_key_events = self.client.get_events_of(vec![filter], timeout).await?;
-
-        // Index global keys by policy id
-        let mut shared_keys: HashMap<EventId, Keys> = HashMap::new();
-        for event in shared_key_events.into_iter() {
-            for tag in event.tags {
-                if let Tag::Event(event_id, ..) = tag {
-                    let content =
-                        nips::nip04::decrypt(&keys.secret_key()?, &event.pubkey, &event.content)?;
-                    let sk = SecretKey::from_str(&content)?;
-                    let keys = Keys::new(sk);
-                    shared_keys.insert(event_id, keys);
+            .kind(SIGNER_KEY_KIND);
+        let mut events = self.client.get_events_of(vec![filter], timeout).await?;
+        events.sort_by_key(|event| event.created_at);
+
+        let mut signers: Vec<SignerKey> = Vec::new();
+        for event in events.into_iter() {
+            if event.pubkey != public_key {
+                continue;
+            }
+            let announcement = match encryption::decrypt(&keys.secret_key()?, &public_key, &event)
+                .map_err(Error::from)
+                .and_then(|content| Ok(SignerAnnouncement::from_json(content)?))
+            {
+                Ok(announcement) => announcement,
+                Err(e) => {
+                    log::error!("Impossible to read signer announcement {}: {e}", event.id);
+                    continue;
                 }
+            };
+            if let Err(e) = announcement.verify(&public_key) {
+                log::warn!(
+                    "Signer announcement {} not signed by the signer key: {e}",
+                    event.id
+                );
+                continue;
+            }
+            if !signers.contains(&announcement.signer) {
+                signers.push(announcement.signer);
             }
         }
-        Ok(shared_keys)
+        Ok(signers)
+    }
+
+    pub async fn get_shared_keys(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<EventId, Keys>, Error> {
+        // Index global keys by policy id, keeping the current one
+        Ok(self
+            .get_shared_key_histories(timeout)
+            .await?
+            .into_iter()
+            .filter_map(|(policy_id, mut keys)| keys.pop().map(|keys| (policy_id, keys)))
+            .collect())
+    }
+
+    /// Get all the shared keys, indexed by policy id, with the current one as last
+    async fn get_shared_key_histories(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<EventId, Vec<Keys>>, Error> {
+        let keys = self.client.keys();
+
+        let filters = vec![
+            Filter::new()
+                .pubkey(keys.public_key())
+                .kind(SHARED_KEY_KIND),
+            Filter::new().pubkey(keys.public_key()).kind(POLICY_KIND),
+        ];
+        let events = self.client.get_events_of(filters, timeout).await?;
+        self.resolve_shared_keys(events)
     }
 
+    /// Get the current shared key of a policy
     pub async fn get_shared_key_by_policy_id(
         &self,
         policy_id: EventId,
         timeout: Option<Duration>,
     ) -> Result<Keys, Error> {
+        self.get_shared_key_history_by_policy_id(policy_id, timeout)
+            .await?
+            .pop()
+            .ok_or(Error::SharedKeysNotFound)
+    }
+
+    /// Get all the shared keys of a policy, with the current one as last
+    async fn get_shared_key_history_by_policy_id(
+        &self,
+        policy_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Keys>, Error> {
+        let events = self.get_shared_key_events(policy_id, timeout).await?;
+        self.resolve_shared_keys(events)?
+            .remove(&policy_id)
+            .ok_or(Error::SharedKeysNotFound)
+    }
+
+    /// Get the member that created a policy, if known
+    async fn get_policy_creator(
+        &self,
+        policy_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<XOnlyPublicKey>, Error> {
+        let events = self.get_shared_key_events(policy_id, timeout).await?;
+        Ok(self.policy_creators(&events)?.remove(&policy_id))
+    }
+
+    /// Get the shared key events of a policy and the policy event
+    async fn get_shared_key_events(
+        &self,
+        policy_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error> {
         let keys = self.client.keys();
 
-        let filter = Filter::new()
-            .pubkey(keys.public_key())
-            .event(policy_id)
-            .kind(SHARED_KEY_KIND);
-        let events = self.client.get_events_of(vec![filter], timeout).await?;
-        let shared_key_event = events.first().ok_or(Error::SharedKeysNotFound)?;
-        let content = nips::nip04::decrypt(
-            &keys.secret_key()?,
-            &shared_key_event.pubkey,
-            &shared_key_event.content,
-        )?;
-        let sk = SecretKey::from_str(&content)?;
-        Ok(Keys::new(sk))
+        let filters = vec![
+            Filter::new()
+                .pubkey(keys.public_key())
+                .event(policy_id)
+                .kind(SHARED_KEY_KIND),
+            Filter::new().id(policy_id).kind(POLICY_KIND),
+        ];
+        Ok(self.client.get_events_of(filters, timeout).await?)
+    }
+
+    /// Get the creators of the policies, indexed by policy id
+    ///
+    /// The creator is the member that sent the shared key the policy event is signed with.
+    /// Policies with more than one sender of that key have no known creator.
+    fn policy_creators(&self, events: &[Event]) -> Result<HashMap<EventId, XOnlyPublicKey>, Error> {
+        let keys = self.client.keys();
+        let policies = extract_policies(events);
+
+        let mut senders: HashMap<EventId, Vec<XOnlyPublicKey>> = HashMap::new();
+        for event in events.iter().filter(|e| e.kind == SHARED_KEY_KIND) {
+            let policy_id = match util::extract_first_event_id(event) {
+                Some(policy_id) => policy_id,
+                None => continue,
+            };
+            let (original, members) = match policies.get(&policy_id) {
+                Some(policy) => policy,
+                None => continue,
+            };
+            if !members.contains(&event.pubkey) {
+                continue;
+            }
+
+            let content = encryption::decrypt(&keys.secret_key()?, &event.pubkey, event)?;
+            let shared_key = Keys::new(SecretKey::from_str(&content)?);
+            if shared_key.public_key() == *original {
+                let policy_senders = senders.entry(policy_id).or_default();
+                if !policy_senders.contains(&event.pubkey) {
+                    policy_senders.push(event.pubkey);
+                }
+            }
+        }
+
+        Ok(senders
+            .into_iter()
+            .filter_map(|(policy_id, senders)| match senders.as_slice() {
+                [creator] => Some((policy_id, *creator)),
+                _ => {
+                    log::warn!("Policy {policy_id} has more than one creator: {senders:?}");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Decrypt the shared keys and index them by policy id, with the current key as last
+    ///
+    /// `events` must include the shared key events and the policy events, used to discard the
+    /// shared keys not sent by a member of the policy.
+    /// Besides the key the policy event is signed with, only the keys sent by the creator of the
+    /// policy (the rotations) are accepted: a key is not current anymore once superseded by one.
+    fn resolve_shared_keys(
+        &self,
+        events: Vec<Event>,
+    ) -> Result<HashMap<EventId, Vec<Keys>>, Error> {
+        let keys = self.client.keys();
+        let policies = extract_policies(&events);
+        let creators = self.policy_creators(&events)?;
+
+        let mut superseded: Vec<XOnlyPublicKey> = Vec::new();
+        let mut shared_keys: Vec<(EventId, Timestamp, Keys)> = Vec::new();
+        for event in events.iter().filter(|e| e.kind == SHARED_KEY_KIND) {
+            let policy_id = match util::extract_first_event_id(event) {
+                Some(policy_id) => policy_id,
+                None => continue,
+            };
+            let original = match policies.get(&policy_id) {
+                Some((original, members)) if members.contains(&event.pubkey) => original,
+                Some(_) => {
+                    log::warn!(
+                        "Shared key {} not sent by a member of policy {policy_id}",
+                        event.id
+                    );
+                    continue;
+                }
+                None => {
+                    log::warn!("Policy {policy_id} of shared key {} not found", event.id);
+                    continue;
+                }
+            };
+
+            let content = encryption::decrypt(&keys.secret_key()?, &event.pubkey, event)?;
+            let shared_key = Keys::new(SecretKey::from_str(&content)?);
+            if shared_key.public_key() != *original
+                && creators.get(&policy_id) != Some(&event.pubkey)
+            {
+                log::warn!(
+                    "Shared key {} of policy {policy_id} not sent by the creator of the policy",
+                    event.id
+                );
+                continue;
+            }
+            shared_keys.push((policy_id, event.created_at, shared_key));
+            superseded.extend(extract_superseded_keys(event));
+        }
+
+        // Superseded keys first, then by creation time
+        shared_keys.sort_by_key(|(_, created_at, shared_key)| {
+            (!superseded.contains(&shared_key.public_key()), *created_at)
+        });
+
+        let mut history: HashMap<EventId, Vec<Keys>> = HashMap::new();
+        for (policy_id, _, shared_key) in shared_keys.into_iter() {
+            let policy_keys = history.entry(policy_id).or_default();
+            policy_keys.retain(|k| k.public_key() != shared_key.public_key());
+            policy_keys.push(shared_key);
+        }
+        Ok(history)
     }
 
     pub async fn get_policy_by_id(
@@ -158,19 +647,25 @@ impl CoinstrClient {
         policy_id: EventId,
         timeout: Option<Duration>,
     ) -> Result<(Policy, Keys), Error> {
-        // Get policy event
-        let filter = Filter::new().id(policy_id).kind(POLICY_KIND);
-        let events = self.client.get_events_of(vec![filter], timeout).await?;
-        let policy_event = events.first().ok_or(Error::PolicyNotFound)?;
+        // Get policy events (the rotations of the shared key republish the policy)
+        let filters = vec![
+            Filter::new().id(policy_id).kind(POLICY_KIND),
+            Filter::new().event(policy_id).kind(POLICY_KIND),
+        ];
+        let events = self.client.get_events_of(filters, timeout).await?;
 
         // Get shared key
         let shared_keys = self.get_shared_key_by_policy_id(policy_id, timeout).await?;
 
-        // Decrypt and deserialize the policy
-        let content = nips::nip04::decrypt(
+        // Decrypt and deserialize the policy published with the current shared key
+        let policy_event = events
+            .iter()
+            .find(|event| event.pubkey == shared_keys.public_key())
+            .ok_or(Error::PolicyNotFound)?;
+        let content = encryption::decrypt(
             &shared_keys.secret_key()?,
             &shared_keys.public_key(),
-            &policy_event.content,
+            policy_event,
         )?;
         Ok((Policy::from_json(content)?, shared_keys))
     }
@@ -191,10 +686,10 @@ impl CoinstrClient {
         let shared_keys = self.get_shared_key_by_policy_id(policy_id, timeout).await?;
 
         // Decrypt and deserialize the spending proposal
-        let content = nips::nip04::decrypt(
+        let content = encryption::decrypt(
             &shared_keys.secret_key()?,
             &shared_keys.public_key(),
-            &proposal_event.content,
+            &proposal_event,
         )?;
         Ok((
             SpendingProposal::from_json(content)?,
@@ -203,37 +698,190 @@ impl CoinstrClient {
         ))
     }
 
+    /// Get the PSBT of a proposal and its approvals
+    ///
+    /// Approvals not authored by a member of the policy (check [`Policy::is_member`]) are discarded,
+    /// whatever the keys they sign with, as the ones that can't be decrypted or parsed.
     pub async fn get_signed_psbts_by_proposal_id(
         &self,
         proposal_id: EventId,
         timeout: Option<Duration>,
-    ) -> Result<(PartiallySignedTransaction, Vec<PartiallySignedTransaction>), Error> {
+    ) -> Result<(PartiallySignedTransaction, Vec<Approval>), Error> {
+        // Get proposal and policy
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
         // Get approved proposals
         let filter = Filter::new()
             .event(proposal_id)
             .kind(APPROVED_PROPOSAL_KIND);
         let proposals_events = self.client.get_events_of(vec![filter], timeout).await?;
-        let first_event = proposals_events
-            .first()
-            .ok_or(Error::ApprovedProposalNotFound)?;
-        let proposal_id =
-            util::extract_first_event_id(first_event).ok_or(Error::ApprovedProposalNotFound)?;
 
-        // Get global shared key
-        let (proposal, _, shared_keys) = self.get_proposal_by_id(proposal_id, timeout).await?;
-
-        let mut psbts: Vec<PartiallySignedTransaction> = Vec::new();
+        let mut approvals: Vec<Approval> = Vec::new();
 
         for event in proposals_events.into_iter() {
-            let content = nips::nip04::decrypt(
-                &shared_keys.secret_key()?,
-                &shared_keys.public_key(),
-                &event.content,
-            )?;
-            psbts.push(PartiallySignedTransaction::from_str(&content)?);
+            if !policy.is_member(&event.pubkey)? {
+                log::warn!(
+                    "Approval {} of proposal {proposal_id} not authored by a member of the policy",
+                    event.id
+                );
+                continue;
+            }
+
+            let psbt = encryption::decrypt(&shared_keys.secret_key()?, &event.pubkey, &event)
+                .map_err(Error::from)
+                .and_then(|content| Ok(PartiallySignedTransaction::from_str(&content)?));
+            let psbt = match psbt {
+                Ok(psbt) => psbt,
+                Err(e) => {
+                    log::warn!(
+                        "Invalid approval {} of proposal {proposal_id}: {e}",
+                        event.id
+                    );
+                    continue;
+                }
+            };
+            approvals.push(Approval {
+                approval_id: event.id,
+                author: event.pubkey,
+                psbt,
+                timestamp: event.created_at,
+            });
+        }
+
+        if approvals.is_empty() {
+            return Err(Error::ApprovedProposalNotFound);
+        }
+
+        Ok((proposal.psbt, approvals))
+    }
+
+    /// Get the rejections of a proposal
+    ///
+    /// Rejections not authored by a member of the policy (check [`Policy::is_member`]) are discarded.
+    pub async fn get_rejections_by_proposal_id(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Rejection>, Error> {
+        // Get proposal and policy
+        let (_proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        let filter = Filter::new()
+            .event(proposal_id)
+            .kind(REJECTED_PROPOSAL_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+
+        let mut rejections: Vec<Rejection> = Vec::new();
+        for event in events.into_iter() {
+            if !policy.is_member(&event.pubkey)? {
+                log::warn!(
+                    "Rejection {} of proposal {proposal_id} not authored by a member of the policy",
+                    event.id
+                );
+                continue;
+            }
+
+            let reason = encryption::decrypt(&shared_keys.secret_key()?, &event.pubkey, &event)?;
+            rejections.push(Rejection {
+                rejection_id: event.id,
+                author: event.pubkey,
+                reason,
+                timestamp: event.created_at,
+            });
         }
 
-        Ok((proposal.psbt, psbts))
+        rejections.sort_by_key(|r| r.timestamp);
+        Ok(rejections)
+    }
+
+    /// Get the approval status of a spending proposal
+    pub async fn proposal_status(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<ApprovalStatus, Error> {
+        let (proposal, policy_id, _shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        // Combine the approvals
+        let (psbt, approved_by) = match self
+            .get_signed_psbts_by_proposal_id(proposal_id, timeout)
+            .await
+        {
+            Ok((_, approvals)) => {
+                let approved_by: Vec<XOnlyPublicKey> = approvals.iter().map(|a| a.author).collect();
+                (combine_approvals(proposal.psbt, approvals), approved_by)
+            }
+            Err(Error::ApprovedProposalNotFound) => (proposal.psbt, Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let signers = util::extract_psbt_signers(&psbt);
+
+        // Get the rejections of the members that haven't approved
+        let mut rejected_by: Vec<XOnlyPublicKey> = self
+            .get_rejections_by_proposal_id(proposal_id, timeout)
+            .await?
+            .into_iter()
+            .map(|r| r.author)
+            .filter(|author| !approved_by.contains(author) && !signers.contains(author))
+            .collect();
+        rejected_by.sort();
+        rejected_by.dedup();
+
+        // Evaluate the policy
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        let wallet_policy = wallet
+            .policies(KeychainKind::External)?
+            .ok_or(Error::WalletSpendingPolicyNotFound)?;
+        let is_satisfied =
+            policy::path::is_satisfied(&wallet_policy, proposal.policy_path.as_ref(), &signers);
+
+        let branch = self
+            .policy_branches(&policy)?
+            .into_iter()
+            .find(|branch| Some(&branch.path) == proposal.policy_path.as_ref());
+
+        // The proposal can't be satisfied anymore if the members that haven't rejected it
+        // can't reach the threshold
+        let is_unsatisfiable = !is_satisfied && {
+            let mut candidates: Vec<XOnlyPublicKey> = Vec::new();
+            for branch in self.policy_branches(&policy)?.into_iter() {
+                for key in branch.keys.into_iter() {
+                    if let PkOrF::XOnlyPubkey(key) = key {
+                        if !rejected_by.contains(&key) && !candidates.contains(&key) {
+                            candidates.push(key);
+                        }
+                    }
+                }
+            }
+            !policy::path::is_satisfied(&wallet_policy, proposal.policy_path.as_ref(), &candidates)
+        };
+
+        let missing: Vec<XOnlyPublicKey> = match &branch {
+            Some(branch) => branch
+                .keys
+                .iter()
+                .filter_map(|key| match key {
+                    PkOrF::XOnlyPubkey(key) if !signers.contains(key) => Some(*key),
+                    _ => None,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(ApprovalStatus {
+            signers,
+            branch,
+            missing,
+            is_satisfied,
+            rejected_by,
+            is_unsatisfiable,
+        })
     }
 
     pub async fn delete_policy_by_id(
@@ -241,18 +889,28 @@ impl CoinstrClient {
         policy_id: EventId,
         timeout: Option<Duration>,
     ) -> Result<(), Error> {
-        // Get shared key
-        let shared_keys = self.get_shared_key_by_policy_id(policy_id, timeout).await?;
+        // Get shared keys, including the superseded ones
+        let shared_keys = self
+            .get_shared_key_history_by_policy_id(policy_id, timeout)
+            .await?;
 
         // Get all events linked to the policy
-        let filter = Filter::new().event(policy_id);
-        let events = self.client.get_events_of(vec![filter], timeout).await?;
+        let filters = vec![Filter::new().event(policy_id), Filter::new().id(policy_id)];
+        let events = self.client.get_events_of(filters, timeout).await?;
 
-        let mut ids: Vec<EventId> = events.iter().map(|e| e.id).collect();
-        ids.push(policy_id);
-
-        let event = EventBuilder::delete::<String>(ids, None).to_event(&shared_keys)?;
-        self.client.send_event(event).await?;
+        // Delete the events with the shared key that published them
+        for shared_key in shared_keys.iter() {
+            let ids: Vec<EventId> = events
+                .iter()
+                .filter(|e| e.pubkey == shared_key.public_key())
+                .map(|e| e.id)
+                .collect();
+            if ids.is_empty() {
+                continue;
+            }
+            let event = EventBuilder::delete::<String>(ids, None).to_event(shared_key)?;
+            self.client.send_event(event).await?;
+        }
 
         Ok(())
     }
@@ -269,10 +927,15 @@ impl CoinstrClient {
         let policy_id =
             util::extract_first_event_id(proposal_event).ok_or(Error::PolicyNotFound)?;
 
-        // Get shared key
-        let shared_keys = self.get_shared_key_by_policy_id(policy_id, timeout).await?;
+        // Get the shared key that published the proposal, even if superseded since
+        let shared_keys = self
+            .get_shared_key_history_by_policy_id(policy_id, timeout)
+            .await?
+            .into_iter()
+            .find(|k| k.public_key() == proposal_event.pubkey)
+            .ok_or(Error::SharedKeysNotFound)?;
 
-        // Get all events linked to the proposal
+        // Get all events linked to the proposal, approvals included
         let filter = Filter::new().event(proposal_id);
         let events = self.client.get_events_of(vec![filter], timeout).await?;
 
@@ -282,9 +945,104 @@ impl CoinstrClient {
         let event = EventBuilder::delete::<String>(ids, None).to_event(&shared_keys)?;
         self.client.send_event(event).await?;
 
+        // The approvals are authored by the members: delete the own ones with the own keys too
+        // (the other members delete theirs with `delete_orphan_events`)
+        let keys = self.client.keys();
+        let own_ids: Vec<EventId> = events
+            .iter()
+            .filter(|e| e.pubkey == keys.public_key())
+            .map(|e| e.id)
+            .collect();
+        if !own_ids.is_empty() {
+            let event = EventBuilder::delete::<String>(own_ids, None).to_event(&keys)?;
+            self.client.send_event(event).await?;
+        }
+
         Ok(())
     }
 
+    /// Delete the own events (approvals, rejections and key path rounds) of the proposals
+    /// deleted by another member
+    ///
+    /// The deletion of a proposal is signed with the shared key, that can't delete the events
+    /// authored by the members: each member deletes its own ones when it sees the deletion.
+    /// Return the ids of the deleted events.
+    pub async fn delete_orphan_events(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<EventId>, Error> {
+        let keys = self.client.keys();
+
+        // Own events, by proposal
+        let filters: Vec<Filter> = [
+            APPROVED_PROPOSAL_KIND,
+            REJECTED_PROPOSAL_KIND,
+            MUSIG2_NONCE_KIND,
+            MUSIG2_PARTIAL_SIG_KIND,
+            FROST_NONCE_KIND,
+            FROST_SIGNATURE_SHARE_KIND,
+        ]
+        .into_iter()
+        .map(|kind| Filter::new().author(keys.public_key()).kind(kind))
+        .collect();
+        let events = self.client.get_events_of(filters, timeout).await?;
+        let mut own_events: HashMap<EventId, (EventId, Vec<EventId>)> = HashMap::new();
+        for event in events.into_iter() {
+            let ids: Vec<EventId> = event
+                .tags
+                .iter()
+                .filter_map(|tag| match tag {
+                    Tag::Event(id, ..) => Some(*id),
+                    _ => None,
+                })
+                .collect();
+            if let [proposal_id, policy_id, ..] = ids.as_slice() {
+                own_events
+                    .entry(*proposal_id)
+                    .or_insert_with(|| (*policy_id, Vec::new()))
+                    .1
+                    .push(event.id);
+            }
+        }
+        if own_events.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Deletions of the proposals, signed with a shared key of their policy
+        let filters: Vec<Filter> = own_events
+            .keys()
+            .map(|proposal_id| Filter::new().kind(Kind::EventDeletion).event(*proposal_id))
+            .collect();
+        let deletions = self.client.get_events_of(filters, timeout).await?;
+        let shared_keys = self.get_shared_key_histories(timeout).await?;
+
+        let mut ids: Vec<EventId> = Vec::new();
+        for (proposal_id, (policy_id, event_ids)) in own_events.into_iter() {
+            let shared_keys = match shared_keys.get(&policy_id) {
+                Some(shared_keys) => shared_keys,
+                None => continue,
+            };
+            let deleted = deletions.iter().any(|deletion| {
+                shared_keys
+                    .iter()
+                    .any(|k| k.public_key() == deletion.pubkey)
+                    && deletion
+                        .tags
+                        .iter()
+                        .any(|tag| matches!(tag, Tag::Event(id, ..) if *id == proposal_id))
+            });
+            if deleted {
+                ids.extend(event_ids);
+            }
+        }
+
+        if !ids.is_empty() {
+            let event = EventBuilder::delete::<String>(ids.clone(), None).to_event(&keys)?;
+            self.client.send_event(event).await?;
+        }
+        Ok(ids)
+    }
+
     pub async fn get_policies(
         &self,
         timeout: Option<Duration>,
@@ -301,15 +1059,21 @@ impl CoinstrClient {
         let mut policies: Vec<(EventId, Policy)> = Vec::new();
 
         for event in policies_events.into_iter() {
-            if let Some(shared_key) = shared_keys.get(&event.id) {
-                let content = nips::nip04::decrypt(
+            // The rotations of the shared key refer to the original policy event
+            let policy_id = util::extract_first_event_id(&event).unwrap_or(event.id);
+            if let Some(shared_key) = shared_keys.get(&policy_id) {
+                // Skip the policy events of the superseded shared keys
+                if event.pubkey != shared_key.public_key() {
+                    continue;
+                }
+                let content = encryption::decrypt(
                     &shared_key.secret_key()?,
                     &shared_key.public_key(),
-                    &event.content,
+                    &event,
                 )?;
-                policies.push((event.id, Policy::from_json(&content)?));
+                policies.push((policy_id, Policy::from_json(&content)?));
             } else {
-                log::error!("Shared key not found for policy {}", event.id);
+                log::error!("Shared key not found for policy {policy_id}");
             }
         }
 
@@ -335,213 +1099,2668 @@ impl CoinstrClient {
 
         for event in proposals_events.into_iter() {
             let policy_id = util::extract_first_event_id(&event).ok_or(Error::PolicyNotFound)?;
-            let global_key: &Keys = shared_keys
-                .get(&policy_id)
-                .ok_or(Error::SharedKeysNotFound)?;
-
-            let content = nips::nip04::decrypt(
-                &global_key.secret_key()?,
-                &global_key.public_key(),
-                &event.content,
-            )?;
+            let global_key: &Keys = match shared_keys.get(&policy_id) {
+                Some(global_key) => global_key,
+                None => {
+                    log::warn!("Shared key of proposal {} not found", event.id);
+                    continue;
+                }
+            };
 
-            proposals.push((event.id, SpendingProposal::from_json(&content)?, policy_id));
+            // Proposals encrypted with a superseded shared key (not deleted by the relay)
+            // can't be decrypted: skip them without failing the whole listing
+            let proposal =
+                encryption::decrypt(&global_key.secret_key()?, &global_key.public_key(), &event)
+                    .map_err(Error::from)
+                    .and_then(|content| SpendingProposal::from_json(content).map_err(Error::from));
+            match proposal {
+                Ok(proposal) => proposals.push((event.id, proposal, policy_id)),
+                Err(e) => log::warn!("Impossible to decrypt proposal {}: {e}", event.id),
+            }
         }
 
         Ok(proposals)
     }
 
-    pub async fn save_policy<S>(
+    /// Get the proposals, excluding the stale ones (see [`CoinstrClient::get_stale_proposals`])
+    ///
+    /// The proposals of the policies that can't be found are neither active nor stale.
+    pub async fn get_active_proposals(
         &self,
-        name: S,
-        description: S,
-        descriptor: S,
-    ) -> Result<EventId, Error>
-    where
-        S: Into<String>,
-    {
-        let keys = self.client.keys();
-        let descriptor = descriptor.into();
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(EventId, SpendingProposal, EventId)>, Error> {
+        let (active, _stale) = self.partition_proposals(&blockchain, timeout).await?;
+        Ok(active)
+    }
 
-        let extracted_pubkeys = util::extract_public_keys(&descriptor)?;
+    /// Get the proposals that are expired or that spend UTXOs already spent
+    pub async fn get_stale_proposals(
+        &self,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(EventId, SpendingProposal, EventId)>, Error> {
+        let (_active, stale) = self.partition_proposals(&blockchain, timeout).await?;
+        Ok(stale)
+    }
 
-        // Generate a shared key
-        let shared_key = Keys::generate();
-        let policy = Policy::from_desc_or_policy(name, description, descriptor)?;
-        let content = nips::nip04::encrypt(
-            &shared_key.secret_key()?,
-            &shared_key.public_key(),
-            policy.as_json(),
-        )?;
-        let tags: Vec<Tag> = extracted_pubkeys
-            .iter()
-            .map(|p| Tag::PubKey(*p, None))
-            .collect();
-        // Publish policy with `shared_key` so every owner can delete it
-        let policy_event = EventBuilder::new(POLICY_KIND, content, &tags).to_event(&shared_key)?;
-        let policy_id = policy_event.id;
+    /// Delete the stale proposals, returning their ids
+    pub async fn delete_stale_proposals(
+        &self,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<EventId>, Error> {
+        let mut deleted: Vec<EventId> = Vec::new();
+        for (proposal_id, ..) in self.get_stale_proposals(blockchain, timeout).await? {
+            self.delete_proposal_by_id(proposal_id, timeout).await?;
+            deleted.push(proposal_id);
+        }
+        Ok(deleted)
+    }
 
-        // Publish the shared key
-        for pubkey in extracted_pubkeys.into_iter() {
-            let encrypted_shared_key = nips::nip04::encrypt(
-                &keys.secret_key()?,
-                &pubkey,
-                shared_key.secret_key()?.display_secret().to_string(),
-            )?;
-            let event = EventBuilder::new(
-                SHARED_KEY_KIND,
-                encrypted_shared_key,
-                &[Tag::Event(policy_id, None, None), Tag::PubKey(pubkey, None)],
-            )
-            .to_event(&keys)?;
-            let event_id = self.client.send_event(event).await?;
-            log::info!("Published shared key for {pubkey} at event {event_id}");
+    #[allow(clippy::type_complexity)]
+    async fn partition_proposals(
+        &self,
+        blockchain: &impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<
+        (
+            Vec<(EventId, SpendingProposal, EventId)>,
+            Vec<(EventId, SpendingProposal, EventId)>,
+        ),
+        Error,
+    > {
+        let proposals = self.get_proposals(timeout).await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let height: u32 = blockchain.get_height()?;
+        #[cfg(target_arch = "wasm32")]
+        let height: u32 = blockchain.get_height().await?;
+
+        // Unspent outpoints and unconfirmed txids, indexed by policy id
+        let mut wallets: HashMap<EventId, (Vec<OutPoint>, Vec<Txid>)> = HashMap::new();
+
+        let mut active = Vec::new();
+        let mut stale = Vec::new();
+
+        for (proposal_id, proposal, policy_id) in proposals.into_iter() {
+            if proposal.is_expired(height) {
+                stale.push((proposal_id, proposal, policy_id));
+                continue;
+            }
+
+            if !wallets.contains_key(&policy_id) {
+                // The policy may have been deleted (or migrated) since: its status is unknown
+                let policy = match self.get_policy_by_id(policy_id, timeout).await {
+                    Ok((policy, _shared_keys)) => policy,
+                    Err(Error::PolicyNotFound | Error::SharedKeysNotFound) => {
+                        log::warn!("Policy {policy_id} of proposal {proposal_id} not found");
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let wallet = self.wallet(policy.descriptor.to_string())?;
+                #[cfg(not(target_arch = "wasm32"))]
+                wallet.sync(blockchain, SyncOptions::default())?;
+                #[cfg(target_arch = "wasm32")]
+                wallet.sync(blockchain, SyncOptions::default()).await?;
+
+                let unspent: Vec<OutPoint> = wallet
+                    .list_unspent()?
+                    .into_iter()
+                    .map(|utxo| utxo.outpoint)
+                    .collect();
+                let unconfirmed: Vec<Txid> = wallet
+                    .list_transactions(false)?
+                    .into_iter()
+                    .filter(|tx| tx.confirmation_time.is_none())
+                    .map(|tx| tx.txid)
+                    .collect();
+                wallets.insert(policy_id, (unspent, unconfirmed));
+            }
+
+            let (unspent, unconfirmed) = wallets
+                .get(&policy_id)
+                .ok_or(Error::WalletSpendingPolicyNotFound)?;
+            let is_spent = match proposal.fee_bump {
+                // The inputs are spent by the transaction to replace, until it's confirmed
+                Some(FeeBump::Rbf(txid)) => !unconfirmed.contains(&txid),
+                _ => proposal
+                    .psbt
+                    .unsigned_tx
+                    .input
+                    .iter()
+                    .any(|txin| !unspent.contains(&txin.previous_output)),
+            };
+
+            if is_spent {
+                stale.push((proposal_id, proposal, policy_id));
+            } else {
+                active.push((proposal_id, proposal, policy_id));
+            }
         }
 
-        self.client.send_event(policy_event).await?;
+        Ok((active, stale))
+    }
 
-        Ok(policy_id)
+    /// Get the UTXO labels of a policy
+    pub async fn get_utxo_labels(
+        &self,
+        policy_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<OutPoint, String>, Error> {
+        let shared_keys = self.get_shared_key_by_policy_id(policy_id, timeout).await?;
+
+        let filter = Filter::new().event(policy_id).kind(UTXO_LABEL_KIND);
+        let mut events = self.client.get_events_of(vec![filter], timeout).await?;
+        events.sort_by_key(|event| event.created_at);
+
+        // Newer labels replace the older ones
+        let mut labels: HashMap<OutPoint, String> = HashMap::new();
+        for event in events.into_iter() {
+            if event.pubkey != shared_keys.public_key() {
+                log::warn!("UTXO label {} not signed by the shared key", event.id);
+                continue;
+            }
+
+            let content = encryption::decrypt(
+                &shared_keys.secret_key()?,
+                &shared_keys.public_key(),
+                &event,
+            )?;
+            let label = UtxoLabel::from_json(content)?;
+            if label.label.is_empty() {
+                labels.remove(&label.outpoint);
+            } else {
+                labels.insert(label.outpoint, label.label);
+            }
+        }
+
+        Ok(labels)
     }
 
-    /// Make a spending proposal
-    pub async fn spend<S>(
+    /// Label a UTXO of a policy
+    ///
+    /// An empty `label` removes the current one.
+    pub async fn save_utxo_label<S>(
         &self,
         policy_id: EventId,
-        to_address: Address,
-        amount: u64,
-        memo: S,
-        blockchain: impl Blockchain,
+        outpoint: OutPoint,
+        label: S,
         timeout: Option<Duration>,
     ) -> Result<EventId, Error>
     where
         S: Into<String>,
     {
-        // Get policy
-        let (policy, shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+        let shared_keys = self.get_shared_key_by_policy_id(policy_id, timeout).await?;
+        self.publish_utxo_label(policy_id, &shared_keys, outpoint, label)
+            .await
+    }
 
-        // Sync balance
-        let wallet = self.wallet(policy.descriptor.to_string())?;
-        #[cfg(not(target_arch = "wasm32"))]
-        wallet.sync(&blockchain, SyncOptions::default())?;
-        #[cfg(target_arch = "wasm32")]
-        wallet.sync(&blockchain, SyncOptions::default()).await?;
+    async fn publish_utxo_label<S>(
+        &self,
+        policy_id: EventId,
+        shared_keys: &Keys,
+        outpoint: OutPoint,
+        label: S,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let label = UtxoLabel::new(outpoint, label);
+        let content = encryption::encrypt(
+            &shared_keys.secret_key()?,
+            &shared_keys.public_key(),
+            label.as_json(),
+        )?;
+        let event = EventBuilder::new(
+            UTXO_LABEL_KIND,
+            content,
+            &[
+                Tag::Event(policy_id, None, None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(shared_keys)?;
 
-        // Get policies and specify which ones to use
-        let wallet_policy = wallet
-            .policies(KeychainKind::External)?
-            .ok_or(Error::WalletSpendingPolicyNotFound)?;
-        let mut path = BTreeMap::new();
-        path.insert(wallet_policy.id, vec![1]);
+        Ok(self.client.send_event(event).await?)
+    }
+
+    pub async fn save_policy<S>(
+        &self,
+        name: S,
+        description: S,
+        descriptor: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let policy = Policy::from_desc_or_policy(name, description, descriptor)?;
+        self.save_compiled_policy(policy, timeout).await
+    }
+
+    /// Save a miniscript policy of the nostr public keys of the members, with the MuSig2
+    /// aggregate of their last announced BIP-86 signer keys as internal key
+    /// (check [`Policy::from_miniscript_policy_with_musig2`])
+    pub async fn save_musig2_policy<S>(
+        &self,
+        name: S,
+        description: S,
+        policy: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let policy: String = policy.into();
+        // The extended keys are rejected by the policy
+        let members: Vec<XOnlyPublicKey> = util::extract_descriptor_keys(&policy)?
+            .into_iter()
+            .filter(|key| key.xpub.is_none())
+            .map(|key| key.public_key)
+            .collect();
+        let signers = self.get_bip86_signers(&members, timeout).await?;
+        let policy = Policy::from_miniscript_policy_with_musig2(
+            name.into(),
+            description.into(),
+            policy,
+            &signers,
+        )?;
+        self.save_compiled_policy(policy, timeout).await
+    }
+
+    /// Get the last BIP-86 signer key announced by each member
+    async fn get_bip86_signers(
+        &self,
+        members: &[XOnlyPublicKey],
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<XOnlyPublicKey, SignerKey>, Error> {
+        let mut signers: HashMap<XOnlyPublicKey, SignerKey> = HashMap::new();
+        for member in members.iter() {
+            let signer = self
+                .get_signer_keys(*member, timeout)
+                .await?
+                .into_iter()
+                .filter(|signer| {
+                    SignerPurpose::from_derivation_path(&signer.derivation_path)
+                        == Some(SignerPurpose::Bip86)
+                })
+                .last()
+                .ok_or(Error::MissingSignerKey(*member))?;
+            signers.insert(*member, signer);
+        }
+        Ok(signers)
+    }
+
+    /// Save a policy already built, like from a template or with a tweaked internal key
+    ///
+    /// If the policy has extended keys and its members aren't set, they are resolved from the
+    /// signer keys announced by the contacts (check [`CoinstrClient::resolve_members`]).
+    pub async fn save_compiled_policy(
+        &self,
+        mut policy: Policy,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        if policy.members.is_empty() && policy.member_keys()?.iter().any(|k| k.xpub.is_some()) {
+            let members = self.resolve_members(&policy, timeout).await?;
+            policy = policy.with_members(members);
+        }
+
+        // Generate a shared key
+        let shared_key = Keys::generate();
+        let extracted_pubkeys = policy.member_pubkeys()?;
+        self.publish_policy(&policy, &shared_key, extracted_pubkeys)
+            .await
+    }
+
+    /// Get the nostr public keys of the members of a policy from its keys: the extended keys
+    /// are matched with the signer keys announced by the contacts (or by the own key), while
+    /// the x-only keys are used as they are
+    ///
+    /// Fail if the same fingerprint or xpub is claimed by more than one public key.
+    pub async fn resolve_members(
+        &self,
+        policy: &Policy,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<XOnlyPublicKey>, Error> {
+        let mut candidates: Vec<XOnlyPublicKey> =
+            self.get_contacts(timeout).await?.into_keys().collect();
+        candidates.push(self.client.keys().public_key());
+
+        let mut signers: Vec<(XOnlyPublicKey, SignerKey)> = Vec::new();
+        for candidate in candidates.into_iter() {
+            for signer in self.get_signer_keys(candidate, timeout).await?.into_iter() {
+                signers.push((candidate, signer));
+            }
+        }
+
+        let mut members: Vec<XOnlyPublicKey> = Vec::new();
+        for key in policy.member_keys()?.into_iter() {
+            let member: XOnlyPublicKey = match key.xpub {
+                Some(xpub) => {
+                    let mut owners: Vec<XOnlyPublicKey> = Vec::new();
+                    for (owner, signer) in signers.iter() {
+                        if (signer.xpub == xpub || signer.fingerprint == key.fingerprint)
+                            && !owners.contains(owner)
+                        {
+                            owners.push(*owner);
+                        }
+                    }
+                    match owners.as_slice() {
+                        [] => return Err(Error::SignerOwnerNotFound(key.fingerprint)),
+                        [owner] => *owner,
+                        _ => return Err(Error::DuplicateSignerClaim(key.fingerprint, owners)),
+                    }
+                }
+                None => key.public_key,
+            };
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+        Ok(members)
+    }
+
+    /// Start the collaborative creation of a policy
+    ///
+    /// The draft is sent to the `members` (in the order of the `template` keys), that answer
+    /// with their signer key. Return the draft id.
+    pub async fn create_policy_draft<S>(
+        &self,
+        name: S,
+        description: S,
+        template: PolicyTemplate,
+        members: Vec<XOnlyPublicKey>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        // Check the template
+        let pubkeys: Vec<String> = members.iter().map(|m| m.to_string()).collect();
+        template.build(&pubkeys).map_err(policy::Error::from)?;
+
+        let draft = PolicyDraft::new(name, description, template, members);
+        self.send_policy_draft(None, &draft).await
+    }
+
+    /// Publish a copy of the draft for the coordinator and for each member
+    ///
+    /// The copy of the coordinator is published first: its id is the draft id,
+    /// and it's referenced by the other copies and by the updates.
+    async fn send_policy_draft(
+        &self,
+        draft_id: Option<EventId>,
+        draft: &PolicyDraft,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+
+        let mut recipients: Vec<XOnlyPublicKey> = vec![keys.public_key()];
+        recipients.extend(draft.members.iter().filter(|m| **m != keys.public_key()));
+
+        let mut draft_id = draft_id;
+        for pubkey in recipients.into_iter() {
+            let content = encryption::encrypt(&keys.secret_key()?, &pubkey, draft.as_json())?;
+            let mut tags = vec![Tag::PubKey(pubkey, None), EncryptionVersion::Nip44.tag()];
+            if let Some(draft_id) = draft_id {
+                tags.push(Tag::Event(draft_id, None, None));
+            }
+            let event = EventBuilder::new(POLICY_DRAFT_KIND, content, &tags).to_event(&keys)?;
+            let event_id = self.client.send_event(event).await?;
+            draft_id.get_or_insert(event_id);
+        }
+
+        draft_id.ok_or(Error::PolicyDraftNotFound)
+    }
+
+    /// Get the policy drafts sent to me, with their coordinator
+    pub async fn get_policy_drafts(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<EventId, (PolicyDraft, XOnlyPublicKey)>, Error> {
+        let keys = self.client.keys();
+        let filter = Filter::new()
+            .pubkey(keys.public_key())
+            .kind(POLICY_DRAFT_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+        self.resolve_policy_drafts(events, timeout).await
+    }
+
+    pub async fn get_policy_draft_by_id(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<(PolicyDraft, XOnlyPublicKey), Error> {
+        let keys = self.client.keys();
+        let filters = vec![
+            Filter::new()
+                .pubkey(keys.public_key())
+                .id(draft_id)
+                .kind(POLICY_DRAFT_KIND),
+            Filter::new()
+                .pubkey(keys.public_key())
+                .event(draft_id)
+                .kind(POLICY_DRAFT_KIND),
+        ];
+        let events = self.client.get_events_of(filters, timeout).await?;
+        self.resolve_policy_drafts(events, timeout)
+            .await?
+            .remove(&draft_id)
+            .ok_or(Error::PolicyDraftNotFound)
+    }
+
+    /// Decrypt the draft copies, keeping the last one for each draft
+    ///
+    /// Copies not authored by the coordinator (the author of the draft id) are discarded.
+    async fn resolve_policy_drafts(
+        &self,
+        events: Vec<Event>,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<EventId, (PolicyDraft, XOnlyPublicKey)>, Error> {
+        let keys = self.client.keys();
+
+        let mut draft_ids: Vec<EventId> = Vec::new();
+        for event in events.iter() {
+            let draft_id = util::extract_first_event_id(event).unwrap_or(event.id);
+            if !draft_ids.contains(&draft_id) {
+                draft_ids.push(draft_id);
+            }
+        }
+        if draft_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // Get the coordinators
+        let filters = draft_ids
+            .into_iter()
+            .map(|draft_id| Filter::new().id(draft_id).kind(POLICY_DRAFT_KIND))
+            .collect();
+        let coordinators: HashMap<EventId, XOnlyPublicKey> = self
+            .client
+            .get_events_of(filters, timeout)
+            .await?
+            .into_iter()
+            .map(|event| (event.id, event.pubkey))
+            .collect();
+
+        let mut drafts: HashMap<EventId, (Timestamp, PolicyDraft, XOnlyPublicKey)> = HashMap::new();
+        for event in events.into_iter() {
+            let draft_id = util::extract_first_event_id(&event).unwrap_or(event.id);
+            match coordinators.get(&draft_id) {
+                Some(coordinator) if *coordinator == event.pubkey => (),
+                _ => {
+                    log::warn!(
+                        "Draft {} not sent by the coordinator of draft {draft_id}",
+                        event.id
+                    );
+                    continue;
+                }
+            }
+
+            if let Some((created_at, ..)) = drafts.get(&draft_id) {
+                if *created_at > event.created_at {
+                    continue;
+                }
+            }
+
+            let content = encryption::decrypt(&keys.secret_key()?, &event.pubkey, &event)?;
+            let draft = PolicyDraft::from_json(content)?;
+            drafts.insert(draft_id, (event.created_at, draft, event.pubkey));
+        }
+
+        Ok(drafts
+            .into_iter()
+            .map(|(draft_id, (_, draft, coordinator))| (draft_id, (draft, coordinator)))
+            .collect())
+    }
+
+    /// Get the signer keys and the confirmations sent by the members (coordinator only)
+    pub async fn get_policy_draft_status(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<DraftStatus, Error> {
+        let keys = self.client.keys();
+
+        let (draft, coordinator) = self.get_policy_draft_by_id(draft_id, timeout).await?;
+        if coordinator != keys.public_key() {
+            return Err(Error::NotDraftCoordinator);
+        }
+
+        let filter = Filter::new()
+            .pubkey(keys.public_key())
+            .event(draft_id)
+            .kind(POLICY_DRAFT_RESPONSE_KIND);
+        let mut events = self.client.get_events_of(vec![filter], timeout).await?;
+        events.sort_by_key(|event| event.created_at);
+
+        let mut signers: HashMap<XOnlyPublicKey, SignerKey> = HashMap::new();
+        let mut confirmed_by: Vec<XOnlyPublicKey> = Vec::new();
+        for event in events.into_iter() {
+            if !draft.members.contains(&event.pubkey) {
+                log::warn!(
+                    "Response {} to draft {draft_id} not sent by a member",
+                    event.id
+                );
+                continue;
+            }
+
+            let content = encryption::decrypt(&keys.secret_key()?, &event.pubkey, &event)?;
+            match DraftResponse::from_json(content)? {
+                DraftResponse::Join(announcement) => {
+                    if let Err(e) = announcement.verify(&event.pubkey) {
+                        log::warn!(
+                            "Signer key of draft response {} not owned by the author: {e}",
+                            event.id
+                        );
+                        continue;
+                    }
+                    signers.insert(event.pubkey, announcement.signer);
+                }
+                DraftResponse::Confirm(descriptor) => {
+                    if draft.descriptor.as_ref() == Some(&descriptor)
+                        && !confirmed_by.contains(&event.pubkey)
+                    {
+                        confirmed_by.push(event.pubkey);
+                    }
+                }
+            }
+        }
+
+        Ok(DraftStatus {
+            draft,
+            signers,
+            confirmed_by,
+        })
+    }
+
+    /// Join a policy draft, sending the signer key derived from the seed to the coordinator
+    pub async fn join_policy_draft(
+        &self,
+        draft_id: EventId,
+        purpose: SignerPurpose,
+        account: u32,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+        let root_key = self.root_key.as_ref().ok_or(Error::SignerRootKeyNotFound)?;
+
+        let (draft, coordinator) = self.get_policy_draft_by_id(draft_id, timeout).await?;
+        if !draft.members.contains(&keys.public_key()) {
+            return Err(Error::NotDraftMember);
+        }
+
+        let announcement = SignerAnnouncement::new(root_key, purpose, account, &keys.public_key())?;
+        self.send_draft_response(draft_id, coordinator, DraftResponse::Join(announcement))
+            .await
+    }
+
+    /// Assemble the descriptor of a draft, once every member sent a signer key (coordinator only)
+    ///
+    /// The draft is sent again to the members, with the descriptor to confirm.
+    pub async fn assemble_policy_draft(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Policy, Error> {
+        let status = self.get_policy_draft_status(draft_id, timeout).await?;
+        let pending = status.pending_signers();
+        if !pending.is_empty() {
+            return Err(Error::PendingDraftSigners(pending));
+        }
+
+        let mut draft = status.draft;
+        let signer_keys: Vec<String> = draft
+            .members
+            .iter()
+            .filter_map(|member| status.signers.get(member))
+            .map(|signer| signer.multipath_descriptor_key())
+            .collect();
+        let policy = Policy::from_template(
+            draft.name.clone(),
+            draft.description.clone(),
+            &draft.template,
+            &signer_keys,
+        )?
+        .with_members(draft.members.clone());
+
+        draft.descriptor = Some(policy.descriptor.to_string());
+        self.send_policy_draft(Some(draft_id), &draft).await?;
+
+        Ok(policy)
+    }
+
+    /// Confirm the assembled descriptor of a draft
+    ///
+    /// The descriptor must include the signer key of the seed.
+    pub async fn confirm_policy_draft(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+        let root_key = self.root_key.as_ref().ok_or(Error::SignerRootKeyNotFound)?;
+
+        let (draft, coordinator) = self.get_policy_draft_by_id(draft_id, timeout).await?;
+        if !draft.members.contains(&keys.public_key()) {
+            return Err(Error::NotDraftMember);
+        }
+
+        let descriptor = draft.descriptor.ok_or(Error::PolicyDraftNotAssembled)?;
+        if signer::derive_descriptor_xprvs(root_key, descriptor.clone())?.is_empty() {
+            return Err(Error::SignerNotInDescriptor);
+        }
+
+        self.send_draft_response(draft_id, coordinator, DraftResponse::Confirm(descriptor))
+            .await
+    }
+
+    async fn send_draft_response(
+        &self,
+        draft_id: EventId,
+        coordinator: XOnlyPublicKey,
+        response: DraftResponse,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+        let content = encryption::encrypt(&keys.secret_key()?, &coordinator, response.as_json())?;
+        let event = EventBuilder::new(
+            POLICY_DRAFT_RESPONSE_KIND,
+            content,
+            &[
+                Tag::Event(draft_id, None, None),
+                Tag::PubKey(coordinator, None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        Ok(self.client.send_event(event).await?)
+    }
+
+    /// Save the policy of a draft, once every member confirmed the descriptor (coordinator only)
+    ///
+    /// The draft is deleted.
+    pub async fn finalize_policy_draft(
+        &self,
+        draft_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+
+        let status = self.get_policy_draft_status(draft_id, timeout).await?;
+        let descriptor = status
+            .draft
+            .descriptor
+            .clone()
+            .ok_or(Error::PolicyDraftNotAssembled)?;
+        let pending = status.pending_confirmations();
+        if !pending.is_empty() {
+            return Err(Error::PendingDraftConfirmations(pending));
+        }
+
+        let draft = status.draft;
+        let policy = Policy::from_descriptor(draft.name, draft.description, descriptor)?
+            .with_members(draft.members);
+        let policy_id = self.save_compiled_policy(policy, timeout).await?;
+
+        // Delete the draft copies
+        let filter = Filter::new().event(draft_id).kind(POLICY_DRAFT_KIND);
+        let mut ids: Vec<EventId> = self
+            .client
+            .get_events_of(vec![filter], timeout)
+            .await?
+            .into_iter()
+            .filter(|e| e.pubkey == keys.public_key())
+            .map(|e| e.id)
+            .collect();
+        ids.push(draft_id);
+        let event = EventBuilder::delete::<String>(ids, None).to_event(&keys)?;
+        self.client.send_event(event).await?;
+
+        Ok(policy_id)
+    }
+
+    /// Publish a policy and send the shared key to its members
+    async fn publish_policy(
+        &self,
+        policy: &Policy,
+        shared_key: &Keys,
+        extracted_pubkeys: Vec<XOnlyPublicKey>,
+    ) -> Result<EventId, Error> {
+        let content = encryption::encrypt(
+            &shared_key.secret_key()?,
+            &shared_key.public_key(),
+            policy.as_json(),
+        )?;
+        let mut tags: Vec<Tag> = extracted_pubkeys
+            .iter()
+            .map(|p| Tag::PubKey(*p, None))
+            .collect();
+        tags.push(EncryptionVersion::Nip44.tag());
+        // Publish policy with `shared_key` so every owner can delete it
+        let policy_event = EventBuilder::new(POLICY_KIND, content, &tags).to_event(shared_key)?;
+        let policy_id = policy_event.id;
+
+        // Publish the shared key
+        self.send_shared_key(policy_id, shared_key, extracted_pubkeys, None)
+            .await?;
+
+        self.client.send_event(policy_event).await?;
+
+        Ok(policy_id)
+    }
+
+    /// Send the shared key of a policy to its members
+    async fn send_shared_key(
+        &self,
+        policy_id: EventId,
+        shared_key: &Keys,
+        extracted_pubkeys: Vec<XOnlyPublicKey>,
+        superseded: Option<XOnlyPublicKey>,
+    ) -> Result<(), Error> {
+        let keys = self.client.keys();
+
+        for pubkey in extracted_pubkeys.into_iter() {
+            let encrypted_shared_key = encryption::encrypt(
+                &keys.secret_key()?,
+                &pubkey,
+                shared_key.secret_key()?.display_secret().to_string(),
+            )?;
+            let mut tags = vec![
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(pubkey, None),
+                EncryptionVersion::Nip44.tag(),
+            ];
+            if let Some(superseded) = superseded {
+                tags.push(Tag::Generic(
+                    TagKind::Custom(SUPERSEDED_TAG.to_string()),
+                    vec![superseded.to_string()],
+                ));
+            }
+            let event =
+                EventBuilder::new(SHARED_KEY_KIND, encrypted_shared_key, &tags).to_event(&keys)?;
+            let event_id = self.client.send_event(event).await?;
+            log::info!("Published shared key for {pubkey} at event {event_id}");
+        }
+
+        Ok(())
+    }
+
+    /// Replace the shared key of a policy
+    ///
+    /// The new key is sent to every member but the `excluded` ones, superseding the current one,
+    /// and the policy is republished encrypted with it, keeping the same policy id. The UTXO labels
+    /// are republished too. Policies with pending proposals can't rotate the shared key.
+    ///
+    /// The excluded members (like a compromised device) can't read the new proposals and their
+    /// approvals are discarded, but their keys are still in the descriptor: to revoke them,
+    /// the funds must be moved to a new policy.
+    ///
+    /// Only the creator of the policy can rotate the shared key: the members discard the keys
+    /// sent by the others.
+    pub async fn rotate_shared_key(
+        &self,
+        policy_id: EventId,
+        excluded: Vec<XOnlyPublicKey>,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+        if self.get_policy_creator(policy_id, timeout).await? != Some(keys.public_key()) {
+            return Err(Error::NotPolicyCreator);
+        }
+
+        // Check pending proposals
+        if self
+            .get_proposals(timeout)
+            .await?
+            .into_iter()
+            .any(|(_, _, id)| id == policy_id)
+        {
+            return Err(Error::PendingProposals);
+        }
+
+        let (mut policy, shared_key) = self.get_policy_by_id(policy_id, timeout).await?;
+        let labels = self.get_utxo_labels(policy_id, timeout).await?;
+
+        // Remove the excluded members
+        let mut extracted_pubkeys = policy.member_pubkeys()?;
+        if let Some(member) = excluded.iter().find(|m| !extracted_pubkeys.contains(m)) {
+            return Err(Error::MemberNotFound(*member));
+        }
+        if !excluded.is_empty() {
+            extracted_pubkeys.retain(|m| !excluded.contains(m));
+            policy = policy.with_members(extracted_pubkeys.clone());
+        }
+
+        // Generate and send the new shared key
+        let new_shared_key = Keys::generate();
+        self.send_shared_key(
+            policy_id,
+            &new_shared_key,
+            extracted_pubkeys.clone(),
+            Some(shared_key.public_key()),
+        )
+        .await?;
+
+        // Republish the policy
+        let content = encryption::encrypt(
+            &new_shared_key.secret_key()?,
+            &new_shared_key.public_key(),
+            policy.as_json(),
+        )?;
+        let mut tags: Vec<Tag> = extracted_pubkeys
+            .iter()
+            .map(|p| Tag::PubKey(*p, None))
+            .collect();
+        tags.push(Tag::Event(policy_id, None, None));
+        tags.push(EncryptionVersion::Nip44.tag());
+        let event = EventBuilder::new(POLICY_KIND, content, &tags).to_event(&new_shared_key)?;
+        let event_id = self.client.send_event(event).await?;
+
+        // Republish the UTXO labels
+        for (outpoint, label) in labels.into_iter() {
+            self.publish_utxo_label(policy_id, &new_shared_key, outpoint, label)
+                .await?;
+        }
+
+        Ok(event_id)
+    }
+
+    /// Re-encrypt a NIP-04 policy with NIP-44
+    ///
+    /// The policy is republished, so it gets a new id: the shared key and the dependent events
+    /// are published again for the new policy, and the old policy is deleted once they are all
+    /// moved. The dependent events are the UTXO labels and the pending proposals, with the
+    /// signatures of their approvals combined in the PSBT (the key path sessions of the
+    /// proposals must be started again).
+    pub async fn migrate_policy(
+        &self,
+        policy_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        // Get policy event
+        let filter = Filter::new().id(policy_id).kind(POLICY_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+        let policy_event = events.first().ok_or(Error::PolicyNotFound)?;
+        if EncryptionVersion::from_event(policy_event) == EncryptionVersion::Nip44 {
+            return Ok(policy_id);
+        }
+
+        let (policy, shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+        let labels = self.get_utxo_labels(policy_id, timeout).await?;
+        let proposals: Vec<(EventId, SpendingProposal)> = self
+            .get_proposals(timeout)
+            .await?
+            .into_iter()
+            .filter(|(_, _, id)| *id == policy_id)
+            .map(|(proposal_id, proposal, _)| (proposal_id, proposal))
+            .collect();
+
+        // Republish
+        let extracted_pubkeys = policy.member_pubkeys()?;
+        let new_policy_id = self
+            .publish_policy(&policy, &shared_keys, extracted_pubkeys)
+            .await?;
+        for (outpoint, label) in labels.into_iter() {
+            self.save_utxo_label(new_policy_id, outpoint, label, timeout)
+                .await?;
+        }
+        for (proposal_id, proposal) in proposals.iter() {
+            let (_, approvals) = self
+                .get_signed_psbts_by_proposal_id(*proposal_id, timeout)
+                .await?;
+            let mut proposal = proposal.clone();
+            proposal.psbt = combine_approvals(proposal.psbt, approvals);
+            self.send_proposal(new_policy_id, &policy, &shared_keys, &proposal)
+                .await?;
+        }
+
+        // Delete the old policy, with its proposals and their approvals
+        for (proposal_id, _) in proposals.into_iter() {
+            self.delete_proposal_by_id(proposal_id, timeout).await?;
+        }
+        self.delete_policy_by_id(policy_id, timeout).await?;
+
+        Ok(new_policy_id)
+    }
+
+    /// Make a spending proposal
+    ///
+    /// If `drain_to` is set, all the spendable UTXOs (check `coin_control`) are spent and what
+    /// remains after paying the `recipients` and the fee is sent to that address.
+    ///
+    /// If `policy_path` is `None`, the cheapest spending path that can be satisfied now is selected.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn spend<S>(
+        &self,
+        policy_id: EventId,
+        recipients: Vec<Recipient>,
+        drain_to: Option<Address>,
+        memo: S,
+        fee: Fee,
+        rbf: bool,
+        coin_control: CoinControl,
+        policy_path: Option<PolicyPath>,
+        expiry: Option<Expiry>,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        if recipients.is_empty() && drain_to.is_none() {
+            return Err(Error::NoRecipients);
+        }
+
+        // Get policy
+        let (policy, shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        // Sync balance
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        #[cfg(not(target_arch = "wasm32"))]
+        wallet.sync(&blockchain, SyncOptions::default())?;
+        #[cfg(target_arch = "wasm32")]
+        wallet.sync(&blockchain, SyncOptions::default()).await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let height: u32 = blockchain.get_height()?;
+        #[cfg(target_arch = "wasm32")]
+        let height: u32 = blockchain.get_height().await?;
+
+        // Get the spending path to use
+        let path: PolicyPath = match policy_path {
+            Some(path) => path,
+            None => {
+                let outpoints: Vec<OutPoint> = wallet
+                    .list_unspent()?
+                    .into_iter()
+                    .map(|utxo| utxo.outpoint)
+                    .filter(|outpoint| coin_control.is_spendable(outpoint))
+                    .collect();
+                self.select_policy_path(&policy, &wallet, &outpoints, height)?
+            }
+        };
+        let condition = path_condition(&wallet, &path)?;
+
+        // Get the fee rate
+        let fee_rate: FeeRate = match fee {
+            Fee::Rate(rate) => FeeRate::from_sat_per_vb(rate),
+            Fee::Absolute(..) => FeeRate::default(),
+            Fee::Target(target) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let fee_rate: FeeRate = blockchain.estimate_fee(target)?;
+                #[cfg(target_arch = "wasm32")]
+                let fee_rate: FeeRate = blockchain.estimate_fee(target).await?;
+                fee_rate
+            }
+        };
+
+        // Build the transaction
+        let mut builder = wallet.build_tx();
+        builder
+            .set_recipients(
+                recipients
+                    .iter()
+                    .map(|r| (r.address.script_pubkey(), r.amount))
+                    .collect(),
+            )
+            .policy_path(path.clone(), KeychainKind::External)
+            .policy_path(path.clone(), KeychainKind::Internal)
+            .current_height(height);
+
+        // Timelocks required by the spending path (the nSequence is set by the policy path)
+        if let Some(lock_time) = policy::path::lock_time(&condition, height) {
+            builder.nlocktime(lock_time);
+        }
+
+        // Coin control
+        let manually_selected_only: bool =
+            coin_control.manually_selected_only && !coin_control.must_spend.is_empty();
+        if !coin_control.must_spend.is_empty() {
+            builder.add_utxos(&coin_control.must_spend)?;
+        }
+        if manually_selected_only {
+            builder.manually_selected_only();
+        }
+        builder.unspendable(coin_control.do_not_spend);
+
+        if let Some(drain_to) = &drain_to {
+            if !manually_selected_only {
+                builder.drain_wallet();
+            }
+            builder.drain_to(drain_to.script_pubkey());
+        }
+
+        match fee {
+            Fee::Absolute(fee) => builder.fee_absolute(fee),
+            _ => builder.fee_rate(fee_rate),
+        };
+
+        if rbf {
+            builder.enable_rbf();
+        }
+
+        // Build the PSBT
+        let (psbt, details) = builder.finish()?;
+        if !policy::path::has_timelocks(&condition, &psbt.unsigned_tx) {
+            return Err(Error::PsbtTimelockMismatch);
+        }
+
+        // Add the drain output to the recipients
+        let mut recipients = recipients;
+        if let Some(drain_to) = drain_to {
+            let script = drain_to.script_pubkey();
+            let total: u64 = psbt
+                .unsigned_tx
+                .output
+                .iter()
+                .filter(|o| o.script_pubkey == script)
+                .map(|o| o.value)
+                .sum();
+            let sent: u64 = recipients
+                .iter()
+                .filter(|r| r.address == drain_to)
+                .map(|r| r.amount)
+                .sum();
+            recipients.push(Recipient::new(drain_to, total.saturating_sub(sent)));
+        }
+
+        // Create spending proposal
+        let mut proposal = SpendingProposal::new(recipients, memo, psbt, Some(path), details.fee);
+        proposal.expiry = expiry;
+        self.publish_proposal(policy_id, &policy, &shared_keys, &proposal)
+            .await
+    }
+
+    /// Make a proposal to bump the fee of a stuck transaction
+    ///
+    /// If the transaction signals RBF, a replacement is built. Otherwise, a child transaction
+    /// spending the outputs of the policy (CPFP) is built, paying enough fee for both.
+    ///
+    /// If `policy_path` is `None`, the cheapest spending path that can be satisfied now is selected.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn bump_fee<S>(
+        &self,
+        policy_id: EventId,
+        txid: Txid,
+        memo: S,
+        fee: Fee,
+        policy_path: Option<PolicyPath>,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        // Get policy
+        let (policy, shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        // Sync balance
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        #[cfg(not(target_arch = "wasm32"))]
+        wallet.sync(&blockchain, SyncOptions::default())?;
+        #[cfg(target_arch = "wasm32")]
+        wallet.sync(&blockchain, SyncOptions::default()).await?;
+
+        // Get the stuck transaction
+        let details = wallet
+            .get_tx(&txid, true)?
+            .ok_or(Error::TransactionNotFound)?;
+        if details.confirmation_time.is_some() {
+            return Err(Error::TransactionAlreadyConfirmed);
+        }
+        let tx = details.transaction.ok_or(Error::TransactionNotFound)?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let height: u32 = blockchain.get_height()?;
+        #[cfg(target_arch = "wasm32")]
+        let height: u32 = blockchain.get_height().await?;
+
+        // Get the fee rate
+        let fee_rate: FeeRate = match fee {
+            Fee::Rate(rate) => FeeRate::from_sat_per_vb(rate),
+            Fee::Absolute(..) => FeeRate::default(),
+            Fee::Target(target) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let fee_rate: FeeRate = blockchain.estimate_fee(target)?;
+                #[cfg(target_arch = "wasm32")]
+                let fee_rate: FeeRate = blockchain.estimate_fee(target).await?;
+                fee_rate
+            }
+        };
+
+        let proposal = if tx.input.iter().any(|txin| txin.sequence.is_rbf()) {
+            // Replace the transaction, spending the same inputs
+            let path: PolicyPath = match policy_path {
+                Some(path) => path,
+                None => {
+                    let outpoints: Vec<OutPoint> =
+                        tx.input.iter().map(|txin| txin.previous_output).collect();
+                    self.select_policy_path(&policy, &wallet, &outpoints, height)?
+                }
+            };
+            let condition = path_condition(&wallet, &path)?;
+
+            let mut builder = wallet.build_fee_bump(txid)?;
+            builder
+                .policy_path(path.clone(), KeychainKind::External)
+                .policy_path(path.clone(), KeychainKind::Internal)
+                .current_height(height)
+                .enable_rbf();
+            if let Some(lock_time) = policy::path::lock_time(&condition, height) {
+                builder.nlocktime(lock_time);
+            }
+            match fee {
+                Fee::Absolute(fee) => builder.fee_absolute(fee),
+                _ => builder.fee_rate(fee_rate),
+            };
+            let (psbt, details) = builder.finish()?;
+            if !policy::path::has_timelocks(&condition, &psbt.unsigned_tx) {
+                return Err(Error::PsbtTimelockMismatch);
+            }
+
+            // The outputs that don't belong to the policy are the payments to keep
+            let mut recipients: Vec<Recipient> = Vec::new();
+            for txout in psbt.unsigned_tx.output.iter() {
+                if !wallet.is_mine(&txout.script_pubkey)? {
+                    if let Some(address) = Address::from_script(&txout.script_pubkey, self.network)
+                    {
+                        recipients.push(Recipient::new(address, txout.value));
+                    }
+                }
+            }
+
+            let mut proposal =
+                SpendingProposal::new(recipients, memo, psbt, Some(path), details.fee);
+            proposal.fee_bump = Some(FeeBump::Rbf(txid));
+            proposal
+        } else {
+            // Spend the outputs of the policy with a child transaction
+            let mut outpoints: Vec<OutPoint> = Vec::new();
+            for (vout, txout) in tx.output.iter().enumerate() {
+                if wallet.is_mine(&txout.script_pubkey)? {
+                    outpoints.push(OutPoint::new(txid, vout as u32));
+                }
+            }
+            if outpoints.is_empty() {
+                return Err(Error::CannotBumpFee);
+            }
+
+            let path: PolicyPath = match policy_path {
+                Some(path) => path,
+                None => self.select_policy_path(&policy, &wallet, &outpoints, height)?,
+            };
+            let condition = path_condition(&wallet, &path)?;
+
+            let address = wallet.get_internal_address(AddressIndex::New)?.address;
+            let build_child = |fee: Fee| {
+                let mut builder = wallet.build_tx();
+                builder
+                    .add_utxos(&outpoints)?
+                    .manually_selected_only()
+                    .drain_to(address.script_pubkey())
+                    .policy_path(path.clone(), KeychainKind::External)
+                    .policy_path(path.clone(), KeychainKind::Internal)
+                    .current_height(height)
+                    .enable_rbf();
+                if let Some(lock_time) = policy::path::lock_time(&condition, height) {
+                    builder.nlocktime(lock_time);
+                }
+                match fee {
+                    Fee::Absolute(fee) => builder.fee_absolute(fee),
+                    _ => builder.fee_rate(fee_rate),
+                };
+                builder.finish()
+            };
+
+            let (psbt, details) = match fee {
+                Fee::Absolute(..) => build_child(fee)?,
+                _ => {
+                    // Pay the fee of the package at the target fee rate
+                    let (_, child) = build_child(fee)?;
+                    let child_fee: u64 = child.fee.unwrap_or_default();
+                    let child_vsize: usize =
+                        (child_fee as f32 / fee_rate.as_sat_per_vb()).ceil() as usize;
+                    let package_fee: u64 = fee_rate.fee_vb(tx.vsize() + child_vsize);
+                    let parent_fee: u64 = details.fee.unwrap_or_default();
+                    let fee: u64 = package_fee.saturating_sub(parent_fee).max(child_fee);
+                    build_child(Fee::Absolute(fee))?
+                }
+            };
+            if !policy::path::has_timelocks(&condition, &psbt.unsigned_tx) {
+                return Err(Error::PsbtTimelockMismatch);
+            }
+
+            let amount: u64 = psbt.unsigned_tx.output.iter().map(|o| o.value).sum();
+            let recipients = vec![Recipient::new(address, amount)];
+            let mut proposal =
+                SpendingProposal::new(recipients, memo, psbt, Some(path), details.fee);
+            proposal.fee_bump = Some(FeeBump::Cpfp(txid));
+            proposal
+        };
+
+        self.publish_proposal(policy_id, &policy, &shared_keys, &proposal)
+            .await
+    }
+
+    /// Check how many blocks are left before the relative timelock paths of a policy activate
+    /// and, if any UTXO is under `threshold` blocks, make a proposal that sends them back to
+    /// the policy with a path without relative timelocks, restarting them
+    ///
+    /// The UTXOs already spent by a proposal that isn't expired aren't refreshed again,
+    /// nor the `do_not_spend` ones (like the frozen UTXOs).
+    ///
+    /// Only the block based relative timelocks are tracked: the policies with a time based one
+    /// are not supported.
+    pub async fn heartbeat(
+        &self,
+        policy_id: EventId,
+        threshold: u32,
+        fee: Fee,
+        do_not_spend: Vec<OutPoint>,
+        blockchain: impl Blockchain,
+        timeout: Option<Duration>,
+    ) -> Result<Heartbeat, Error> {
+        // Get policy
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+        if self
+            .policy_branches(&policy)?
+            .iter()
+            .any(|branch| branch.has_time_based_csv())
+        {
+            return Err(Error::TimeBasedCsvNotSupported);
+        }
+
+        // Sync balance
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        #[cfg(not(target_arch = "wasm32"))]
+        wallet.sync(&blockchain, SyncOptions::default())?;
+        #[cfg(target_arch = "wasm32")]
+        wallet.sync(&blockchain, SyncOptions::default()).await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let height: u32 = blockchain.get_height()?;
+        #[cfg(target_arch = "wasm32")]
+        let height: u32 = blockchain.get_height().await?;
+
+        let mut heartbeat = Heartbeat::default();
+        for utxo in self.get_maturity(&policy, &wallet)?.into_iter() {
+            if let Some(blocks_left) = utxo.blocks_left(height) {
+                heartbeat.blocks_left.insert(utxo.outpoint, blocks_left);
+            }
+        }
+
+        // UTXOs already spent by a proposal
+        let mut pending: Vec<OutPoint> = Vec::new();
+        for (_proposal_id, proposal, proposal_policy_id) in self.get_proposals(timeout).await? {
+            if proposal_policy_id == policy_id && !proposal.is_expired(height) {
+                pending.extend(
+                    proposal
+                        .psbt
+                        .unsigned_tx
+                        .input
+                        .iter()
+                        .map(|txin| txin.previous_output),
+                );
+            }
+        }
+
+        let must_spend: Vec<OutPoint> = heartbeat
+            .blocks_left
+            .iter()
+            .filter(|(outpoint, blocks_left)| {
+                **blocks_left < threshold
+                    && !pending.contains(outpoint)
+                    && !do_not_spend.contains(outpoint)
+            })
+            .map(|(outpoint, _)| *outpoint)
+            .collect();
+        if must_spend.is_empty() {
+            return Ok(heartbeat);
+        }
+
+        // Refresh with the cheapest path without relative timelocks
+        let path: PolicyPath = self
+            .policy_branches(&policy)?
+            .into_iter()
+            .filter(|branch| {
+                branch.condition.csv.is_none()
+                    && branch.is_satisfiable(height, Timestamp::now().as_u64(), &[])
+            })
+            .min_by_key(|branch| branch.signatures)
+            .map(|branch| branch.path)
+            .ok_or(Error::NoSatisfiableBranch)?;
+
+        let address = wallet.get_address(AddressIndex::New)?.address;
+        let coin_control = CoinControl {
+            must_spend,
+            do_not_spend,
+            manually_selected_only: true,
+        };
+        let proposal_id = self
+            .spend(
+                policy_id,
+                Vec::new(),
+                Some(address),
+                "Heartbeat: refresh the relative timelocks",
+                fee,
+                true,
+                coin_control,
+                Some(path),
+                None,
+                blockchain,
+                timeout,
+            )
+            .await?;
+        heartbeat.refresh = Some(proposal_id);
+
+        Ok(heartbeat)
+    }
+
+    /// Check that the PSBT of a proposal pays the declared recipients,
+    /// sends the change back to the policy and has a sane fee
+    fn verify_proposal(
+        &self,
+        policy: &Policy,
+        wallet: &Wallet<MemoryDatabase>,
+        proposal: &SpendingProposal,
+    ) -> Result<(), Error> {
+        let tx = &proposal.psbt.unsigned_tx;
+
+        // Match every recipient with an output
+        let mut outputs: Vec<usize> = (0..tx.output.len()).collect();
+        for recipient in proposal.recipients.iter() {
+            let script = recipient.address.script_pubkey();
+            let pos = outputs
+                .iter()
+                .position(|i| {
+                    let txout = &tx.output[*i];
+                    txout.script_pubkey == script && txout.value == recipient.amount
+                })
+                .ok_or_else(|| Error::PsbtRecipientMismatch {
+                    address: recipient.address.clone(),
+                    amount: recipient.amount,
+                })?;
+            outputs.remove(pos);
+        }
+
+        // The remaining outputs must be a change of the policy (of any keychain)
+        let descriptors = [
+            wallet.get_descriptor_for_keychain(KeychainKind::External),
+            wallet.get_descriptor_for_keychain(KeychainKind::Internal),
+        ];
+        for i in outputs.into_iter() {
+            let script = &tx.output[i].script_pubkey;
+            let mut indexes: Vec<u32> = proposal.psbt.outputs[i]
+                .tap_key_origins
+                .values()
+                .filter_map(|(_, (_, path))| match path.as_ref().last() {
+                    Some(ChildNumber::Normal { index }) => Some(*index),
+                    _ => None,
+                })
+                .collect();
+            indexes.push(0);
+            let is_change = indexes.into_iter().any(|index| {
+                descriptors.iter().any(|descriptor| {
+                    descriptor
+                        .at_derivation_index(index)
+                        .script_pubkey()
+                        .eq(script)
+                })
+            });
+            if !is_change {
+                return Err(Error::PsbtUnknownOutput(script_to_string(
+                    script,
+                    self.network,
+                )));
+            }
+        }
+
+        // Check fee
+        let mut input_value: u64 = 0;
+        for (index, input) in proposal.psbt.inputs.iter().enumerate() {
+            let value = match (&input.witness_utxo, &input.non_witness_utxo) {
+                (Some(txout), _) => txout.value,
+                (None, Some(prev_tx)) => {
+                    let previous_output = &tx.input[index].previous_output;
+                    if prev_tx.txid() != previous_output.txid {
+                        return Err(Error::PsbtNonWitnessUtxoMismatch(index));
+                    }
+                    let vout = previous_output.vout as usize;
+                    prev_tx
+                        .output
+                        .get(vout)
+                        .ok_or(Error::PsbtInputValueNotFound)?
+                        .value
+                }
+                (None, None) => return Err(Error::PsbtInputValueNotFound),
+            };
+            input_value += value;
+        }
+        let output_value: u64 = tx.output.iter().map(|txout| txout.value).sum();
+        let fee = input_value
+            .checked_sub(output_value)
+            .ok_or(Error::PsbtNegativeFee {
+                input: input_value,
+                output: output_value,
+            })?;
+
+        if let Some(proposal_fee) = proposal.fee {
+            if proposal_fee != fee {
+                return Err(Error::PsbtFeeMismatch {
+                    psbt: fee,
+                    proposal: proposal_fee,
+                });
+            }
+        }
+
+        // Estimate the vsize of the signed transaction with the satisfaction weight of the
+        // spending path (the scriptSig len is already in the weight of the unsigned tx)
+        let descriptor = wallet.get_descriptor_for_keychain(KeychainKind::External);
+        let satisfaction_weight: usize = match &proposal.policy_path {
+            Some(path) => {
+                policy::analysis::spending_paths(policy, descriptor, self.policy_branches(policy)?)?
+                    .into_iter()
+                    .find(|p| &p.branch.path == path)
+                    .and_then(|p| p.satisfaction_weight)
+            }
+            None => None,
+        }
+        .or_else(|| descriptor.max_satisfaction_weight().ok())
+        .unwrap_or_default();
+        let weight: usize =
+            tx.weight() + 2 + tx.input.len() * satisfaction_weight.saturating_sub(4);
+        let fee_rate = fee as f32 / ((weight + 3) / 4) as f32;
+        if fee_rate > self.max_fee_rate {
+            return Err(Error::FeeRateTooHigh(fee_rate));
+        }
+
+        // Check the timelocks of the spending path
+        if let Some(path) = &proposal.policy_path {
+            let condition = path_condition(wallet, path)?;
+            if !policy::path::has_timelocks(&condition, tx) {
+                return Err(Error::PsbtTimelockMismatch);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Select the cheapest spending path that can be satisfied at `height` spending `outpoints`
+    fn select_policy_path(
+        &self,
+        policy: &Policy,
+        wallet: &Wallet<MemoryDatabase>,
+        outpoints: &[OutPoint],
+        height: u32,
+    ) -> Result<PolicyPath, Error> {
+        let mut utxos: Vec<Option<BlockTime>> = Vec::new();
+        for outpoint in outpoints.iter() {
+            let tx = wallet.get_tx(&outpoint.txid, false)?;
+            utxos.push(tx.and_then(|tx| tx.confirmation_time));
+        }
+
+        let branches = self.policy_branches(policy)?;
+        let branch =
+            policy::path::select_branch(&branches, height, Timestamp::now().as_u64(), &utxos)
+                .ok_or(Error::NoSatisfiableBranch)?;
+        Ok(branch.path.clone())
+    }
+
+    /// Publish a spending proposal and notify the other members of the policy
+    async fn publish_proposal(
+        &self,
+        policy_id: EventId,
+        policy: &Policy,
+        shared_keys: &Keys,
+        proposal: &SpendingProposal,
+    ) -> Result<EventId, Error> {
+        let proposal_id = self
+            .send_proposal(policy_id, policy, shared_keys, proposal)
+            .await?;
+
+        // Send DM msg
+        let extracted_pubkeys = policy.member_pubkeys()?;
+        let sender = self.client.keys().public_key();
+        let mut msg = String::from("New spending proposal:\n");
+        if let Some(fee_bump) = proposal.fee_bump {
+            msg.push_str(&format!("- Fee bump: {fee_bump}\n"));
+        }
+        for recipient in proposal.recipients.iter() {
+            msg.push_str(&format!(
+                "- Amount: {} sats to {}\n",
+                util::format::big_number(recipient.amount),
+                recipient.address
+            ));
+        }
+        if let Some(fee) = proposal.fee {
+            msg.push_str(&format!("- Fee: {} sats\n", util::format::number(fee)));
+        }
+        msg.push_str(&format!("- Memo: {}", proposal.memo));
+        for pubkey in extracted_pubkeys.into_iter() {
+            if sender != pubkey {
+                self.client.send_direct_msg(pubkey, &msg).await?;
+            }
+        }
+
+        Ok(proposal_id)
+    }
+
+    /// Publish the proposal event, without notifying the members
+    async fn send_proposal(
+        &self,
+        policy_id: EventId,
+        policy: &Policy,
+        shared_keys: &Keys,
+        proposal: &SpendingProposal,
+    ) -> Result<EventId, Error> {
+        let mut tags: Vec<Tag> = policy
+            .member_pubkeys()?
+            .iter()
+            .map(|p| Tag::PubKey(*p, None))
+            .collect();
+        tags.push(Tag::Event(policy_id, None, None));
+        tags.push(EncryptionVersion::Nip44.tag());
+        let content = encryption::encrypt(
+            &shared_keys.secret_key()?,
+            &shared_keys.public_key(),
+            proposal.as_json(),
+        )?;
+        // Publish proposal with `shared_key` so every owner can delete it
+        let event =
+            EventBuilder::new(SPENDING_PROPOSAL_KIND, content, &tags).to_event(shared_keys)?;
+        Ok(self.client.send_event(event).await?)
+    }
+
+    pub async fn approve(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+
+        // Get proposal
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+
+        // Get policy id
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        // Create a BDK wallet
+        let mut wallet = self.wallet(policy.descriptor.to_string())?;
+
+        // Check that the PSBT matches the proposal
+        self.verify_proposal(&policy, &wallet, &proposal)?;
+
+        // Add the signers of the seed keys, matched by the descriptor key origins
+        if let Some(root_key) = &self.root_key {
+            for xprv in signer::derive_descriptor_xprvs(root_key, policy.descriptor.to_string())? {
+                for is_internal_key in [false, true] {
+                    let signer =
+                        SignerWrapper::new(xprv.clone(), SignerContext::Tap { is_internal_key });
+                    wallet.add_signer(KeychainKind::External, SignerOrdering(0), Arc::new(signer));
+                }
+            }
+        }
+
+        // Add the BDK signer of the nostr key (single keys)
+        let private_key = PrivateKey::new(keys.secret_key()?, self.network);
+        let signer = SignerWrapper::new(
+            private_key,
+            SignerContext::Tap {
+                is_internal_key: false,
+            },
+        );
+        let internal_signer = SignerWrapper::new(
+            private_key,
+            SignerContext::Tap {
+                is_internal_key: true,
+            },
+        );
+
+        wallet.add_signer(KeychainKind::External, SignerOrdering(0), Arc::new(signer));
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(0),
+            Arc::new(internal_signer),
+        );
+
+        // Sign the transaction
+        let mut psbt = proposal.psbt.clone();
+        let _finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+        if psbt != proposal.psbt {
+            let content = encryption::encrypt(
+                &keys.secret_key()?,
+                &shared_keys.public_key(),
+                psbt.to_string(),
+            )?;
+            // Publish approved proposal with the own key, so every member can verify who approved
+            let event = EventBuilder::new(
+                APPROVED_PROPOSAL_KIND,
+                content,
+                &[
+                    Tag::Event(proposal_id, None, None),
+                    Tag::Event(policy_id, None, None),
+                    Tag::PubKey(shared_keys.public_key(), None),
+                    EncryptionVersion::Nip44.tag(),
+                ],
+            )
+            .to_event(&keys)?;
+            let event_id = self.client.send_event(event).await?;
+            Ok(event_id)
+        } else {
+            Err(Error::PsbtNotSigned)
+        }
+    }
+
+    /// Reject a spending proposal, with the reason
+    pub async fn reject<S>(
+        &self,
+        proposal_id: EventId,
+        reason: S,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let keys = self.client.keys();
+
+        // Get proposal
+        let (_proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+
+        let content = encryption::encrypt(
+            &keys.secret_key()?,
+            &shared_keys.public_key(),
+            reason.into(),
+        )?;
+        // Publish rejected proposal with the own key, as the approvals
+        let event = EventBuilder::new(
+            REJECTED_PROPOSAL_KIND,
+            content,
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        let event_id = self.client.send_event(event).await?;
+        Ok(event_id)
+    }
+
+    /// Get the last MuSig2 public nonces of each participant for a proposal
+    ///
+    /// The nonces that can't be decrypted or that don't match the `inputs` of the proposal
+    /// are discarded.
+    async fn get_musig2_nonces(
+        &self,
+        proposal_id: EventId,
+        policy: &Policy,
+        shared_keys: &Keys,
+        inputs: usize,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<XOnlyPublicKey, Vec<PublicNonce>>, Error> {
+        let filter = Filter::new().event(proposal_id).kind(MUSIG2_NONCE_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+
+        let mut nonces: HashMap<XOnlyPublicKey, (Timestamp, Vec<PublicNonce>)> = HashMap::new();
+        for event in events.into_iter() {
+            if policy.musig2_key_of(&event.pubkey).is_none() {
+                log::warn!(
+                    "MuSig2 nonces {} of proposal {proposal_id} not authored by a participant",
+                    event.id
+                );
+                continue;
+            }
+
+            if matches!(nonces.get(&event.pubkey), Some((timestamp, _)) if *timestamp >= event.created_at)
+            {
+                continue;
+            }
+
+            let pubnonces = encryption::decrypt(&shared_keys.secret_key()?, &event.pubkey, &event)
+                .map_err(Error::from)
+                .and_then(|content| {
+                    serde_json::from_str::<Vec<PublicNonce>>(&content).map_err(Error::from)
+                });
+            match pubnonces {
+                Ok(pubnonces) if pubnonces.len() == inputs => {
+                    nonces.insert(event.pubkey, (event.created_at, pubnonces));
+                }
+                Ok(..) => log::warn!(
+                    "MuSig2 nonces {} of proposal {proposal_id} don't match the inputs",
+                    event.id
+                ),
+                Err(e) => log::warn!(
+                    "Invalid MuSig2 nonces {} of proposal {proposal_id}: {e}",
+                    event.id
+                ),
+            }
+        }
+
+        Ok(nonces
+            .into_iter()
+            .map(|(public_key, (_, pubnonces))| (public_key, pubnonces))
+            .collect())
+    }
+
+    /// Commit the MuSig2 nonces to spend a proposal with the key path (first round)
+    ///
+    /// The secret nonces are kept in the secret store until [`CoinstrClient::musig2_sign`]:
+    /// a new commitment replaces the previous one.
+    pub async fn musig2_commit_nonces(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+
+        // Get proposal and policy
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        if policy.musig2_keys.is_empty() {
+            return Err(Error::NotMuSig2Policy);
+        }
+        // Check that the signer key can sign before committing
+        self.musig2_secret_key(&policy)?;
+
+        // One nonce for each input
+        let secnonces: Vec<SecretNonce> = proposal
+            .psbt
+            .inputs
+            .iter()
+            .map(|_| SecretNonce::generate())
+            .collect();
+        let pubnonces: Vec<PublicNonce> = secnonces.iter().map(|n| n.public_nonce()).collect();
+
+        let content = encryption::encrypt(
+            &keys.secret_key()?,
+            &shared_keys.public_key(),
+            serde_json::json!(pubnonces).to_string(),
+        )?;
+        let event = EventBuilder::new(
+            MUSIG2_NONCE_KIND,
+            content,
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        // Saved before publishing, so the published nonces can always be used
+        self.secrets.save_musig2_nonces(proposal_id, &secnonces)?;
+        let event_id = self.client.send_event(event).await?;
+        Ok(event_id)
+    }
+
+    /// Sign a proposal with the MuSig2 key path (second round)
+    ///
+    /// All the participants must have committed their nonces: if some of them aren't online,
+    /// approve the proposal with a script path instead.
+    pub async fn musig2_sign(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+
+        // Get proposal and policy
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        // Check that the PSBT matches the proposal
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        self.verify_proposal(&policy, &wallet, &proposal)?;
+
+        let (ctx, messages) = musig2_session(&policy, &proposal.psbt)?;
+        let nonces = self
+            .get_musig2_nonces(proposal_id, &policy, &shared_keys, messages.len(), timeout)
+            .await?;
+        let aggregate_nonces = musig2_aggregate_nonces(&policy, &nonces, messages.len())?;
+
+        // The secret nonces are removed before signing, so they can't be used twice
+        let secnonces: Vec<SecretNonce> = self
+            .secrets
+            .take_musig2_nonces(proposal_id)?
+            .ok_or(Error::MuSig2NoncesNotFound)?;
+        let pubnonces: Vec<PublicNonce> = secnonces.iter().map(|n| n.public_nonce()).collect();
+        if nonces.get(&keys.public_key()) != Some(&pubnonces) {
+            return Err(Error::MuSig2NoncesNotFound);
+        }
+
+        let secret_key = self.musig2_secret_key(&policy)?;
+        let mut signatures: Vec<PartialSignature> = Vec::with_capacity(messages.len());
+        for ((secnonce, aggregate_nonce), msg) in secnonces
+            .into_iter()
+            .zip(aggregate_nonces.iter())
+            .zip(messages.iter())
+        {
+            signatures.push(musig2::partial_sign(
+                &ctx,
+                secnonce,
+                &secret_key,
+                aggregate_nonce,
+                msg,
+            )?);
+        }
+
+        let partial_signatures = PartialSignatures {
+            aggregate_nonces,
+            signatures,
+        };
+        let content = encryption::encrypt(
+            &keys.secret_key()?,
+            &shared_keys.public_key(),
+            partial_signatures.as_json(),
+        )?;
+        let event = EventBuilder::new(
+            MUSIG2_PARTIAL_SIG_KIND,
+            content,
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        let event_id = self.client.send_event(event).await?;
+        Ok(event_id)
+    }
+
+    /// Secret key of the own MuSig2 key, derived from the seed
+    fn musig2_secret_key(&self, policy: &Policy) -> Result<SecretKey, Error> {
+        let musig2_key = policy
+            .musig2_key_of(&self.client.keys().public_key())
+            .ok_or(Error::NotMuSig2Participant)?;
+        let root_key = self.root_key.as_ref().ok_or(Error::SignerRootKeyNotFound)?;
+        for xprv in signer::derive_descriptor_xprvs(root_key, policy.descriptor.to_string())? {
+            let secret_key = xprv
+                .xkey
+                .derive_priv(SECP256K1, &xprv.derivation_path)?
+                .private_key;
+            if secret_key.x_only_public_key(SECP256K1).0 == musig2_key {
+                return Ok(secret_key);
+            }
+        }
+        Err(Error::SignerNotInDescriptor)
+    }
+
+    /// Get the PSBT of a proposal, signed with the MuSig2 key path
+    ///
+    /// All the participants must have sent valid partial signatures for their last nonces.
+    pub async fn get_musig2_signed_psbt(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        // Get proposal and policy
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
+
+        let (ctx, messages) = musig2_session(&policy, &proposal.psbt)?;
+        let nonces = self
+            .get_musig2_nonces(proposal_id, &policy, &shared_keys, messages.len(), timeout)
+            .await?;
+        let aggregate_nonces = musig2_aggregate_nonces(&policy, &nonces, messages.len())?;
+
+        // Get partial signatures
+        let filter = Filter::new()
+            .event(proposal_id)
+            .kind(MUSIG2_PARTIAL_SIG_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+
+        let mut signatures: HashMap<XOnlyPublicKey, Vec<PartialSignature>> = HashMap::new();
+        for event in events.into_iter() {
+            let (pubnonces, musig2_key) = match (
+                nonces.get(&event.pubkey),
+                policy.musig2_key_of(&event.pubkey),
+            ) {
+                (Some(pubnonces), Some(musig2_key)) => (pubnonces, musig2_key),
+                _ => continue,
+            };
+
+            let content = encryption::decrypt(&shared_keys.secret_key()?, &event.pubkey, &event)?;
+            let partial_signatures = PartialSignatures::from_json(content)?;
+
+            // Discard the signatures made for previous nonces
+            if partial_signatures.aggregate_nonces != aggregate_nonces
+                || partial_signatures.signatures.len() != messages.len()
+            {
+                continue;
+            }
+
+            let valid = partial_signatures.signatures.iter().enumerate().all(
+                |(index, partial_signature)| {
+                    musig2::verify_partial_signature(
+                        &ctx,
+                        partial_signature,
+                        &pubnonces[index],
+                        &musig2_key,
+                        &aggregate_nonces[index],
+                        &messages[index],
+                    )
+                    .is_ok()
+                },
+            );
+            if !valid {
+                log::warn!(
+                    "Invalid MuSig2 partial signatures {} of proposal {proposal_id}",
+                    event.id
+                );
+                continue;
+            }
+
+            signatures.insert(event.pubkey, partial_signatures.signatures);
+        }
+
+        let missing: Vec<XOnlyPublicKey> = policy
+            .members
+            .iter()
+            .filter(|pk| !signatures.contains_key(pk))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::MissingMuSig2PartialSignatures(missing));
+        }
+
+        // Aggregate the partial signatures of each input
+        let mut psbt = proposal.psbt;
+        for (index, input) in psbt.inputs.iter_mut().enumerate() {
+            let partial_signatures: Vec<PartialSignature> = policy
+                .members
+                .iter()
+                .filter_map(|pk| signatures.get(pk).map(|s| s[index]))
+                .collect();
+            let sig = musig2::aggregate_partial_signatures(
+                &ctx,
+                &aggregate_nonces[index],
+                &partial_signatures,
+                &messages[index],
+            )?;
+            input.tap_key_sig = Some(SchnorrSig {
+                sig,
+                hash_ty: SchnorrSighashType::Default,
+            });
+        }
+
+        Ok(psbt)
+    }
+
+    /// Invite the members to a FROST key generation session
+    ///
+    /// The members are in the order of their indexes in the group, the coordinator included.
+    pub async fn create_frost_keygen<S>(
+        &self,
+        name: S,
+        description: S,
+        threshold: usize,
+        members: Vec<XOnlyPublicKey>,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let keys = self.client.keys();
+
+        let session = FrostKeyGen::new(name, description, threshold, members)?;
+        if session.index_of(&keys.public_key()).is_none() {
+            return Err(Error::NotFrostMember);
+        }
+
+        let mut recipients: Vec<XOnlyPublicKey> = vec![keys.public_key()];
+        recipients.extend(session.members.iter().filter(|m| **m != keys.public_key()));
+
+        // The copy of the coordinator is published first: its id is the session id
+        let message = KeyGenMessage::Session(session).as_json();
+        let mut session_id: Option<EventId> = None;
+        for pubkey in recipients.into_iter() {
+            let content = encryption::encrypt(&keys.secret_key()?, &pubkey, message.clone())?;
+            let mut tags = vec![Tag::PubKey(pubkey, None), EncryptionVersion::Nip44.tag()];
+            if let Some(session_id) = session_id {
+                tags.push(Tag::Event(session_id, None, None));
+            }
+            let event = EventBuilder::new(FROST_KEYGEN_KIND, content, &tags).to_event(&keys)?;
+            let event_id = self.client.send_event(event).await?;
+            session_id.get_or_insert(event_id);
+        }
+
+        session_id.ok_or(Error::FrostKeyGenNotFound)
+    }
+
+    /// Get a FROST key generation session, with its coordinator
+    pub async fn get_frost_keygen_by_id(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<(FrostKeyGen, XOnlyPublicKey), Error> {
+        let keys = self.client.keys();
+
+        let filters = vec![
+            Filter::new().id(session_id).kind(FROST_KEYGEN_KIND),
+            Filter::new()
+                .event(session_id)
+                .pubkey(keys.public_key())
+                .kind(FROST_KEYGEN_KIND),
+        ];
+        let events = self.client.get_events_of(filters, timeout).await?;
+
+        // The coordinator is the author of the session event
+        let coordinator = events
+            .iter()
+            .find(|event| event.id == session_id)
+            .map(|event| event.pubkey)
+            .ok_or(Error::FrostKeyGenNotFound)?;
+
+        for event in events.iter().filter(|event| event.pubkey == coordinator) {
+            if let Ok(content) = encryption::decrypt(&keys.secret_key()?, &event.pubkey, event) {
+                if let Ok(KeyGenMessage::Session(session)) = KeyGenMessage::from_json(content) {
+                    return Ok((session, coordinator));
+                }
+            }
+        }
+
+        Err(Error::FrostKeyGenNotFound)
+    }
+
+    /// Send the key generation round to the other members of a FROST session:
+    /// the commitment to the own polynomial, with the secret share of the recipient
+    ///
+    /// The polynomial is derived from the nostr key, so the round can be sent again.
+    pub async fn frost_keygen_join(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        let keys = self.client.keys();
+
+        let (session, _coordinator) = self.get_frost_keygen_by_id(session_id, timeout).await?;
+        let index = session
+            .index_of(&keys.public_key())
+            .ok_or(Error::NotFrostMember)?;
+
+        let context = frost_keygen_context(session_id);
+        let secret = KeyGenSecret::derive(&keys.secret_key()?, &context, index, session.threshold)?;
+        let commitment = secret.commitment(&context)?;
+
+        for (member_index, member) in (1..).zip(session.members.iter()) {
+            if *member == keys.public_key() {
+                continue;
+            }
+
+            let round = KeyGenMessage::Round(KeyGenRound {
+                commitment: commitment.clone(),
+                share: secret.share(member_index)?,
+            });
+            let content = encryption::encrypt(&keys.secret_key()?, member, round.as_json())?;
+            let event = EventBuilder::new(
+                FROST_KEYGEN_KIND,
+                content,
+                &[
+                    Tag::PubKey(*member, None),
+                    Tag::Event(session_id, None, None),
+                    EncryptionVersion::Nip44.tag(),
+                ],
+            )
+            .to_event(&keys)?;
+            self.client.send_event(event).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Verify the key generation rounds of the other members and compute the own key package
+    ///
+    /// The hash of the commitments is sent to the other members (echo round): the group is
+    /// accepted only when every member received the same commitments, so call it again
+    /// until no echo is pending.
+    /// The key package is saved in the secret store and, encrypted to the own key, on the relays.
+    pub async fn finalize_frost_keygen(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<FrostGroup, Error> {
+        let keys = self.client.keys();
+
+        let (session, _coordinator) = self.get_frost_keygen_by_id(session_id, timeout).await?;
+        let index = session
+            .index_of(&keys.public_key())
+            .ok_or(Error::NotFrostMember)?;
+
+        // Get the rounds sent to me
+        let filter = Filter::new()
+            .event(session_id)
+            .pubkey(keys.public_key())
+            .kind(FROST_KEYGEN_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+
+        let mut rounds: HashMap<XOnlyPublicKey, (Timestamp, KeyGenRound)> = HashMap::new();
+        let mut echoes: HashMap<XOnlyPublicKey, (Timestamp, sha256::Hash)> = HashMap::new();
+        for event in events.into_iter() {
+            if event.pubkey == keys.public_key() || session.index_of(&event.pubkey).is_none() {
+                continue;
+            }
+
+            let content = encryption::decrypt(&keys.secret_key()?, &event.pubkey, &event)?;
+            match KeyGenMessage::from_json(content)? {
+                KeyGenMessage::Round(round) => {
+                    if !matches!(rounds.get(&event.pubkey), Some((timestamp, _)) if *timestamp >= event.created_at)
+                    {
+                        rounds.insert(event.pubkey, (event.created_at, round));
+                    }
+                }
+                KeyGenMessage::Echo(hash) => {
+                    if !matches!(echoes.get(&event.pubkey), Some((timestamp, _)) if *timestamp >= event.created_at)
+                    {
+                        echoes.insert(event.pubkey, (event.created_at, hash));
+                    }
+                }
+                KeyGenMessage::Session(..) => (),
+            }
+        }
+
+        let context = frost_keygen_context(session_id);
+        let secret = KeyGenSecret::derive(&keys.secret_key()?, &context, index, session.threshold)?;
+
+        let mut commitments: Vec<KeyGenCommitment> = Vec::with_capacity(session.members.len());
+        let mut shares: Vec<SecretKey> = Vec::with_capacity(session.members.len());
+        let mut pending: Vec<XOnlyPublicKey> = Vec::new();
+        for (member_index, member) in (1..).zip(session.members.iter()) {
+            if *member == keys.public_key() {
+                commitments.push(secret.commitment(&context)?);
+                shares.push(secret.share(index)?);
+                continue;
+            }
+
+            match rounds.remove(member) {
+                Some((_, round)) => {
+                    round
+                        .commitment
+                        .verify(member_index, &context, session.threshold)?;
+                    round
+                        .commitment
+                        .verify_share(index, &round.share)
+                        .map_err(|_| frost::Error::InvalidShare(member_index))?;
+                    commitments.push(round.commitment);
+                    shares.push(round.share);
+                }
+                None => pending.push(*member),
+            }
+        }
+
+        if !pending.is_empty() {
+            return Err(Error::PendingFrostMembers(pending));
+        }
+
+        let group = FrostGroup::from_commitments(session.threshold, &commitments)?;
+        let key_package = KeyPackage::new(index, group.clone(), &shares)?;
+
+        // Echo round: send the hash of the commitments to the other members
+        let hash = frost::commitments_hash(&context, &commitments);
+        let echo = KeyGenMessage::Echo(hash).as_json();
+        for member in session.members.iter().filter(|m| **m != keys.public_key()) {
+            let content = encryption::encrypt(&keys.secret_key()?, member, echo.clone())?;
+            let event = EventBuilder::new(
+                FROST_KEYGEN_KIND,
+                content,
+                &[
+                    Tag::PubKey(*member, None),
+                    Tag::Event(session_id, None, None),
+                    EncryptionVersion::Nip44.tag(),
+                ],
+            )
+            .to_event(&keys)?;
+            self.client.send_event(event).await?;
+        }
+
+        // Accept the group only if every member received the same commitments
+        let mut pending: Vec<XOnlyPublicKey> = Vec::new();
+        for member in session.members.iter().filter(|m| **m != keys.public_key()) {
+            match echoes.get(member) {
+                Some((_, echo)) if *echo == hash => (),
+                Some(_) => return Err(Error::FrostCommitmentsMismatch(*member)),
+                None => pending.push(*member),
+            }
+        }
+        if !pending.is_empty() {
+            return Err(Error::PendingFrostEchoes(pending));
+        }
+
+        // Save the key package locally and, encrypted to the own key, on the relays
+        self.secrets
+            .save_frost_key_package(session_id, &key_package)?;
+        let content = encryption::encrypt(
+            &keys.secret_key()?,
+            &keys.public_key(),
+            key_package.as_json(),
+        )?;
+        let event = EventBuilder::new(
+            FROST_KEY_PACKAGE_KIND,
+            content,
+            &[
+                Tag::PubKey(keys.public_key(), None),
+                Tag::Event(session_id, None, None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        self.client.send_event(event).await?;
+
+        Ok(group)
+    }
+
+    /// Get the own FROST key packages, by key generation session: the ones of the secret store
+    /// and the ones saved on the relays (like from another device)
+    async fn get_frost_key_packages(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<EventId, KeyPackage>, Error> {
+        let keys = self.client.keys();
+
+        let filter = Filter::new()
+            .author(keys.public_key())
+            .kind(FROST_KEY_PACKAGE_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+
+        let mut key_packages: HashMap<EventId, KeyPackage> = self.secrets.frost_key_packages()?;
+        for event in events.into_iter() {
+            if let Some(session_id) = util::extract_first_event_id(&event) {
+                if key_packages.contains_key(&session_id) {
+                    continue;
+                }
+                let content = encryption::decrypt(&keys.secret_key()?, &event.pubkey, &event)?;
+                let key_package = KeyPackage::from_json(content)?;
+                self.secrets
+                    .save_frost_key_package(session_id, &key_package)?;
+                key_packages.insert(session_id, key_package);
+            }
+        }
+        Ok(key_packages)
+    }
+
+    /// Get the own key package of the FROST group of a policy
+    async fn get_frost_key_package(
+        &self,
+        policy: &Policy,
+        timeout: Option<Duration>,
+    ) -> Result<KeyPackage, Error> {
+        let keys = self.client.keys();
+
+        let group = policy.frost.as_ref().ok_or(Error::NotFrostPolicy)?;
+        let index = policy
+            .frost_index_of(&keys.public_key())
+            .ok_or(Error::NotFrostMember)?;
+
+        let key_package = self
+            .get_frost_key_packages(timeout)
+            .await?
+            .into_values()
+            .find(|key_package| key_package.group.group_key == group.group_key)
+            .ok_or(Error::FrostKeyPackageNotFound)?;
+        if &key_package.group != group || key_package.index != index {
+            return Err(Error::FrostGroupMismatch);
+        }
+
+        Ok(key_package)
+    }
+
+    /// Save the policy of a FROST key generation session, with the group key as key path
+    /// (coordinator only)
+    ///
+    /// The script path uses the BIP-86 signer keys announced by the members.
+    pub async fn save_frost_policy(
+        &self,
+        session_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
+
+        let (session, coordinator) = self.get_frost_keygen_by_id(session_id, timeout).await?;
+        if coordinator != keys.public_key() {
+            return Err(Error::NotFrostCoordinator);
+        }
+
+        let key_package = self
+            .get_frost_key_packages(timeout)
+            .await?
+            .remove(&session_id)
+            .ok_or(Error::FrostKeyPackageNotFound)?;
+        let signers = self.get_bip86_signers(&session.members, timeout).await?;
+        let policy = Policy::from_frost_group(
+            session.name,
+            session.description,
+            key_package.group,
+            session.members,
+            &signers,
+        )?;
+        self.save_compiled_policy(policy, timeout).await
+    }
+
+    /// Get the last FROST signing commitments of each member for a proposal, by index
+    async fn get_frost_nonces(
+        &self,
+        proposal_id: EventId,
+        policy: &Policy,
+        shared_keys: &Keys,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<u32, (Timestamp, SigningCommitments)>, Error> {
+        let filter = Filter::new().event(proposal_id).kind(FROST_NONCE_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
+
+        let mut nonces: HashMap<u32, (Timestamp, SigningCommitments)> = HashMap::new();
+        for event in events.into_iter() {
+            let index = match policy.frost_index_of(&event.pubkey) {
+                Some(index) => index,
+                None => {
+                    log::warn!(
+                        "FROST nonces {} of proposal {proposal_id} not authored by a member",
+                        event.id
+                    );
+                    continue;
+                }
+            };
+
+            if matches!(nonces.get(&index), Some((timestamp, _)) if *timestamp >= event.created_at)
+            {
+                continue;
+            }
+
+            let commitments =
+                encryption::decrypt(&shared_keys.secret_key()?, &event.pubkey, &event)
+                    .map_err(Error::from)
+                    .and_then(|content| Ok(SigningCommitments::from_json(content)?));
+            match commitments {
+                Ok(commitments) if commitments.signers.contains(&index) => {
+                    nonces.insert(index, (event.created_at, commitments));
+                }
+                Ok(..) => log::warn!(
+                    "FROST nonces {} of proposal {proposal_id} not in their own signing set",
+                    event.id
+                ),
+                Err(e) => log::warn!(
+                    "Invalid FROST nonces {} of proposal {proposal_id}: {e}",
+                    event.id
+                ),
+            }
+        }
+
+        Ok(nonces)
+    }
+
+    /// Commit the FROST signing nonces to spend a proposal with the key path (first round)
+    ///
+    /// The nonces are committed to the signing set of the `signers` (`threshold` members,
+    /// own key included): each of them must commit to the same set.
+    /// The nonces are kept in the secret store until [`CoinstrClient::frost_sign`]:
+    /// a new commitment replaces the previous one.
+    pub async fn frost_commit_nonces(
+        &self,
+        proposal_id: EventId,
+        signers: Vec<XOnlyPublicKey>,
+        timeout: Option<Duration>,
+    ) -> Result<EventId, Error> {
+        let keys = self.client.keys();
 
-        // Build the transaction
-        let mut builder = wallet.build_tx();
-        builder
-            .add_recipient(to_address.script_pubkey(), amount)
-            .policy_path(path, KeychainKind::External);
+        // Get proposal and policy
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
 
-        // Build the PSBT
-        let (psbt, _details) = builder.finish()?;
+        let threshold = match &policy.frost {
+            Some(group) => group.threshold,
+            None => return Err(Error::NotFrostPolicy),
+        };
+        let index = policy
+            .frost_index_of(&keys.public_key())
+            .ok_or(Error::NotFrostMember)?;
 
-        let memo: &str = &memo.into();
+        // Signing set, sorted by index
+        let mut set: Vec<u32> = signers
+            .iter()
+            .map(|signer| policy.frost_index_of(signer))
+            .collect::<Option<_>>()
+            .ok_or(Error::InvalidFrostSigningSet(threshold))?;
+        set.sort();
+        set.dedup();
+        if set.len() != threshold || !set.contains(&index) {
+            return Err(Error::InvalidFrostSigningSet(threshold));
+        }
 
-        // Create spending proposal
-        let proposal = SpendingProposal::new(to_address, amount, memo, psbt);
-        let extracted_pubkeys = util::extract_public_keys(policy.descriptor.to_string())?;
-        let mut tags: Vec<Tag> = extracted_pubkeys
+        // One pair of nonces for each input
+        let nonces: Vec<SigningNonces> = proposal
+            .psbt
+            .inputs
             .iter()
-            .map(|p| Tag::PubKey(*p, None))
+            .map(|_| SigningNonces::generate())
             .collect();
-        tags.push(Tag::Event(policy_id, None, None));
-        let content = nips::nip04::encrypt(
-            &shared_keys.secret_key()?,
+        let commitments = SigningCommitments {
+            signers: set,
+            commitments: nonces.iter().map(|n| n.commitment()).collect(),
+        };
+
+        let content = encryption::encrypt(
+            &keys.secret_key()?,
             &shared_keys.public_key(),
-            proposal.as_json(),
+            commitments.as_json(),
         )?;
-        // Publish proposal with `shared_key` so every owner can delete it
-        let event =
-            EventBuilder::new(SPENDING_PROPOSAL_KIND, content, &tags).to_event(&shared_keys)?;
-        let proposal_id = self.client.send_event(event).await?;
-
-        // Send DM msg
-        let sender = self.client.keys().public_key();
-        let mut msg = String::from("New spending proposal:\n");
-        msg.push_str(&format!(
-            "- Amount: {} sats\n",
-            util::format::big_number(amount)
-        ));
-        msg.push_str(&format!("- Memo: {memo}"));
-        for pubkey in extracted_pubkeys.into_iter() {
-            if sender != pubkey {
-                self.client.send_direct_msg(pubkey, &msg).await?;
-            }
-        }
-
-        Ok(proposal_id)
+        let event = EventBuilder::new(
+            FROST_NONCE_KIND,
+            content,
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        // Saved before publishing, so the published commitments can always be used
+        self.secrets.save_frost_nonces(proposal_id, &nonces)?;
+        let event_id = self.client.send_event(event).await?;
+        Ok(event_id)
     }
 
-    pub async fn approve(
+    /// Sign a proposal with the FROST key path (second round)
+    ///
+    /// The signers are the signing set of the own nonces: all of them must have committed
+    /// their nonces to the same set, before the own nonces are used.
+    pub async fn frost_sign(
         &self,
         proposal_id: EventId,
         timeout: Option<Duration>,
     ) -> Result<EventId, Error> {
         let keys = self.client.keys();
 
-        // Get proposal
+        // Get proposal and policy
         let (proposal, policy_id, shared_keys) =
             self.get_proposal_by_id(proposal_id, timeout).await?;
+        let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
 
-        // Get policy id
+        // Check that the PSBT matches the proposal
+        let wallet = self.wallet(policy.descriptor.to_string())?;
+        self.verify_proposal(&policy, &wallet, &proposal)?;
+
+        let key_package = self.get_frost_key_package(&policy, timeout).await?;
+        let (ctx, messages) = frost_session(&policy, &proposal.psbt)?;
+        let nonces = self
+            .get_frost_nonces(proposal_id, &policy, &shared_keys, timeout)
+            .await?;
+        let signers = frost_signing_set(&policy, &nonces, key_package.index)?;
+
+        // The nonces are removed before signing (once the signing set is agreed),
+        // so they can't be used twice
+        let own_nonces: Vec<SigningNonces> = self
+            .secrets
+            .take_frost_nonces(proposal_id)?
+            .ok_or(Error::FrostNoncesNotFound)?;
+        let own_commitments: Vec<SigningCommitment> =
+            own_nonces.iter().map(|n| n.commitment()).collect();
+        if nonces.get(&key_package.index).map(|(_, c)| &c.commitments) != Some(&own_commitments) {
+            return Err(Error::FrostNoncesNotFound);
+        }
+
+        let mut shares: Vec<SignatureShare> = Vec::with_capacity(messages.len());
+        for (input, (signing_nonces, msg)) in
+            own_nonces.into_iter().zip(messages.iter()).enumerate()
+        {
+            let commitments =
+                frost_commitments(&nonces, &signers, input).ok_or(frost::Error::InvalidSigners)?;
+            shares.push(frost::sign(
+                &ctx,
+                &key_package,
+                signing_nonces,
+                &commitments,
+                msg,
+            )?);
+        }
+
+        let signature_shares = SignatureShares { signers, shares };
+        let content = encryption::encrypt(
+            &keys.secret_key()?,
+            &shared_keys.public_key(),
+            signature_shares.as_json(),
+        )?;
+        let event = EventBuilder::new(
+            FROST_SIGNATURE_SHARE_KIND,
+            content,
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys)?;
+        let event_id = self.client.send_event(event).await?;
+        Ok(event_id)
+    }
+
+    /// Get the PSBT of a proposal, signed with the FROST key path
+    ///
+    /// All the signers of a signing set must have sent valid signature shares for their last nonces.
+    pub async fn get_frost_signed_psbt(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        // Get proposal and policy
+        let (proposal, policy_id, shared_keys) =
+            self.get_proposal_by_id(proposal_id, timeout).await?;
         let (policy, _shared_keys) = self.get_policy_by_id(policy_id, timeout).await?;
 
-        // Create a BDK wallet
-        let mut wallet = self.wallet(policy.descriptor.to_string())?;
+        let (ctx, messages) = frost_session(&policy, &proposal.psbt)?;
+        let nonces = self
+            .get_frost_nonces(proposal_id, &policy, &shared_keys, timeout)
+            .await?;
 
-        // Add the BDK signer
-        let private_key = PrivateKey::new(keys.secret_key()?, self.network);
-        let signer = SignerWrapper::new(
-            private_key,
-            SignerContext::Tap {
-                is_internal_key: false,
-            },
-        );
-        let internal_signer = SignerWrapper::new(
-            private_key,
-            SignerContext::Tap {
-                is_internal_key: true,
-            },
-        );
+        // Get signature shares
+        let filter = Filter::new()
+            .event(proposal_id)
+            .kind(FROST_SIGNATURE_SHARE_KIND);
+        let events = self.client.get_events_of(vec![filter], timeout).await?;
 
-        wallet.add_signer(KeychainKind::External, SignerOrdering(0), Arc::new(signer));
-        wallet.add_signer(
-            KeychainKind::External,
-            SignerOrdering(0),
-            Arc::new(internal_signer),
-        );
+        // Valid shares, by signing set
+        let mut sets: HashMap<Vec<u32>, HashMap<u32, Vec<SignatureShare>>> = HashMap::new();
+        for event in events.into_iter() {
+            let index = match policy.frost_index_of(&event.pubkey) {
+                Some(index) => index,
+                None => continue,
+            };
 
-        // Sign the transaction
-        let mut psbt = proposal.psbt.clone();
-        let _finalized = wallet.sign(&mut psbt, SignOptions::default())?;
-        if psbt != proposal.psbt {
-            let content = nips::nip04::encrypt(
-                &shared_keys.secret_key()?,
-                &shared_keys.public_key(),
-                psbt.to_string(),
-            )?;
-            // Publish approved proposal with `shared_key` so after the broadcast
-            // of the transaction it can be deleted
-            let event = EventBuilder::new(
-                APPROVED_PROPOSAL_KIND,
-                content,
-                &[
-                    Tag::Event(proposal_id, None, None),
-                    Tag::Event(policy_id, None, None),
-                ],
-            )
-            .to_event(&shared_keys)?;
-            let event_id = self.client.send_event(event).await?;
-            Ok(event_id)
-        } else {
-            Err(Error::PsbtNotSigned)
+            let content = encryption::decrypt(&shared_keys.secret_key()?, &event.pubkey, &event)?;
+            let signature_shares = SignatureShares::from_json(content)?;
+            // The shares must be made for the signing set of the author nonces
+            if signature_shares.shares.len() != messages.len()
+                || nonces.get(&index).map(|(_, c)| &c.signers) != Some(&signature_shares.signers)
+            {
+                continue;
+            }
+
+            let valid = signature_shares
+                .shares
+                .iter()
+                .zip(messages.iter())
+                .enumerate()
+                .all(|(input, (share, msg))| {
+                    match frost_commitments(&nonces, &signature_shares.signers, input) {
+                        Some(commitments) => {
+                            frost::verify_signature_share(&ctx, index, share, &commitments, msg)
+                                .is_ok()
+                        }
+                        None => false,
+                    }
+                });
+            if !valid {
+                log::warn!(
+                    "Invalid FROST signature shares {} of proposal {proposal_id}",
+                    event.id
+                );
+                continue;
+            }
+
+            sets.entry(signature_shares.signers)
+                .or_default()
+                .insert(index, signature_shares.shares);
+        }
+
+        // Aggregate the shares of a complete signing set
+        let (signers, shares) = sets
+            .into_iter()
+            .find(|(signers, shares)| signers.iter().all(|s| shares.contains_key(s)))
+            .ok_or(Error::MissingFrostSignatureShares)?;
+
+        let mut psbt = proposal.psbt;
+        for (input, (psbt_input, msg)) in psbt.inputs.iter_mut().zip(messages.iter()).enumerate() {
+            let commitments = frost_commitments(&nonces, &signers, input)
+                .ok_or(Error::MissingFrostSignatureShares)?;
+            let input_shares: Vec<SignatureShare> = signers
+                .iter()
+                .filter_map(|signer| shares.get(signer).map(|s| s[input]))
+                .collect();
+            let sig = frost::aggregate(&ctx, &commitments, &input_shares, msg)?;
+            psbt_input.tap_key_sig = Some(SchnorrSig {
+                sig,
+                hash_ty: SchnorrSighashType::Default,
+            });
+        }
+
+        Ok(psbt)
+    }
+
+    /// Get the signed PSBT of a proposal: with the key path (MuSig2 or FROST), if signed,
+    /// else with the approvals
+    ///
+    /// A key path session that can't complete (like with invalid nonces of a member) doesn't
+    /// block the script paths: only the errors of the relays are returned.
+    async fn get_signed_psbt(
+        &self,
+        proposal_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let key_path_psbt = match self.get_musig2_signed_psbt(proposal_id, timeout).await {
+            Err(Error::NotMuSig2Policy) => self.get_frost_signed_psbt(proposal_id, timeout).await,
+            res => res,
+        };
+        match key_path_psbt {
+            Ok(psbt) => Ok(psbt),
+            Err(e @ Error::Client(..)) => Err(e),
+            Err(e) => {
+                if !matches!(e, Error::NotFrostPolicy) {
+                    log::warn!("Key path not available for proposal {proposal_id}: {e}");
+                }
+
+                // Get and combine PSBTs
+                let (base_psbt, approvals) = self
+                    .get_signed_psbts_by_proposal_id(proposal_id, timeout)
+                    .await?;
+                Ok(combine_approvals(base_psbt, approvals))
+            }
         }
     }
 
@@ -551,15 +3770,7 @@ impl CoinstrClient {
         blockchain: impl Blockchain,
         timeout: Option<Duration>,
     ) -> Result<Txid, Error> {
-        // Get PSBTs
-        let (mut base_psbt, psbts) = self
-            .get_signed_psbts_by_proposal_id(proposal_id, timeout)
-            .await?;
-
-        // Combine PSBTs
-        for psbt in psbts {
-            base_psbt.combine(psbt)?;
-        }
+        let mut base_psbt = self.get_signed_psbt(proposal_id, timeout).await?;
 
         // Finalize and broadcast the transaction
         base_psbt
@@ -584,3 +3795,720 @@ impl CoinstrClient {
         self.client.clone()
     }
 }
+
+/// MuSig2 key aggregation context, with the taproot tweak, and the key path sighash of each input
+fn musig2_session(
+    policy: &Policy,
+    psbt: &PartiallySignedTransaction,
+) -> Result<(KeyAggContext, Vec<[u8; 32]>), Error> {
+    if policy.musig2_keys.is_empty() {
+        return Err(Error::NotMuSig2Policy);
+    }
+    let ctx = KeyAggContext::new(&policy.musig2_keys)?
+        .with_taproot_tweak(tap_merkle_root(policy, psbt)?)?;
+    Ok((ctx, key_spend_sighashes(psbt)?))
+}
+
+/// FROST signing context, with the taproot tweak, and the key path sighash of each input
+fn frost_session(
+    policy: &Policy,
+    psbt: &PartiallySignedTransaction,
+) -> Result<(SigningContext, Vec<[u8; 32]>), Error> {
+    let group = policy.frost.clone().ok_or(Error::NotFrostPolicy)?;
+    let ctx = SigningContext::new(group, tap_merkle_root(policy, psbt)?)?;
+    Ok((ctx, key_spend_sighashes(psbt)?))
+}
+
+/// Merkle root of the script paths of the policy, checked against the one of the PSBT inputs
+fn tap_merkle_root(
+    policy: &Policy,
+    psbt: &PartiallySignedTransaction,
+) -> Result<Option<TapBranchHash>, Error> {
+    let merkle_root = policy.tap_merkle_root()?;
+    if psbt
+        .inputs
+        .iter()
+        .any(|input| input.tap_merkle_root.is_some() && input.tap_merkle_root != merkle_root)
+    {
+        return Err(Error::PsbtTapMerkleRootMismatch);
+    }
+    Ok(merkle_root)
+}
+
+/// Key path sighash of each input
+fn key_spend_sighashes(psbt: &PartiallySignedTransaction) -> Result<Vec<[u8; 32]>, Error> {
+    let prevouts: Vec<TxOut> = psbt
+        .inputs
+        .iter()
+        .map(|input| {
+            input
+                .witness_utxo
+                .clone()
+                .ok_or(Error::PsbtInputValueNotFound)
+        })
+        .collect::<Result<_, _>>()?;
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let mut messages: Vec<[u8; 32]> = Vec::with_capacity(prevouts.len());
+    for index in 0..prevouts.len() {
+        let sighash = cache.taproot_key_spend_signature_hash(
+            index,
+            &Prevouts::All(&prevouts),
+            SchnorrSighashType::Default,
+        )?;
+        messages.push(sighash.into_inner());
+    }
+    Ok(messages)
+}
+
+/// Key generation context of a FROST session: the hash of the session id
+fn frost_keygen_context(session_id: EventId) -> [u8; 32] {
+    sha256::Hash::hash(session_id.to_string().as_bytes()).into_inner()
+}
+
+/// Signing set of the last nonces of the member `index`, once all its signers committed
+/// their last nonces to the same set
+fn frost_signing_set(
+    policy: &Policy,
+    nonces: &HashMap<u32, (Timestamp, SigningCommitments)>,
+    index: u32,
+) -> Result<Vec<u32>, Error> {
+    let (_, own) = nonces.get(&index).ok_or(Error::FrostNoncesNotFound)?;
+    let missing: Vec<XOnlyPublicKey> = own
+        .signers
+        .iter()
+        .filter(|signer| nonces.get(signer).map(|(_, c)| &c.signers) != Some(&own.signers))
+        .filter_map(|signer| {
+            (*signer as usize)
+                .checked_sub(1)
+                .and_then(|i| policy.members.get(i))
+        })
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingFrostNonces(missing));
+    }
+    Ok(own.signers.clone())
+}
+
+/// Signing commitments of the `signers`, for the input `index`
+fn frost_commitments(
+    nonces: &HashMap<u32, (Timestamp, SigningCommitments)>,
+    signers: &[u32],
+    index: usize,
+) -> Option<Vec<(u32, SigningCommitment)>> {
+    signers
+        .iter()
+        .map(|signer| {
+            nonces
+                .get(signer)
+                .and_then(|(_, c)| c.commitments.get(index))
+                .map(|commitment| (*signer, *commitment))
+        })
+        .collect()
+}
+
+/// Aggregate the nonces of the participants, for each input
+fn musig2_aggregate_nonces(
+    policy: &Policy,
+    nonces: &HashMap<XOnlyPublicKey, Vec<PublicNonce>>,
+    inputs: usize,
+) -> Result<Vec<PublicNonce>, Error> {
+    let missing: Vec<XOnlyPublicKey> = policy
+        .members
+        .iter()
+        .filter(|pk| !nonces.contains_key(pk))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingMuSig2Nonces(missing));
+    }
+
+    let mut aggregate_nonces: Vec<PublicNonce> = Vec::with_capacity(inputs);
+    for index in 0..inputs {
+        let mut input_nonces: Vec<PublicNonce> = Vec::with_capacity(policy.members.len());
+        for public_key in policy.members.iter() {
+            let pubnonces = nonces
+                .get(public_key)
+                .ok_or(Error::MissingMuSig2Nonces(vec![*public_key]))?;
+            if pubnonces.len() != inputs {
+                return Err(Error::InvalidMuSig2Nonces(*public_key));
+            }
+            input_nonces.push(pubnonces[index]);
+        }
+        aggregate_nonces.push(musig2::aggregate_nonces(&input_nonces)?);
+    }
+    Ok(aggregate_nonces)
+}
+
+/// Combine the signed PSBTs of the approvals, skipping the ones that can't be combined
+fn combine_approvals(
+    mut psbt: PartiallySignedTransaction,
+    approvals: Vec<Approval>,
+) -> PartiallySignedTransaction {
+    for approval in approvals.into_iter() {
+        let mut combined = psbt.clone();
+        match combined.combine(approval.psbt) {
+            Ok(()) => psbt = combined,
+            Err(e) => log::warn!(
+                "Skipped approval {} of {}: {e}",
+                approval.approval_id,
+                approval.author
+            ),
+        }
+    }
+    psbt
+}
+
+/// Timelocks required by a spending path
+fn path_condition(wallet: &Wallet<MemoryDatabase>, path: &PolicyPath) -> Result<Condition, Error> {
+    let wallet_policy = wallet
+        .policies(KeychainKind::External)?
+        .ok_or(Error::WalletSpendingPolicyNotFound)?;
+    Ok(wallet_policy
+        .get_condition(path)
+        .map_err(bdk::Error::from)?)
+}
+
+fn script_to_string(script: &Script, network: Network) -> String {
+    match Address::from_script(script, network) {
+        Some(address) => address.to_string(),
+        None => script.to_string(),
+    }
+}
+
+/// Get the shared key (the author of the policy event) and the members of the policies,
+/// indexed by policy event id
+fn extract_policies(events: &[Event]) -> HashMap<EventId, (XOnlyPublicKey, Vec<XOnlyPublicKey>)> {
+    events
+        .iter()
+        .filter(|event| event.kind == POLICY_KIND)
+        .map(|event| {
+            let members = event
+                .tags
+                .iter()
+                .filter_map(|tag| match tag {
+                    Tag::PubKey(pubkey, ..) => Some(*pubkey),
+                    _ => None,
+                })
+                .collect();
+            (event.id, (event.pubkey, members))
+        })
+        .collect()
+}
+
+/// Get the shared keys superseded by a shared key event
+fn extract_superseded_keys(event: &Event) -> Vec<XOnlyPublicKey> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            Tag::Generic(TagKind::Custom(kind), values) if kind == SUPERSEDED_TAG => values
+                .first()
+                .and_then(|v| XOnlyPublicKey::from_str(v).ok()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use bdk::bitcoin::{PackedLockTime, Sequence, Transaction, TxIn, Witness};
+
+    use super::mock_relay::MockRelay;
+    use super::*;
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(5));
+
+    /// Seed root key of a member, derived from its nostr keys (so it survives the restarts)
+    fn root_key(keys: &Keys) -> ExtendedPrivKey {
+        let seed = keys.secret_key().unwrap().secret_bytes();
+        ExtendedPrivKey::new_master(Network::Testnet, &seed).unwrap()
+    }
+
+    async fn client(url: &str, keys: &Keys) -> CoinstrClient {
+        CoinstrClient::new(keys.clone(), vec![url.to_string()], Network::Testnet)
+            .await
+            .unwrap()
+            .with_signer(root_key(keys))
+    }
+
+    /// PSBT spending a fake UTXO of the policy, with the taproot data of the descriptor
+    fn key_path_psbt(
+        policy: &Policy,
+        wallet: &Wallet<MemoryDatabase>,
+    ) -> (PartiallySignedTransaction, Recipient) {
+        let address = wallet.get_address(AddressIndex::Peek(0)).unwrap().address;
+        let prev_tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: 100_000,
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        let recipient = Recipient::new(
+            Address::p2tr(
+                SECP256K1,
+                Keys::generate().public_key(),
+                None,
+                Network::Testnet,
+            ),
+            99_000,
+        );
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(prev_tx.txid(), 0),
+                script_sig: Script::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value: recipient.amount,
+                script_pubkey: recipient.address.script_pubkey(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(prev_tx.output[0].clone());
+        psbt.inputs[0].non_witness_utxo = Some(prev_tx);
+        let descriptor = wallet.get_descriptor_for_keychain(KeychainKind::External);
+        psbt.update_input_with_descriptor(0, &descriptor.at_derivation_index(0))
+            .unwrap();
+        assert_eq!(
+            psbt.inputs[0].tap_merkle_root,
+            policy.tap_merkle_root().unwrap()
+        );
+        (psbt, recipient)
+    }
+
+    /// Publish a proposal spending a fake UTXO of the policy, with a fee of 1000 sat
+    async fn publish_key_path_proposal(client: &CoinstrClient, policy_id: EventId) -> EventId {
+        let (policy, shared_keys) = client.get_policy_by_id(policy_id, TIMEOUT).await.unwrap();
+        let wallet = client.wallet(policy.descriptor.to_string()).unwrap();
+        let (psbt, recipient) = key_path_psbt(&policy, &wallet);
+        let proposal = SpendingProposal::new(vec![recipient], "Test", psbt, None, Some(1_000));
+        client
+            .publish_proposal(policy_id, &policy, &shared_keys, &proposal)
+            .await
+            .unwrap()
+    }
+
+    /// 2-of-3 miniscript policy of the nostr public keys of the members
+    fn musig2_miniscript_policy(keys: &[Keys]) -> String {
+        let pks: Vec<String> = keys
+            .iter()
+            .map(|k| format!("pk({})", k.public_key()))
+            .collect();
+        format!("thresh(2,{})", pks.join(","))
+    }
+
+    fn musig2_policy(keys: &[Keys]) -> Policy {
+        let signers: HashMap<XOnlyPublicKey, SignerKey> = keys
+            .iter()
+            .map(|k| {
+                let signer =
+                    SignerKey::from_root_key(&root_key(k), SignerPurpose::Bip86, 0).unwrap();
+                (k.public_key(), signer)
+            })
+            .collect();
+        let policy = musig2_miniscript_policy(keys);
+        Policy::from_miniscript_policy_with_musig2("Test", "", policy.as_str(), &signers).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_musig2_key_path() {
+        let url = MockRelay::run().await;
+        let keys: Vec<Keys> = (0..3).map(|_| Keys::generate()).collect();
+        let path = std::env::temp_dir().join(format!("coinstr-{}.secrets", keys[2].public_key()));
+
+        let mut clients: Vec<CoinstrClient> = Vec::new();
+        for k in keys.iter() {
+            clients.push(client(&url, k).await);
+        }
+        clients[2] = clients[2]
+            .clone()
+            .with_secret_store(SecretStore::open(&path, keys[2].clone()).unwrap());
+
+        // The nostr keys of the policy are replaced with the announced signer keys
+        let members: Vec<XOnlyPublicKey> = keys.iter().map(|k| k.public_key()).collect();
+        for client in clients.iter() {
+            client
+                .announce_signer(SignerPurpose::Bip86, 0, members.clone())
+                .await
+                .unwrap();
+        }
+        let policy_id = clients[0]
+            .save_musig2_policy(
+                "Test",
+                "",
+                musig2_miniscript_policy(&keys).as_str(),
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+        let (policy, _) = clients[1]
+            .get_policy_by_id(policy_id, TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(policy, musig2_policy(&keys));
+        let proposal_id = publish_key_path_proposal(&clients[0], policy_id).await;
+
+        // First round
+        for client in clients.iter() {
+            client
+                .musig2_commit_nonces(proposal_id, TIMEOUT)
+                .await
+                .unwrap();
+        }
+
+        // The last member restarts between the rounds: its secret nonces are in the store
+        clients[2] = client(&url, &keys[2])
+            .await
+            .with_secret_store(SecretStore::open(&path, keys[2].clone()).unwrap());
+
+        // Second round
+        for client in clients.iter() {
+            client.musig2_sign(proposal_id, TIMEOUT).await.unwrap();
+        }
+
+        // The finalizer checks the aggregate signature against the output key
+        let mut psbt = clients[1]
+            .get_musig2_signed_psbt(proposal_id, TIMEOUT)
+            .await
+            .unwrap();
+        psbt.finalize_mut(SECP256K1).unwrap();
+
+        // The nonces can't be used twice
+        assert!(matches!(
+            clients[2].musig2_sign(proposal_id, TIMEOUT).await,
+            Err(Error::MuSig2NoncesNotFound)
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_musig2_invalid_nonces_script_path() {
+        let url = MockRelay::run().await;
+        let keys: Vec<Keys> = (0..3).map(|_| Keys::generate()).collect();
+
+        let mut clients: Vec<CoinstrClient> = Vec::new();
+        for k in keys.iter() {
+            clients.push(client(&url, k).await);
+        }
+
+        let policy_id = clients[0]
+            .save_compiled_policy(musig2_policy(&keys), TIMEOUT)
+            .await
+            .unwrap();
+        let proposal_id = publish_key_path_proposal(&clients[0], policy_id).await;
+
+        // The last member sends garbage instead of its nonces
+        for client in clients[..2].iter() {
+            client
+                .musig2_commit_nonces(proposal_id, TIMEOUT)
+                .await
+                .unwrap();
+        }
+        let (_, _, shared_keys) = clients[2]
+            .get_proposal_by_id(proposal_id, TIMEOUT)
+            .await
+            .unwrap();
+        let content = encryption::encrypt(
+            &keys[2].secret_key().unwrap(),
+            &shared_keys.public_key(),
+            "garbage",
+        )
+        .unwrap();
+        let event = EventBuilder::new(
+            MUSIG2_NONCE_KIND,
+            content.clone(),
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys[2])
+        .unwrap();
+        clients[2].client.send_event(event).await.unwrap();
+
+        // The garbage is discarded: the key path misses the nonces of the last member
+        match clients[0].musig2_sign(proposal_id, TIMEOUT).await {
+            Err(Error::MissingMuSig2Nonces(missing)) => {
+                assert_eq!(missing, vec![keys[2].public_key()])
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        // The first members approve with a script path, that is used to spend
+        for client in clients[..2].iter() {
+            client.approve(proposal_id, TIMEOUT).await.unwrap();
+        }
+
+        // The garbage approval of the last member is discarded too
+        let event = EventBuilder::new(
+            APPROVED_PROPOSAL_KIND,
+            content,
+            &[
+                Tag::Event(proposal_id, None, None),
+                Tag::Event(policy_id, None, None),
+                Tag::PubKey(shared_keys.public_key(), None),
+                EncryptionVersion::Nip44.tag(),
+            ],
+        )
+        .to_event(&keys[2])
+        .unwrap();
+        clients[2].client.send_event(event).await.unwrap();
+        let (_, approvals) = clients[2]
+            .get_signed_psbts_by_proposal_id(proposal_id, TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(approvals.len(), 2);
+        let mut psbt = clients[2]
+            .get_signed_psbt(proposal_id, TIMEOUT)
+            .await
+            .unwrap();
+        assert!(psbt.inputs[0].tap_key_sig.is_none());
+        psbt.finalize_mut(SECP256K1).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_orphan_events() {
+        let url = MockRelay::run().await;
+        let keys: Vec<Keys> = (0..3).map(|_| Keys::generate()).collect();
+
+        let mut clients: Vec<CoinstrClient> = Vec::new();
+        for k in keys.iter() {
+            clients.push(client(&url, k).await);
+        }
+
+        let policy_id = clients[0]
+            .save_compiled_policy(musig2_policy(&keys), TIMEOUT)
+            .await
+            .unwrap();
+        let proposal_id = publish_key_path_proposal(&clients[0], policy_id).await;
+        let approval_id = clients[1].approve(proposal_id, TIMEOUT).await.unwrap();
+
+        // Nothing to delete while the proposal exists
+        assert!(clients[1]
+            .delete_orphan_events(TIMEOUT)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The shared key can't delete the approval of another member
+        clients[0]
+            .delete_proposal_by_id(proposal_id, TIMEOUT)
+            .await
+            .unwrap();
+        let filter = Filter::new().id(approval_id);
+        let events = clients[2]
+            .client
+            .get_events_of(vec![filter.clone()], TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+
+        // The approver deletes it when it sees the deletion of the proposal
+        let deleted = clients[1].delete_orphan_events(TIMEOUT).await.unwrap();
+        assert_eq!(deleted, vec![approval_id]);
+        let events = clients[2]
+            .client
+            .get_events_of(vec![filter], TIMEOUT)
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_shared_key() {
+        let url = MockRelay::run().await;
+        let keys: Vec<Keys> = (0..3).map(|_| Keys::generate()).collect();
+
+        let mut clients: Vec<CoinstrClient> = Vec::new();
+        for k in keys.iter() {
+            clients.push(client(&url, k).await);
+        }
+
+        let policy_id = clients[0]
+            .save_compiled_policy(musig2_policy(&keys), TIMEOUT)
+            .await
+            .unwrap();
+        let original = clients[2]
+            .get_shared_key_by_policy_id(policy_id, TIMEOUT)
+            .await
+            .unwrap();
+
+        // Only the creator can rotate the shared key
+        assert!(matches!(
+            clients[1]
+                .rotate_shared_key(policy_id, Vec::new(), TIMEOUT)
+                .await,
+            Err(Error::NotPolicyCreator)
+        ));
+
+        // The keys sent by the other members are discarded
+        let members: Vec<XOnlyPublicKey> = keys.iter().map(|k| k.public_key()).collect();
+        clients[1]
+            .send_shared_key(
+                policy_id,
+                &Keys::generate(),
+                members,
+                Some(original.public_key()),
+            )
+            .await
+            .unwrap();
+        let shared_key = clients[2]
+            .get_shared_key_by_policy_id(policy_id, TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(shared_key.public_key(), original.public_key());
+
+        // The rotation of the creator supersedes the original key, that is kept in the history
+        clients[0]
+            .rotate_shared_key(policy_id, Vec::new(), TIMEOUT)
+            .await
+            .unwrap();
+        let history = clients[2]
+            .get_shared_key_history_by_policy_id(policy_id, TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].public_key(), original.public_key());
+        clients[2]
+            .get_policy_by_id(policy_id, TIMEOUT)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_frost_keygen_and_key_path() {
+        let url = MockRelay::run().await;
+        let keys: Vec<Keys> = (0..3).map(|_| Keys::generate()).collect();
+        let members: Vec<XOnlyPublicKey> = keys.iter().map(|k| k.public_key()).collect();
+        let path = std::env::temp_dir().join(format!("coinstr-{}.secrets", keys[1].public_key()));
+
+        let mut clients: Vec<CoinstrClient> = Vec::new();
+        for k in keys.iter() {
+            clients.push(client(&url, k).await);
+        }
+        clients[1] = clients[1]
+            .clone()
+            .with_secret_store(SecretStore::open(&path, keys[1].clone()).unwrap());
+        for client in clients.iter() {
+            client
+                .announce_signer(SignerPurpose::Bip86, 0, members.clone())
+                .await
+                .unwrap();
+        }
+
+        // Key generation: the rounds, then the echoes of the commitments
+        let session_id = clients[0]
+            .create_frost_keygen("Test", "", 2, members)
+            .await
+            .unwrap();
+        for client in clients.iter() {
+            client.frost_keygen_join(session_id, TIMEOUT).await.unwrap();
+        }
+        for client in clients.iter() {
+            match client.finalize_frost_keygen(session_id, TIMEOUT).await {
+                Ok(..) | Err(Error::PendingFrostEchoes(..)) => (),
+                Err(e) => panic!("{e}"),
+            }
+        }
+        let mut groups: Vec<FrostGroup> = Vec::new();
+        for client in clients.iter() {
+            groups.push(
+                client
+                    .finalize_frost_keygen(session_id, TIMEOUT)
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert!(groups.iter().all(|group| group == &groups[0]));
+
+        // The policy has a script path, to spend without the group
+        let policy_id = clients[0]
+            .save_frost_policy(session_id, TIMEOUT)
+            .await
+            .unwrap();
+        let (policy, _) = clients[2]
+            .get_policy_by_id(policy_id, TIMEOUT)
+            .await
+            .unwrap();
+        assert!(policy.has_frost_internal_key());
+        assert!(policy.has_script_paths());
+
+        // The script path uses the signer keys of the members, not their nostr keys
+        let member_keys = policy.member_keys().unwrap();
+        assert_eq!(member_keys.len(), keys.len());
+        assert!(member_keys.iter().all(|key| key.xpub.is_some()));
+
+        // Signing rounds, with 2 of the 3 members
+        let proposal_id = publish_key_path_proposal(&clients[0], policy_id).await;
+        let signers: Vec<XOnlyPublicKey> = keys[..2].iter().map(|k| k.public_key()).collect();
+        for client in clients[..2].iter() {
+            client
+                .frost_commit_nonces(proposal_id, signers.clone(), TIMEOUT)
+                .await
+                .unwrap();
+        }
+
+        // The last member commits to another signing set, that the first member doesn't join
+        let other_signers = vec![keys[0].public_key(), keys[2].public_key()];
+        clients[2]
+            .frost_commit_nonces(proposal_id, other_signers, TIMEOUT)
+            .await
+            .unwrap();
+        match clients[2].frost_sign(proposal_id, TIMEOUT).await {
+            Err(Error::MissingFrostNonces(missing)) => {
+                assert_eq!(missing, vec![keys[0].public_key()])
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        // The second member restarts between the rounds: its nonces are in the store
+        clients[1] = client(&url, &keys[1])
+            .await
+            .with_secret_store(SecretStore::open(&path, keys[1].clone()).unwrap());
+        for client in clients[..2].iter() {
+            client.frost_sign(proposal_id, TIMEOUT).await.unwrap();
+        }
+
+        // The finalizer checks the aggregate signature against the output key
+        let mut psbt = clients[2]
+            .get_frost_signed_psbt(proposal_id, TIMEOUT)
+            .await
+            .unwrap();
+        psbt.finalize_mut(SECP256K1).unwrap();
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_tap_merkle_root_mismatch() {
+        let keys: Vec<Keys> = (0..3).map(|_| Keys::generate()).collect();
+        let policy = musig2_policy(&keys);
+        let wallet = Wallet::new(
+            &policy.descriptor.to_string(),
+            None,
+            Network::Testnet,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        let (mut psbt, _) = key_path_psbt(&policy, &wallet);
+        assert!(musig2_session(&policy, &psbt).is_ok());
+
+        // The merkle root set by the proposer must be the one of the policy
+        psbt.inputs[0].tap_merkle_root = Some(TapBranchHash::all_zeros());
+        assert!(matches!(
+            musig2_session(&policy, &psbt),
+            Err(Error::PsbtTapMerkleRootMismatch)
+        ));
+    }
+}
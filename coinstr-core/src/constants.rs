@@ -5,3 +5,22 @@ pub const SHARED_KEY_KIND: Kind = Kind::Custom(9288);
 pub const POLICY_KIND: Kind = Kind::Custom(9289);
 pub const SPENDING_PROPOSAL_KIND: Kind = Kind::Custom(9290);
 pub const APPROVED_PROPOSAL_KIND: Kind = Kind::Custom(9291);
+pub const REJECTED_PROPOSAL_KIND: Kind = Kind::Custom(9292);
+pub const UTXO_LABEL_KIND: Kind = Kind::Custom(9293);
+pub const SIGNER_KEY_KIND: Kind = Kind::Custom(9294);
+pub const POLICY_DRAFT_KIND: Kind = Kind::Custom(9295);
+pub const POLICY_DRAFT_RESPONSE_KIND: Kind = Kind::Custom(9296);
+pub const MUSIG2_NONCE_KIND: Kind = Kind::Custom(9297);
+pub const MUSIG2_PARTIAL_SIG_KIND: Kind = Kind::Custom(9298);
+pub const FROST_KEYGEN_KIND: Kind = Kind::Custom(9299);
+pub const FROST_KEY_PACKAGE_KIND: Kind = Kind::Custom(9300);
+pub const FROST_NONCE_KIND: Kind = Kind::Custom(9301);
+pub const FROST_SIGNATURE_SHARE_KIND: Kind = Kind::Custom(9302);
+
+// Limits
+/// Default max fee rate (sat/vByte) accepted when approving a spending proposal
+pub const DEFAULT_MAX_FEE_RATE: f32 = 100.0;
+
+// Heartbeat
+/// Blocks left before a relative timelock path activates under which the coins are refreshed
+pub const DEFAULT_HEARTBEAT_THRESHOLD: u32 = 1008;
@@ -0,0 +1,112 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+
+use keechain_core::bitcoin::XOnlyPublicKey;
+
+use crate::policy::PolicyTemplate;
+use crate::signer::{SignerAnnouncement, SignerKey};
+
+/// Policy draft, shared by the coordinator with the invited members
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyDraft {
+    pub name: String,
+    pub description: String,
+    pub template: PolicyTemplate,
+    /// Members, in the order of the template keys
+    pub members: Vec<XOnlyPublicKey>,
+    /// Descriptor assembled from the signer keys of the members
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptor: Option<String>,
+}
+
+impl PolicyDraft {
+    pub fn new<S>(
+        name: S,
+        description: S,
+        template: PolicyTemplate,
+        members: Vec<XOnlyPublicKey>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            template,
+            members,
+            descriptor: None,
+        }
+    }
+
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Answer of a member to a policy draft
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DraftResponse {
+    /// Join the draft with a signer key, signed with the nostr public key of the member
+    Join(SignerAnnouncement),
+    /// Confirm the assembled descriptor
+    Confirm(String),
+}
+
+impl DraftResponse {
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Status of a policy draft, as seen by the coordinator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DraftStatus {
+    pub draft: PolicyDraft,
+    /// Signer keys sent by the members
+    pub signers: HashMap<XOnlyPublicKey, SignerKey>,
+    /// Members that confirmed the assembled descriptor
+    pub confirmed_by: Vec<XOnlyPublicKey>,
+}
+
+impl DraftStatus {
+    /// Members that haven't sent a signer key yet
+    pub fn pending_signers(&self) -> Vec<XOnlyPublicKey> {
+        self.draft
+            .members
+            .iter()
+            .filter(|m| !self.signers.contains_key(m))
+            .copied()
+            .collect()
+    }
+
+    /// Members that haven't confirmed the assembled descriptor yet
+    pub fn pending_confirmations(&self) -> Vec<XOnlyPublicKey> {
+        self.draft
+            .members
+            .iter()
+            .filter(|m| !self.confirmed_by.contains(m))
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,19 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+/// Fee to pay for a transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fee {
+    /// Fee rate in sat/vB
+    Rate(f32),
+    /// Absolute fee in sats
+    Absolute(u64),
+    /// Target confirmation blocks, resolved with the fee estimation of the blockchain backend
+    Target(usize),
+}
+
+impl Default for Fee {
+    fn default() -> Self {
+        Self::Target(6)
+    }
+}
@@ -0,0 +1,822 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+//! FROST threshold signatures
+//!
+//! Distributed key generation (Pedersen DKG, with proofs of knowledge) and the two signing rounds,
+//! for a BIP-340 signature of the group key with the taproot tweak.
+//! The participants are identified by their index, starting from `1`.
+
+use std::fmt;
+
+use keechain_core::bitcoin::hashes::{sha256, Hash};
+use keechain_core::bitcoin::secp256k1::rand::rngs::OsRng;
+use keechain_core::bitcoin::secp256k1::{schnorr, PublicKey, SecretKey, XOnlyPublicKey, SECP256K1};
+use keechain_core::bitcoin::util::taproot::TapBranchHash;
+
+use crate::musig2::{self, has_even_y, tagged_hash, to_scalar, TweakedKey};
+
+/// Order of the curve minus 2, to invert with Fermat's little theorem
+const N_MINUS_2: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x3f,
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Secp256k1(#[from] keechain_core::bitcoin::secp256k1::Error),
+    #[error(transparent)]
+    MuSig2(#[from] musig2::Error),
+    #[error("invalid threshold: {threshold} of {participants} participants")]
+    InvalidThreshold {
+        threshold: usize,
+        participants: usize,
+    },
+    #[error("invalid participant index: {0}")]
+    InvalidIndex(u32),
+    #[error("invalid proof of knowledge of participant {0}")]
+    InvalidProofOfKnowledge(u32),
+    #[error("invalid secret share of participant {0}")]
+    InvalidShare(u32),
+    #[error("invalid signature share of participant {0}")]
+    InvalidSignatureShare(u32),
+    #[error("the signers must be at least the threshold, sorted by index")]
+    InvalidSigners,
+    #[error("not a signer of the signing session")]
+    NotSigner,
+    #[error("the nonces don't match the commitment of the signing session")]
+    NoncesMismatch,
+}
+
+/// Secret polynomial of a participant, of degree `threshold - 1`
+pub struct KeyGenSecret {
+    index: u32,
+    coefficients: Vec<SecretKey>,
+}
+
+impl fmt::Debug for KeyGenSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyGenSecret")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl KeyGenSecret {
+    /// Derive the polynomial of the key generation `session` from a secret key
+    ///
+    /// The same polynomial is derived again when needed, without keeping it anywhere.
+    pub fn derive(
+        secret_key: &SecretKey,
+        session: &[u8; 32],
+        index: u32,
+        threshold: usize,
+    ) -> Result<Self, Error> {
+        if threshold == 0 {
+            return Err(Error::InvalidThreshold {
+                threshold,
+                participants: 0,
+            });
+        }
+        scalar_from_index(index)?;
+
+        let mut coefficients: Vec<SecretKey> = Vec::with_capacity(threshold);
+        for k in 0..threshold as u32 {
+            let hash = tagged_hash(
+                "Coinstr/frost-keygen",
+                &[&secret_key.secret_bytes(), session, &k.to_be_bytes()],
+            );
+            coefficients.push(SecretKey::from_slice(&hash)?);
+        }
+
+        Ok(Self {
+            index,
+            coefficients,
+        })
+    }
+
+    /// Commitments to the coefficients, with the proof of knowledge of the secret
+    pub fn commitment(&self, session: &[u8; 32]) -> Result<KeyGenCommitment, Error> {
+        let commitments: Vec<PublicKey> = self
+            .coefficients
+            .iter()
+            .map(|a| PublicKey::from_secret_key(SECP256K1, a))
+            .collect();
+
+        // Schnorr proof of knowledge of `a_0`, bound to the index and the session
+        let a0 = &self.coefficients[0];
+        let k = SecretKey::from_slice(&tagged_hash(
+            "Coinstr/frost-pok-nonce",
+            &[&a0.secret_bytes(), session],
+        ))?;
+        let r = PublicKey::from_secret_key(SECP256K1, &k);
+        let c = pok_challenge(self.index, session, &commitments[0], &r)?;
+        let mu = k.add_tweak(&to_scalar(&a0.mul_tweak(&to_scalar(&c))?))?;
+
+        Ok(KeyGenCommitment {
+            commitments,
+            proof: (r, mu),
+        })
+    }
+
+    /// Secret share for the participant `index`: `f(index)`
+    pub fn share(&self, index: u32) -> Result<SecretKey, Error> {
+        let x = scalar_from_index(index)?;
+        let mut coefficients = self.coefficients.iter().rev();
+        let mut result: SecretKey = *coefficients.next().ok_or(Error::InvalidThreshold {
+            threshold: 0,
+            participants: 0,
+        })?;
+        for a in coefficients {
+            result = result.mul_tweak(&to_scalar(&x))?.add_tweak(&to_scalar(a))?;
+        }
+        Ok(result)
+    }
+}
+
+/// Public commitments of a participant to its polynomial
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyGenCommitment {
+    pub commitments: Vec<PublicKey>,
+    /// Proof of knowledge of the secret (`R`, `mu`)
+    pub proof: (PublicKey, SecretKey),
+}
+
+impl KeyGenCommitment {
+    /// Verify the proof of knowledge of the participant `index`
+    pub fn verify(&self, index: u32, session: &[u8; 32], threshold: usize) -> Result<(), Error> {
+        if self.commitments.len() != threshold {
+            return Err(Error::InvalidProofOfKnowledge(index));
+        }
+        let (r, mu) = &self.proof;
+        let c = pok_challenge(index, session, &self.commitments[0], r)?;
+        let expected = r.combine(&self.commitments[0].mul_tweak(SECP256K1, &to_scalar(&c))?)?;
+        if PublicKey::from_secret_key(SECP256K1, mu) == expected {
+            Ok(())
+        } else {
+            Err(Error::InvalidProofOfKnowledge(index))
+        }
+    }
+
+    /// Public key of the share of the participant `index`: `f(index) * G`
+    fn evaluate(&self, index: u32) -> Result<PublicKey, Error> {
+        let x = scalar_from_index(index)?;
+        let mut commitments = self.commitments.iter().rev();
+        let mut result: PublicKey = *commitments.next().ok_or(Error::InvalidIndex(index))?;
+        for commitment in commitments {
+            result = result
+                .mul_tweak(SECP256K1, &to_scalar(&x))?
+                .combine(commitment)?;
+        }
+        Ok(result)
+    }
+
+    /// Verify the secret share sent to the participant `index`
+    pub fn verify_share(&self, index: u32, share: &SecretKey) -> Result<(), Error> {
+        if PublicKey::from_secret_key(SECP256K1, share) == self.evaluate(index)? {
+            Ok(())
+        } else {
+            Err(Error::InvalidShare(index))
+        }
+    }
+}
+
+/// FROST group: the group key and the verification shares of the participants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrostGroup {
+    pub threshold: usize,
+    pub group_key: PublicKey,
+    /// Public keys of the signing shares, in the order of the participants indexes
+    pub verification_shares: Vec<PublicKey>,
+}
+
+impl FrostGroup {
+    /// Compute the group from the commitments of all the participants, in the order of their indexes
+    pub fn from_commitments(
+        threshold: usize,
+        commitments: &[KeyGenCommitment],
+    ) -> Result<Self, Error> {
+        if threshold == 0 || threshold > commitments.len() {
+            return Err(Error::InvalidThreshold {
+                threshold,
+                participants: commitments.len(),
+            });
+        }
+
+        for (index, commitment) in (1..).zip(commitments.iter()) {
+            if commitment.commitments.len() != threshold {
+                return Err(Error::InvalidProofOfKnowledge(index));
+            }
+        }
+
+        let constants: Vec<&PublicKey> = commitments.iter().map(|c| &c.commitments[0]).collect();
+        let group_key = PublicKey::combine_keys(&constants)?;
+
+        let mut verification_shares: Vec<PublicKey> = Vec::with_capacity(commitments.len());
+        for index in 1..=commitments.len() as u32 {
+            let shares: Vec<PublicKey> = commitments
+                .iter()
+                .map(|c| c.evaluate(index))
+                .collect::<Result<_, _>>()?;
+            let shares: Vec<&PublicKey> = shares.iter().collect();
+            verification_shares.push(PublicKey::combine_keys(&shares)?);
+        }
+
+        Ok(Self {
+            threshold,
+            group_key,
+            verification_shares,
+        })
+    }
+
+    /// Group key, as x-only key
+    pub fn x_only_public_key(&self) -> XOnlyPublicKey {
+        self.group_key.x_only_public_key().0
+    }
+
+    fn verification_share(&self, index: u32) -> Result<&PublicKey, Error> {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.verification_shares.get(i as usize))
+            .ok_or(Error::InvalidIndex(index))
+    }
+}
+
+/// Signing share of a participant, with its group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPackage {
+    pub index: u32,
+    pub signing_share: SecretKey,
+    pub group: FrostGroup,
+}
+
+impl KeyPackage {
+    /// Sum the secret shares received by the participant `index` (its own included)
+    pub fn new(index: u32, group: FrostGroup, shares: &[SecretKey]) -> Result<Self, Error> {
+        let (first, shares) = shares.split_first().ok_or(Error::InvalidShare(index))?;
+        let mut signing_share: SecretKey = *first;
+        for share in shares.iter() {
+            signing_share = signing_share.add_tweak(&to_scalar(share))?;
+        }
+
+        if &PublicKey::from_secret_key(SECP256K1, &signing_share)
+            != group.verification_share(index)?
+        {
+            return Err(Error::InvalidShare(index));
+        }
+
+        Ok(Self {
+            index,
+            signing_share,
+            group,
+        })
+    }
+
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Public commitment to the signing nonces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningCommitment {
+    pub hiding: PublicKey,
+    pub binding: PublicKey,
+}
+
+/// Signing nonces, to use only once
+#[derive(Serialize, Deserialize)]
+pub struct SigningNonces {
+    hiding: SecretKey,
+    binding: SecretKey,
+    commitment: SigningCommitment,
+}
+
+impl fmt::Debug for SigningNonces {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningNonces")
+            .field("commitment", &self.commitment)
+            .finish()
+    }
+}
+
+impl SigningNonces {
+    /// Generate random signing nonces
+    pub fn generate() -> Self {
+        let hiding = SecretKey::new(&mut OsRng);
+        let binding = SecretKey::new(&mut OsRng);
+        Self {
+            hiding,
+            binding,
+            commitment: SigningCommitment {
+                hiding: PublicKey::from_secret_key(SECP256K1, &hiding),
+                binding: PublicKey::from_secret_key(SECP256K1, &binding),
+            },
+        }
+    }
+
+    pub fn commitment(&self) -> SigningCommitment {
+        self.commitment
+    }
+}
+
+/// Signature share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureShare(SecretKey);
+
+/// Group key of a signing session, with the taproot tweak
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningContext {
+    group: FrostGroup,
+    key: TweakedKey,
+}
+
+impl SigningContext {
+    /// Sign for the output key of the group key used as taproot internal key
+    pub fn new(group: FrostGroup, merkle_root: Option<TapBranchHash>) -> Result<Self, Error> {
+        let key = TweakedKey::new(group.group_key).with_taproot_tweak(merkle_root)?;
+        Ok(Self { group, key })
+    }
+
+    /// Output key, as x-only key
+    pub fn output_key(&self) -> XOnlyPublicKey {
+        self.key.x_only_public_key()
+    }
+}
+
+/// Values shared by the signers of a message
+struct Session {
+    signers: Vec<u32>,
+    binding_factors: Vec<SecretKey>,
+    r: PublicKey,
+    c: SecretKey,
+}
+
+impl Session {
+    fn new(
+        ctx: &SigningContext,
+        commitments: &[(u32, SigningCommitment)],
+        msg: &[u8; 32],
+    ) -> Result<Self, Error> {
+        let signers: Vec<u32> = commitments.iter().map(|(index, _)| *index).collect();
+        if signers.len() < ctx.group.threshold
+            || signers.windows(2).any(|w| w[0] >= w[1])
+            || signers
+                .iter()
+                .any(|index| ctx.group.verification_share(*index).is_err())
+        {
+            return Err(Error::InvalidSigners);
+        }
+
+        let mut encoded: Vec<u8> = Vec::with_capacity(commitments.len() * 70);
+        for (index, commitment) in commitments.iter() {
+            encoded.extend(index.to_be_bytes());
+            encoded.extend(commitment.hiding.serialize());
+            encoded.extend(commitment.binding.serialize());
+        }
+
+        let q = ctx.output_key().serialize();
+        let mut binding_factors: Vec<SecretKey> = Vec::with_capacity(commitments.len());
+        let mut nonces: Vec<PublicKey> = Vec::with_capacity(commitments.len());
+        for (index, commitment) in commitments.iter() {
+            let rho = SecretKey::from_slice(&tagged_hash(
+                "Coinstr/frost-binding",
+                &[&index.to_be_bytes(), &q, msg, &encoded],
+            ))?;
+            nonces.push(
+                commitment
+                    .hiding
+                    .combine(&commitment.binding.mul_tweak(SECP256K1, &to_scalar(&rho))?)?,
+            );
+            binding_factors.push(rho);
+        }
+        let nonces: Vec<&PublicKey> = nonces.iter().collect();
+        let r = PublicKey::combine_keys(&nonces)?;
+
+        let c = SecretKey::from_slice(&tagged_hash(
+            "BIP0340/challenge",
+            &[&r.x_only_public_key().0.serialize(), &q, msg],
+        ))?;
+
+        Ok(Self {
+            signers,
+            binding_factors,
+            r,
+            c,
+        })
+    }
+
+    fn position(&self, index: u32) -> Result<usize, Error> {
+        self.signers
+            .iter()
+            .position(|i| *i == index)
+            .ok_or(Error::NotSigner)
+    }
+}
+
+/// Sign `msg` with the signing share, consuming the nonces
+///
+/// The `commitments` of the signers must be sorted by index.
+pub fn sign(
+    ctx: &SigningContext,
+    key_package: &KeyPackage,
+    nonces: SigningNonces,
+    commitments: &[(u32, SigningCommitment)],
+    msg: &[u8; 32],
+) -> Result<SignatureShare, Error> {
+    let session = Session::new(ctx, commitments, msg)?;
+    let position = session.position(key_package.index)?;
+    if commitments[position].1 != nonces.commitment {
+        return Err(Error::NoncesMismatch);
+    }
+
+    let (mut d, mut e) = (nonces.hiding, nonces.binding);
+    if !has_even_y(&session.r) {
+        d = d.negate();
+        e = e.negate();
+    }
+
+    let mut x = key_package.signing_share;
+    if ctx.key.negate_secret() {
+        x = x.negate();
+    }
+
+    // z = d + rho * e + c * lambda * x
+    let lambda = lagrange_coefficient(key_package.index, &session.signers)?;
+    let rho_e = e.mul_tweak(&to_scalar(&session.binding_factors[position]))?;
+    let c_lambda_x = x
+        .mul_tweak(&to_scalar(&lambda))?
+        .mul_tweak(&to_scalar(&session.c))?;
+    let z = d
+        .add_tweak(&to_scalar(&rho_e))?
+        .add_tweak(&to_scalar(&c_lambda_x))?;
+
+    Ok(SignatureShare(z))
+}
+
+/// Verify the signature share of the participant `index`
+pub fn verify_signature_share(
+    ctx: &SigningContext,
+    index: u32,
+    share: &SignatureShare,
+    commitments: &[(u32, SigningCommitment)],
+    msg: &[u8; 32],
+) -> Result<(), Error> {
+    let session = Session::new(ctx, commitments, msg)?;
+    let position = session.position(index)?;
+    let commitment = &commitments[position].1;
+
+    // R_i = D_i + rho_i * E_i
+    let mut r = commitment.hiding.combine(
+        &commitment
+            .binding
+            .mul_tweak(SECP256K1, &to_scalar(&session.binding_factors[position]))?,
+    )?;
+    if !has_even_y(&session.r) {
+        r = r.negate(SECP256K1);
+    }
+
+    // z_i * G = R_i + c * lambda_i * Y_i
+    let mut y = *ctx.group.verification_share(index)?;
+    if ctx.key.negate_secret() {
+        y = y.negate(SECP256K1);
+    }
+    let lambda = lagrange_coefficient(index, &session.signers)?;
+    let c_lambda = lambda.mul_tweak(&to_scalar(&session.c))?;
+    let expected = r.combine(&y.mul_tweak(SECP256K1, &to_scalar(&c_lambda))?)?;
+
+    if PublicKey::from_secret_key(SECP256K1, &share.0) == expected {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignatureShare(index))
+    }
+}
+
+/// Aggregate the signature shares into a BIP-340 signature for the output key
+///
+/// The `shares` must be in the same order as the `commitments`.
+pub fn aggregate(
+    ctx: &SigningContext,
+    commitments: &[(u32, SigningCommitment)],
+    shares: &[SignatureShare],
+    msg: &[u8; 32],
+) -> Result<schnorr::Signature, Error> {
+    let session = Session::new(ctx, commitments, msg)?;
+    if shares.len() != commitments.len() {
+        return Err(Error::InvalidSigners);
+    }
+
+    let mut z: SecretKey = shares[0].0;
+    for share in shares.iter().skip(1) {
+        z = z.add_tweak(&to_scalar(&share.0))?;
+    }
+
+    // z += c * g * tacc
+    if let Some(ctacc) = ctx.key.signature_tweak(&session.c)? {
+        z = z.add_tweak(&to_scalar(&ctacc))?;
+    }
+
+    let mut sig = [0u8; 64];
+    sig[..32].copy_from_slice(&session.r.x_only_public_key().0.serialize());
+    sig[32..].copy_from_slice(&z.secret_bytes());
+    Ok(schnorr::Signature::from_slice(&sig)?)
+}
+
+/// FROST key generation session, sent by the coordinator to the members
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrostKeyGen {
+    pub name: String,
+    pub description: String,
+    pub threshold: usize,
+    /// Nostr public keys of the members, in the order of their indexes
+    pub members: Vec<XOnlyPublicKey>,
+}
+
+impl FrostKeyGen {
+    pub fn new<S>(
+        name: S,
+        description: S,
+        threshold: usize,
+        members: Vec<XOnlyPublicKey>,
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        if threshold == 0 || threshold > members.len() {
+            return Err(Error::InvalidThreshold {
+                threshold,
+                participants: members.len(),
+            });
+        }
+        Ok(Self {
+            name: name.into(),
+            description: description.into(),
+            threshold,
+            members,
+        })
+    }
+
+    /// Index of a member, starting from `1`
+    pub fn index_of(&self, member: &XOnlyPublicKey) -> Option<u32> {
+        self.members
+            .iter()
+            .position(|m| m == member)
+            .map(|pos| pos as u32 + 1)
+    }
+}
+
+/// Key generation round of a member, sent to each other member
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyGenRound {
+    pub commitment: KeyGenCommitment,
+    /// Secret share for the recipient
+    pub share: SecretKey,
+}
+
+/// Message of a key generation session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyGenMessage {
+    Session(FrostKeyGen),
+    Round(KeyGenRound),
+    /// Hash of the commitments received by a member (check [`commitments_hash`])
+    Echo(sha256::Hash),
+}
+
+impl KeyGenMessage {
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Signing commitments of a signer, for each input of a transaction,
+/// with the indexes of the signing set they are committed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningCommitments {
+    pub signers: Vec<u32>,
+    pub commitments: Vec<SigningCommitment>,
+}
+
+impl SigningCommitments {
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Signature shares of a signer, for each input of a transaction,
+/// with the indexes of the signers they are made for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureShares {
+    pub signers: Vec<u32>,
+    pub shares: Vec<SignatureShare>,
+}
+
+impl SignatureShares {
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Hash of the commitments of all the participants, in the order of their indexes
+///
+/// Each participant sends it to the others (echo round): the group is accepted only if
+/// all of them received the same commitments.
+pub fn commitments_hash(session: &[u8; 32], commitments: &[KeyGenCommitment]) -> sha256::Hash {
+    let mut data: Vec<Vec<u8>> = Vec::new();
+    for commitment in commitments.iter() {
+        data.extend(
+            commitment
+                .commitments
+                .iter()
+                .map(|c| c.serialize().to_vec()),
+        );
+        data.push(commitment.proof.0.serialize().to_vec());
+        data.push(commitment.proof.1.secret_bytes().to_vec());
+    }
+    let mut chunks: Vec<&[u8]> = vec![session];
+    chunks.extend(data.iter().map(|d| d.as_slice()));
+    sha256::Hash::from_inner(tagged_hash("Coinstr/frost-echo", &chunks))
+}
+
+fn pok_challenge(
+    index: u32,
+    session: &[u8; 32],
+    commitment: &PublicKey,
+    r: &PublicKey,
+) -> Result<SecretKey, Error> {
+    Ok(SecretKey::from_slice(&tagged_hash(
+        "Coinstr/frost-pok",
+        &[
+            &index.to_be_bytes(),
+            session,
+            &commitment.serialize(),
+            &r.serialize(),
+        ],
+    ))?)
+}
+
+fn scalar_from_index(index: u32) -> Result<SecretKey, Error> {
+    let mut bytes = [0u8; 32];
+    bytes[28..].copy_from_slice(&index.to_be_bytes());
+    SecretKey::from_slice(&bytes).map_err(|_| Error::InvalidIndex(index))
+}
+
+/// `x^-1`, as `x^(n-2)`
+fn invert(x: &SecretKey) -> Result<SecretKey, Error> {
+    let mut result: Option<SecretKey> = None;
+    for byte in N_MINUS_2.iter() {
+        for bit in (0..8).rev() {
+            if let Some(r) = result {
+                result = Some(r.mul_tweak(&to_scalar(&r))?);
+            }
+            if (byte >> bit) & 1 == 1 {
+                result = Some(match result {
+                    Some(r) => r.mul_tweak(&to_scalar(x))?,
+                    None => *x,
+                });
+            }
+        }
+    }
+    result.ok_or(Error::InvalidSigners)
+}
+
+/// Lagrange coefficient of `index` at `0`, for the `signers` set
+fn lagrange_coefficient(index: u32, signers: &[u32]) -> Result<SecretKey, Error> {
+    let xi = scalar_from_index(index)?;
+    let mut numerator: SecretKey = scalar_from_index(1)?;
+    let mut denominator: SecretKey = scalar_from_index(1)?;
+    for j in signers.iter().filter(|j| **j != index) {
+        let xj = scalar_from_index(*j)?;
+        let diff = xj.add_tweak(&to_scalar(&xi.negate()))?;
+        numerator = numerator.mul_tweak(&to_scalar(&xj))?;
+        denominator = denominator.mul_tweak(&to_scalar(&diff))?;
+    }
+    Ok(numerator.mul_tweak(&to_scalar(&invert(&denominator)?))?)
+}
+
+#[cfg(test)]
+mod test {
+    use keechain_core::bitcoin::hashes::Hash;
+    use keechain_core::bitcoin::secp256k1::Message;
+
+    use super::*;
+
+    fn keygen(threshold: usize, participants: u32) -> Vec<KeyPackage> {
+        let session = [1u8; 32];
+        let secrets: Vec<KeyGenSecret> = (1..=participants)
+            .map(|index| {
+                KeyGenSecret::derive(&SecretKey::new(&mut OsRng), &session, index, threshold)
+                    .unwrap()
+            })
+            .collect();
+        let commitments: Vec<KeyGenCommitment> = secrets
+            .iter()
+            .map(|s| s.commitment(&session).unwrap())
+            .collect();
+        for (index, commitment) in (1..=participants).zip(commitments.iter()) {
+            commitment.verify(index, &session, threshold).unwrap();
+        }
+
+        let group = FrostGroup::from_commitments(threshold, &commitments).unwrap();
+        (1..=participants)
+            .map(|index| {
+                let shares: Vec<SecretKey> = secrets
+                    .iter()
+                    .zip(commitments.iter())
+                    .map(|(secret, commitment)| {
+                        let share = secret.share(index).unwrap();
+                        commitment.verify_share(index, &share).unwrap();
+                        share
+                    })
+                    .collect();
+                KeyPackage::new(index, group.clone(), &shares).unwrap()
+            })
+            .collect()
+    }
+
+    fn sign_with(key_packages: &[&KeyPackage], merkle_root: Option<TapBranchHash>) {
+        let ctx = SigningContext::new(key_packages[0].group.clone(), merkle_root).unwrap();
+        let msg = [42u8; 32];
+
+        // Round 1
+        let nonces: Vec<SigningNonces> = key_packages
+            .iter()
+            .map(|_| SigningNonces::generate())
+            .collect();
+        let commitments: Vec<(u32, SigningCommitment)> = key_packages
+            .iter()
+            .zip(nonces.iter())
+            .map(|(kp, n)| (kp.index, n.commitment()))
+            .collect();
+
+        // Round 2
+        let mut shares: Vec<SignatureShare> = Vec::new();
+        for (key_package, nonces) in key_packages.iter().zip(nonces) {
+            let share = sign(&ctx, key_package, nonces, &commitments, &msg).unwrap();
+            verify_signature_share(&ctx, key_package.index, &share, &commitments, &msg).unwrap();
+            shares.push(share);
+        }
+
+        let sig = aggregate(&ctx, &commitments, &shares, &msg).unwrap();
+        let msg = Message::from_slice(&msg).unwrap();
+        SECP256K1
+            .verify_schnorr(&sig, &msg, &ctx.output_key())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_keygen_and_sign() {
+        let key_packages = keygen(2, 3);
+        let merkle_root = Some(TapBranchHash::from_inner([7u8; 32]));
+        sign_with(&[&key_packages[0], &key_packages[2]], merkle_root);
+        sign_with(&[&key_packages[1], &key_packages[2]], None);
+        sign_with(&key_packages.iter().collect::<Vec<_>>(), merkle_root);
+    }
+
+    #[test]
+    fn test_invalid_share() {
+        let session = [2u8; 32];
+        let secret = KeyGenSecret::derive(&SecretKey::new(&mut OsRng), &session, 1, 2).unwrap();
+        let commitment = secret.commitment(&session).unwrap();
+        assert!(commitment.verify(2, &session, 2).is_err());
+        let share = secret.share(2).unwrap();
+        assert!(commitment.verify_share(3, &share).is_err());
+    }
+}
@@ -1,13 +1,17 @@
 // Copyright (c) 2022-2023 Coinstr
 // Distributed under the MIT software license
 
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use keechain_core::bip39::Mnemonic;
+use keechain_core::bitcoin::util::bip32::ExtendedPrivKey;
 use keechain_core::bitcoin::Network;
 use keechain_core::types::{KeeChain, Keychain, WordCount};
+use keechain_core::util::bip::bip32::Bip32RootKey;
 use keechain_core::Result;
 
+use crate::secrets::{self, SecretStore};
 use crate::CoinstrClient;
 
 #[derive(Debug, thiserror::Error)]
@@ -22,6 +26,10 @@ pub enum Error {
     Nip06(#[from] nostr_sdk::nips::nip06::Error),
     #[error(transparent)]
     Client(#[from] crate::client::Error),
+    #[error(transparent)]
+    Secrets(#[from] secrets::Error),
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
     #[error("{0}")]
     Generic(String),
 }
@@ -31,6 +39,7 @@ pub enum Error {
 pub struct Coinstr {
     network: Network,
     keechain: KeeChain,
+    path: PathBuf,
 }
 
 impl Coinstr {
@@ -39,11 +48,16 @@ impl Coinstr {
         P: AsRef<Path>,
         PSW: FnOnce() -> Result<String>,
     {
-        let mut keechain: KeeChain = KeeChain::open(path, get_password)?;
+        let path = path.as_ref().to_path_buf();
+        let mut keechain: KeeChain = KeeChain::open(&path, get_password)?;
         let passphrase: Option<String> = keechain.keychain.get_passphrase(0);
         keechain.keychain.apply_passphrase(passphrase);
 
-        Ok(Self { network, keechain })
+        Ok(Self {
+            network,
+            keechain,
+            path,
+        })
     }
 
     pub fn generate<P, PSW, PASSP>(
@@ -58,8 +72,9 @@ impl Coinstr {
         PSW: FnOnce() -> Result<String>,
         PASSP: FnOnce() -> Result<Option<String>>,
     {
+        let path = path.as_ref().to_path_buf();
         let mut keechain: KeeChain =
-            KeeChain::generate(path, get_password, word_count, || Ok(None))?;
+            KeeChain::generate(&path, get_password, word_count, || Ok(None))?;
         let passphrase: Option<String> =
             get_passphrase().map_err(|e| Error::Generic(e.to_string()))?;
         if let Some(passphrase) = passphrase {
@@ -68,7 +83,11 @@ impl Coinstr {
             keechain.keychain.apply_passphrase(Some(passphrase));
         }
 
-        Ok(Self { network, keechain })
+        Ok(Self {
+            network,
+            keechain,
+            path,
+        })
     }
 
     pub fn restore<P, PSW, M, PASSP>(
@@ -84,7 +103,8 @@ impl Coinstr {
         M: FnOnce() -> Result<Mnemonic>,
         PASSP: FnOnce() -> Result<Option<String>>,
     {
-        let mut keechain: KeeChain = KeeChain::restore(path, get_password, get_mnemonic)?;
+        let path = path.as_ref().to_path_buf();
+        let mut keechain: KeeChain = KeeChain::restore(&path, get_password, get_mnemonic)?;
         let passphrase: Option<String> =
             get_passphrase().map_err(|e| Error::Generic(e.to_string()))?;
         if let Some(passphrase) = passphrase {
@@ -93,7 +113,11 @@ impl Coinstr {
             keechain.keychain.apply_passphrase(Some(passphrase));
         }
 
-        Ok(Self { network, keechain })
+        Ok(Self {
+            network,
+            keechain,
+            path,
+        })
     }
 
     pub fn save(&self) -> Result<(), Error> {
@@ -111,7 +135,14 @@ impl Coinstr {
     where
         P: AsRef<Path>,
     {
-        Ok(self.keechain.rename(path)?)
+        let path = path.as_ref().to_path_buf();
+        self.keechain.rename(&path)?;
+        let secrets_path = self.secrets_path();
+        self.path = path;
+        if secrets_path.exists() {
+            fs::rename(secrets_path, self.secrets_path())?;
+        }
+        Ok(())
     }
 
     pub fn change_password<NPSW>(&mut self, get_new_password: NPSW) -> Result<(), Error>
@@ -122,7 +153,12 @@ impl Coinstr {
     }
 
     pub fn wipe(&self) -> Result<(), Error> {
-        Ok(self.keechain.wipe()?)
+        self.keechain.wipe()?;
+        let secrets_path = self.secrets_path();
+        if secrets_path.exists() {
+            fs::remove_file(secrets_path)?;
+        }
+        Ok(())
     }
 
     pub fn keychain(&self) -> Keychain {
@@ -133,15 +169,37 @@ impl Coinstr {
         self.network
     }
 
+    /// Path of the secret store, next to the keychain file
+    fn secrets_path(&self) -> PathBuf {
+        self.path.with_extension("secrets")
+    }
+
+    fn root_key(&self) -> Result<ExtendedPrivKey, Error> {
+        self.keechain
+            .keychain
+            .seed
+            .to_bip32_root_key(self.network)
+            .map_err(|e| Error::Generic(e.to_string()))
+    }
+
     #[cfg(not(feature = "blocking"))]
     pub async fn client(&self, relays: Vec<String>) -> Result<CoinstrClient, Error> {
         let keys = self.keechain.keychain.nostr_keys()?;
-        Ok(CoinstrClient::new(keys, relays, self.network).await?)
+        let root_key = self.root_key()?;
+        let secrets = SecretStore::open(self.secrets_path(), keys.clone())?;
+        Ok(CoinstrClient::new(keys, relays, self.network)
+            .await?
+            .with_signer(root_key)
+            .with_secret_store(secrets))
     }
 
     #[cfg(feature = "blocking")]
     pub fn client(&self, relays: Vec<String>) -> Result<CoinstrClient, Error> {
         let keys = self.keechain.keychain.nostr_keys()?;
-        Ok(CoinstrClient::new(keys, relays, self.network)?)
+        let root_key = self.root_key()?;
+        let secrets = SecretStore::open(self.secrets_path(), keys.clone())?;
+        Ok(CoinstrClient::new(keys, relays, self.network)?
+            .with_signer(root_key)
+            .with_secret_store(secrets))
     }
 }
@@ -12,11 +12,18 @@ pub use keechain_core::*;
 
 pub mod client;
 pub mod constants;
+pub mod draft;
+pub mod fee;
+pub mod frost;
 #[cfg(not(target_arch = "wasm32"))]
 mod keychain;
+pub mod musig2;
 pub mod policy;
 pub mod proposal;
+pub mod secrets;
+pub mod signer;
 pub mod util;
+pub mod utxo;
 
 #[cfg(feature = "blocking")]
 pub use self::client::blocking::CoinstrClient;
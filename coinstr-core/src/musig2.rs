@@ -0,0 +1,576 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+//! MuSig2 (BIP-327)
+//!
+//! Key aggregation, with the taproot tweak, and the two signing rounds.
+//! The secret nonces must be used only once: [`partial_sign`] consumes them.
+
+use std::fmt;
+use std::str::FromStr;
+
+use keechain_core::bitcoin::hashes::hex::{FromHex, ToHex};
+use keechain_core::bitcoin::hashes::{sha256, Hash, HashEngine};
+use keechain_core::bitcoin::secp256k1::rand::rngs::OsRng;
+use keechain_core::bitcoin::secp256k1::{
+    schnorr, Parity, PublicKey, Scalar, SecretKey, XOnlyPublicKey, SECP256K1,
+};
+use keechain_core::bitcoin::util::taproot::TapBranchHash;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Secp256k1(#[from] keechain_core::bitcoin::secp256k1::Error),
+    #[error(transparent)]
+    Hex(#[from] keechain_core::bitcoin::hashes::hex::Error),
+    #[error("no public keys")]
+    NoPublicKeys,
+    #[error("invalid nonce")]
+    InvalidNonce,
+    #[error("public key not in the aggregate key")]
+    NotParticipant,
+    #[error("invalid partial signature")]
+    InvalidPartialSignature,
+}
+
+/// Aggregate key of the participants, with the tweaks applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAggContext {
+    pubkeys: Vec<PublicKey>,
+    list_hash: [u8; 32],
+    second_key: Option<PublicKey>,
+    key: TweakedKey,
+}
+
+impl KeyAggContext {
+    /// Aggregate the x-only keys of the participants, sorted
+    pub fn new(pubkeys: &[XOnlyPublicKey]) -> Result<Self, Error> {
+        let mut pubkeys: Vec<PublicKey> = pubkeys
+            .iter()
+            .map(|pk| pk.public_key(Parity::Even))
+            .collect();
+        pubkeys.sort_by_key(|pk| pk.serialize());
+        pubkeys.dedup();
+        Self::from_public_keys(pubkeys)
+    }
+
+    /// Aggregate the keys of the participants, in the given order
+    pub fn from_public_keys(pubkeys: Vec<PublicKey>) -> Result<Self, Error> {
+        let first: PublicKey = *pubkeys.first().ok_or(Error::NoPublicKeys)?;
+        let second_key = pubkeys.iter().find(|pk| **pk != first).copied();
+
+        let serialized: Vec<[u8; 33]> = pubkeys.iter().map(|pk| pk.serialize()).collect();
+        let list: Vec<&[u8]> = serialized.iter().map(|pk| &pk[..]).collect();
+        let list_hash = tagged_hash("KeyAgg list", &list);
+
+        let mut ctx = Self {
+            pubkeys,
+            list_hash,
+            second_key,
+            key: TweakedKey::new(first),
+        };
+
+        let mut points: Vec<PublicKey> = Vec::with_capacity(ctx.pubkeys.len());
+        for pk in ctx.pubkeys.iter() {
+            let a = ctx.coefficient(pk)?;
+            points.push(pk.mul_tweak(SECP256K1, &to_scalar(&a))?);
+        }
+        let points: Vec<&PublicKey> = points.iter().collect();
+        ctx.key = TweakedKey::new(PublicKey::combine_keys(&points)?);
+
+        Ok(ctx)
+    }
+
+    fn coefficient(&self, pk: &PublicKey) -> Result<SecretKey, Error> {
+        if Some(pk) == self.second_key.as_ref() {
+            let mut one = [0u8; 32];
+            one[31] = 1;
+            return Ok(SecretKey::from_slice(&one)?);
+        }
+        let hash = tagged_hash("KeyAgg coefficient", &[&self.list_hash, &pk.serialize()]);
+        Ok(SecretKey::from_slice(&hash)?)
+    }
+
+    /// Aggregate key, as x-only key
+    pub fn aggregate_key(&self) -> XOnlyPublicKey {
+        self.key.x_only_public_key()
+    }
+
+    /// Check if `public_key` is one of the participants
+    pub fn contains(&self, public_key: &XOnlyPublicKey) -> bool {
+        self.pubkeys.contains(&public_key.public_key(Parity::Even))
+    }
+
+    /// Apply the BIP-341 tweak, to sign for the output key of a taproot internal key
+    pub fn with_taproot_tweak(self, merkle_root: Option<TapBranchHash>) -> Result<Self, Error> {
+        Ok(Self {
+            key: self.key.with_taproot_tweak(merkle_root)?,
+            ..self
+        })
+    }
+}
+
+/// Aggregate key `Q`, with the accumulated sign (`gacc`) and tweak (`tacc`) of the BIP-327 tweaking
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TweakedKey {
+    pub q: PublicKey,
+    /// `gacc` is `-1`
+    pub negated: bool,
+    pub tacc: Option<SecretKey>,
+}
+
+impl TweakedKey {
+    pub fn new(q: PublicKey) -> Self {
+        Self {
+            q,
+            negated: false,
+            tacc: None,
+        }
+    }
+
+    pub fn x_only_public_key(&self) -> XOnlyPublicKey {
+        self.q.x_only_public_key().0
+    }
+
+    /// `g * gacc` is `-1`: the secret keys must be negated to sign for the x-only key
+    pub fn negate_secret(&self) -> bool {
+        has_even_y(&self.q) == self.negated
+    }
+
+    pub fn with_taproot_tweak(self, merkle_root: Option<TapBranchHash>) -> Result<Self, Error> {
+        let internal_key = self.x_only_public_key().serialize();
+        let tweak = match merkle_root {
+            Some(merkle_root) => tagged_hash("TapTweak", &[&internal_key, &merkle_root[..]]),
+            None => tagged_hash("TapTweak", &[&internal_key]),
+        };
+        self.with_xonly_tweak(SecretKey::from_slice(&tweak)?)
+    }
+
+    fn with_xonly_tweak(self, tweak: SecretKey) -> Result<Self, Error> {
+        let even = has_even_y(&self.q);
+        let q = if even {
+            self.q
+        } else {
+            self.q.negate(SECP256K1)
+        };
+        let tacc = match self.tacc {
+            Some(tacc) if even => tacc.add_tweak(&to_scalar(&tweak))?,
+            Some(tacc) => tacc.negate().add_tweak(&to_scalar(&tweak))?,
+            None => tweak,
+        };
+        Ok(Self {
+            q: q.add_exp_tweak(SECP256K1, &to_scalar(&tweak))?,
+            // gacc = g * gacc
+            negated: self.negated == even,
+            tacc: Some(tacc),
+        })
+    }
+
+    /// `e * g * tacc`, to add to the sum of the signature shares
+    pub fn signature_tweak(&self, e: &SecretKey) -> Result<Option<SecretKey>, Error> {
+        match self.tacc {
+            Some(tacc) => {
+                let etacc = tacc.mul_tweak(&to_scalar(e))?;
+                if has_even_y(&self.q) {
+                    Ok(Some(etacc))
+                } else {
+                    Ok(Some(etacc.negate()))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Public nonce (`R1`, `R2`), or aggregate nonce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicNonce {
+    r1: PublicKey,
+    r2: PublicKey,
+}
+
+impl PublicNonce {
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut bytes = [0u8; 66];
+        bytes[..33].copy_from_slice(&self.r1.serialize());
+        bytes[33..].copy_from_slice(&self.r2.serialize());
+        bytes
+    }
+}
+
+impl fmt::Display for PublicNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.serialize().to_hex())
+    }
+}
+
+impl FromStr for PublicNonce {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes: Vec<u8> = Vec::from_hex(s)?;
+        if bytes.len() != 66 {
+            return Err(Error::InvalidNonce);
+        }
+        Ok(Self {
+            r1: PublicKey::from_slice(&bytes[..33])?,
+            r2: PublicKey::from_slice(&bytes[33..])?,
+        })
+    }
+}
+
+impl Serialize for PublicNonce {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicNonce {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Secret nonce, to use only once
+#[derive(Serialize, Deserialize)]
+pub struct SecretNonce {
+    k1: SecretKey,
+    k2: SecretKey,
+    public: PublicNonce,
+}
+
+impl fmt::Debug for SecretNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretNonce")
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+impl SecretNonce {
+    /// Generate a random secret nonce
+    pub fn generate() -> Self {
+        let k1 = SecretKey::new(&mut OsRng);
+        let k2 = SecretKey::new(&mut OsRng);
+        Self {
+            k1,
+            k2,
+            public: PublicNonce {
+                r1: PublicKey::from_secret_key(SECP256K1, &k1),
+                r2: PublicKey::from_secret_key(SECP256K1, &k2),
+            },
+        }
+    }
+
+    pub fn public_nonce(&self) -> PublicNonce {
+        self.public
+    }
+}
+
+/// Partial signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSignature(SecretKey);
+
+impl fmt::Display for PartialSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.secret_bytes().to_hex())
+    }
+}
+
+impl FromStr for PartialSignature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes: Vec<u8> = Vec::from_hex(s)?;
+        Ok(Self(SecretKey::from_slice(&bytes)?))
+    }
+}
+
+impl Serialize for PartialSignature {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PartialSignature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Partial signatures of a participant, for each input of a transaction,
+/// with the aggregate nonces they are made for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignatures {
+    pub aggregate_nonces: Vec<PublicNonce>,
+    pub signatures: Vec<PartialSignature>,
+}
+
+impl PartialSignatures {
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Sum the public nonces of the participants
+pub fn aggregate_nonces(nonces: &[PublicNonce]) -> Result<PublicNonce, Error> {
+    let r1: Vec<&PublicKey> = nonces.iter().map(|n| &n.r1).collect();
+    let r2: Vec<&PublicKey> = nonces.iter().map(|n| &n.r2).collect();
+    Ok(PublicNonce {
+        r1: PublicKey::combine_keys(&r1)?,
+        r2: PublicKey::combine_keys(&r2)?,
+    })
+}
+
+/// Values shared by the signers of a message
+struct Session {
+    b: SecretKey,
+    r: PublicKey,
+    e: SecretKey,
+}
+
+impl Session {
+    fn new(ctx: &KeyAggContext, aggnonce: &PublicNonce, msg: &[u8; 32]) -> Result<Self, Error> {
+        let q = ctx.aggregate_key().serialize();
+        let b = SecretKey::from_slice(&tagged_hash(
+            "MuSig/noncecoef",
+            &[&aggnonce.serialize(), &q, msg],
+        ))?;
+        let r = aggnonce
+            .r1
+            .combine(&aggnonce.r2.mul_tweak(SECP256K1, &to_scalar(&b))?)?;
+        let e = SecretKey::from_slice(&tagged_hash(
+            "BIP0340/challenge",
+            &[&r.x_only_public_key().0.serialize(), &q, msg],
+        ))?;
+        Ok(Self { b, r, e })
+    }
+}
+
+/// Sign `msg` with `secret_key`, consuming the secret nonce
+pub fn partial_sign(
+    ctx: &KeyAggContext,
+    secnonce: SecretNonce,
+    secret_key: &SecretKey,
+    aggnonce: &PublicNonce,
+    msg: &[u8; 32],
+) -> Result<PartialSignature, Error> {
+    let session = Session::new(ctx, aggnonce, msg)?;
+
+    let (mut k1, mut k2) = (secnonce.k1, secnonce.k2);
+    if !has_even_y(&session.r) {
+        k1 = k1.negate();
+        k2 = k2.negate();
+    }
+
+    // The participants keys are the even keys of the x-only keys
+    let mut d = *secret_key;
+    let p = PublicKey::from_secret_key(SECP256K1, &d);
+    if !has_even_y(&p) {
+        d = d.negate();
+    }
+    let p = p.x_only_public_key().0.public_key(Parity::Even);
+    if !ctx.pubkeys.contains(&p) {
+        return Err(Error::NotParticipant);
+    }
+
+    let a = ctx.coefficient(&p)?;
+    if ctx.key.negate_secret() {
+        d = d.negate();
+    }
+
+    // s = k1 + b * k2 + e * a * d
+    let bk2 = k2.mul_tweak(&to_scalar(&session.b))?;
+    let ead = d
+        .mul_tweak(&to_scalar(&a))?
+        .mul_tweak(&to_scalar(&session.e))?;
+    let s = k1
+        .add_tweak(&to_scalar(&bk2))?
+        .add_tweak(&to_scalar(&ead))?;
+
+    Ok(PartialSignature(s))
+}
+
+/// Verify the partial signature of `public_key`
+pub fn verify_partial_signature(
+    ctx: &KeyAggContext,
+    partial_signature: &PartialSignature,
+    pubnonce: &PublicNonce,
+    public_key: &XOnlyPublicKey,
+    aggnonce: &PublicNonce,
+    msg: &[u8; 32],
+) -> Result<(), Error> {
+    let session = Session::new(ctx, aggnonce, msg)?;
+
+    let p = public_key.public_key(Parity::Even);
+    if !ctx.pubkeys.contains(&p) {
+        return Err(Error::NotParticipant);
+    }
+
+    // R_i = R1_i + b * R2_i
+    let mut r = pubnonce
+        .r1
+        .combine(&pubnonce.r2.mul_tweak(SECP256K1, &to_scalar(&session.b))?)?;
+    if !has_even_y(&session.r) {
+        r = r.negate(SECP256K1);
+    }
+
+    // s * G = R_i + e * a * g * gacc * P
+    let a = ctx.coefficient(&p)?;
+    let mut p = p;
+    if ctx.key.negate_secret() {
+        p = p.negate(SECP256K1);
+    }
+    let ea = a.mul_tweak(&to_scalar(&session.e))?;
+    let expected = r.combine(&p.mul_tweak(SECP256K1, &to_scalar(&ea))?)?;
+
+    if PublicKey::from_secret_key(SECP256K1, &partial_signature.0) == expected {
+        Ok(())
+    } else {
+        Err(Error::InvalidPartialSignature)
+    }
+}
+
+/// Aggregate the partial signatures into a BIP-340 signature for the aggregate key
+pub fn aggregate_partial_signatures(
+    ctx: &KeyAggContext,
+    aggnonce: &PublicNonce,
+    partial_signatures: &[PartialSignature],
+    msg: &[u8; 32],
+) -> Result<schnorr::Signature, Error> {
+    let session = Session::new(ctx, aggnonce, msg)?;
+
+    let mut s: Option<SecretKey> = None;
+    for partial_signature in partial_signatures.iter() {
+        s = Some(match s {
+            Some(s) => s.add_tweak(&to_scalar(&partial_signature.0))?,
+            None => partial_signature.0,
+        });
+    }
+    let mut s: SecretKey = s.ok_or(Error::InvalidPartialSignature)?;
+
+    // s += e * g * tacc
+    if let Some(etacc) = ctx.key.signature_tweak(&session.e)? {
+        s = s.add_tweak(&to_scalar(&etacc))?;
+    }
+
+    let mut sig = [0u8; 64];
+    sig[..32].copy_from_slice(&session.r.x_only_public_key().0.serialize());
+    sig[32..].copy_from_slice(&s.secret_bytes());
+    Ok(schnorr::Signature::from_slice(&sig)?)
+}
+
+pub(crate) fn has_even_y(pk: &PublicKey) -> bool {
+    pk.serialize()[0] == 0x02
+}
+
+pub(crate) fn to_scalar(sk: &SecretKey) -> Scalar {
+    Scalar::from_be_bytes(sk.secret_bytes()).expect("secret key is a valid scalar")
+}
+
+pub(crate) fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag[..]);
+    engine.input(&tag[..]);
+    for chunk in data.iter() {
+        engine.input(chunk);
+    }
+    sha256::Hash::from_engine(engine).into_inner()
+}
+
+#[cfg(test)]
+mod test {
+    use keechain_core::bitcoin::secp256k1::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_key_agg() {
+        let x1 = PublicKey::from_str(
+            "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        )
+        .unwrap();
+        let x2 = PublicKey::from_str(
+            "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+        )
+        .unwrap();
+        let x3 = PublicKey::from_str(
+            "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66",
+        )
+        .unwrap();
+
+        let ctx = KeyAggContext::from_public_keys(vec![x1, x2, x3]).unwrap();
+        assert_eq!(
+            ctx.aggregate_key().to_string(),
+            "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c"
+        );
+    }
+
+    #[test]
+    fn test_sign_with_taproot_tweak() {
+        let secret_keys: Vec<SecretKey> = (0..3).map(|_| SecretKey::new(&mut OsRng)).collect();
+        let pubkeys: Vec<XOnlyPublicKey> = secret_keys
+            .iter()
+            .map(|sk| sk.x_only_public_key(SECP256K1).0)
+            .collect();
+
+        let merkle_root = TapBranchHash::from_inner([7u8; 32]);
+        let ctx = KeyAggContext::new(&pubkeys)
+            .unwrap()
+            .with_taproot_tweak(Some(merkle_root))
+            .unwrap();
+        let msg = [42u8; 32];
+
+        // Round 1
+        let secnonces: Vec<SecretNonce> = (0..3).map(|_| SecretNonce::generate()).collect();
+        let pubnonces: Vec<PublicNonce> = secnonces.iter().map(|n| n.public_nonce()).collect();
+        let aggnonce = aggregate_nonces(&pubnonces).unwrap();
+
+        // Round 2
+        let mut partial_signatures = Vec::new();
+        for (index, (secnonce, sk)) in secnonces.into_iter().zip(secret_keys.iter()).enumerate() {
+            let partial_signature = partial_sign(&ctx, secnonce, sk, &aggnonce, &msg).unwrap();
+            verify_partial_signature(
+                &ctx,
+                &partial_signature,
+                &pubnonces[index],
+                &pubkeys[index],
+                &aggnonce,
+                &msg,
+            )
+            .unwrap();
+            partial_signatures.push(partial_signature);
+        }
+
+        let sig = aggregate_partial_signatures(&ctx, &aggnonce, &partial_signatures, &msg).unwrap();
+        let msg = Message::from_slice(&msg).unwrap();
+        SECP256K1
+            .verify_schnorr(&sig, &msg, &ctx.aggregate_key())
+            .unwrap();
+    }
+}
@@ -0,0 +1,392 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+//! Human readable spending paths of a policy
+
+use std::collections::HashMap;
+use std::fmt;
+
+use bdk::bitcoin::util::bip32::Fingerprint;
+use bdk::bitcoin::{LockTime, Sequence, VarInt, XOnlyPublicKey};
+use bdk::descriptor::policy::PkOrF;
+use bdk::miniscript::descriptor::{DescriptorPublicKey, SinglePub, SinglePubKey};
+use bdk::miniscript::{Descriptor, Terminal};
+use bdk::FeeRate;
+use nostr_sdk::Metadata;
+
+use super::{Error, Policy, PolicyBranch};
+use crate::util::format;
+
+/// Weight of the key path satisfaction: scriptSig len, stack len, signature len and signature
+const KEY_PATH_SATISFACTION_WEIGHT: usize = 4 + 1 + 1 + 65;
+/// Weight of a typical spend without the satisfaction of its input: version, segwit marker and
+/// flag, input and output counts, locktime, 1 input (outpoint and sequence) and 2 outputs with
+/// 34 bytes scripts (recipient and change)
+const TYPICAL_SPEND_WEIGHT: usize = (4 + 1 + 1 + 4) * 4 + 2 + (36 + 4) * 4 + 2 * (8 + 1 + 34) * 4;
+
+/// Key needed to sign with a spending path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathSigner {
+    pub key: PkOrF,
+    /// Nostr public key of the member owning the key, if known
+    pub member: Option<XOnlyPublicKey>,
+    /// Nostr name of the member, if known
+    pub name: Option<String>,
+}
+
+impl PathSigner {
+    /// Resolve the member owning the key, from the fingerprints of the signer keys announced
+    /// by the members, and the name of the member, from the `contacts`
+    pub fn resolve(
+        &mut self,
+        owners: &HashMap<Fingerprint, XOnlyPublicKey>,
+        contacts: &HashMap<XOnlyPublicKey, Metadata>,
+    ) {
+        if let (None, PkOrF::Fingerprint(fingerprint)) = (&self.member, &self.key) {
+            self.member = owners.get(fingerprint).copied();
+        }
+        if let Some(member) = &self.member {
+            self.name = contacts
+                .get(member)
+                .and_then(|metadata| metadata.display_name.clone().or(metadata.name.clone()));
+        }
+    }
+}
+
+impl fmt::Display for PathSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, &self.member, &self.key) {
+            (Some(name), ..) => write!(f, "{name}"),
+            (None, Some(member), _) => write!(f, "{member}"),
+            (None, None, PkOrF::XOnlyPubkey(key)) => write!(f, "{key}"),
+            (None, None, PkOrF::Pubkey(key)) => write!(f, "{key}"),
+            (None, None, PkOrF::Fingerprint(fingerprint)) => write!(f, "key {fingerprint}"),
+        }
+    }
+}
+
+/// Timelock of a spending path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timelock {
+    /// Spendable after the block height
+    Height(u32),
+    /// Spendable after the unix timestamp
+    Time(u64),
+    /// Spendable this number of blocks after the confirmation of the coins
+    Blocks(u32),
+    /// Spendable this number of seconds after the confirmation of the coins
+    Seconds(u64),
+}
+
+impl fmt::Display for Timelock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Height(height) => write!(f, "after block {}", format::number(*height as u64)),
+            Self::Time(timestamp) => write!(f, "after {}", format::date(*timestamp)),
+            Self::Blocks(blocks) => write!(
+                f,
+                "{} blocks ({}) after confirmation",
+                format::number(*blocks as u64),
+                format::duration(*blocks as u64 * 600)
+            ),
+            Self::Seconds(seconds) => {
+                write!(f, "{} after confirmation", format::duration(*seconds))
+            }
+        }
+    }
+}
+
+/// Spending path of a policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendingPath {
+    /// Branch of the spending policy, to select the path when spending
+    pub branch: PolicyBranch,
+    /// Spend with the key path
+    pub key_path: bool,
+    /// Number of signatures needed
+    pub signatures: usize,
+    pub signers: Vec<PathSigner>,
+    pub timelocks: Vec<Timelock>,
+    /// Estimated max weight of the satisfaction of an input, in weight units
+    pub satisfaction_weight: Option<usize>,
+}
+
+impl SpendingPath {
+    /// Resolve the members owning the keys and their names (check [`PathSigner::resolve`])
+    pub fn resolve(
+        &mut self,
+        owners: &HashMap<Fingerprint, XOnlyPublicKey>,
+        contacts: &HashMap<XOnlyPublicKey, Metadata>,
+    ) {
+        for signer in self.signers.iter_mut() {
+            signer.resolve(owners, contacts);
+        }
+    }
+
+    /// Estimated vsize of a typical spend with this path (1 input and 2 outputs)
+    pub fn estimated_vsize(&self) -> Option<usize> {
+        self.satisfaction_weight
+            .map(|weight| (TYPICAL_SPEND_WEIGHT + weight + 3) / 4)
+    }
+
+    /// Estimated fee of a typical spend with this path at `fee_rate`
+    pub fn estimated_fee(&self, fee_rate: &FeeRate) -> Option<u64> {
+        self.estimated_vsize().map(|vsize| fee_rate.fee_vb(vsize))
+    }
+}
+
+impl fmt::Display for SpendingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key_path {
+            write!(f, "Key path, ")?;
+        }
+        let signers: Vec<String> = self.signers.iter().map(|s| s.to_string()).collect();
+        write!(
+            f,
+            "{} of {}: {}",
+            self.signatures,
+            self.signers.len(),
+            signers.join(", ")
+        )?;
+        for timelock in self.timelocks.iter() {
+            write!(f, ", {timelock}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Tap leaf of a descriptor, with its keys and timelocks
+struct Leaf {
+    keys: Vec<PkOrF>,
+    after: Vec<LockTime>,
+    older: Vec<Sequence>,
+    satisfaction_weight: Option<usize>,
+}
+
+/// Describe the spending `branches` of a policy, with the `descriptor` of its wallet
+///
+/// The signers are matched with the nostr public keys of the members
+/// (check [`Policy::member_pubkeys`]).
+pub fn spending_paths(
+    policy: &Policy,
+    descriptor: &Descriptor<DescriptorPublicKey>,
+    branches: Vec<PolicyBranch>,
+) -> Result<Vec<SpendingPath>, Error> {
+    let members: Vec<XOnlyPublicKey> = policy.member_pubkeys()?;
+
+    let (internal_key, leaves) = match descriptor {
+        Descriptor::Tr(tr) => (Some(pk_or_f(tr.internal_key())), tap_leaves(descriptor)),
+        _ => (None, Vec::new()),
+    };
+
+    Ok(branches
+        .into_iter()
+        .map(|branch| {
+            let key_path: bool = internal_key
+                .as_ref()
+                .map_or(false, |key| branch.keys == [key.clone()]);
+
+            // The key path of the MuSig2 and FROST policies is signed by the members
+            let (signatures, keys): (usize, Vec<PkOrF>) = match (&policy.frost, key_path) {
+                (Some(group), true) => (
+                    group.threshold,
+                    policy
+                        .members
+                        .iter()
+                        .map(|m| PkOrF::XOnlyPubkey(*m))
+                        .collect(),
+                ),
+                (None, true) if !policy.musig2_keys.is_empty() => (
+                    policy.musig2_keys.len(),
+                    policy
+                        .musig2_keys
+                        .iter()
+                        .map(|k| PkOrF::XOnlyPubkey(*k))
+                        .collect(),
+                ),
+                _ => (branch.signatures, branch.keys.clone()),
+            };
+
+            let signers: Vec<PathSigner> = keys
+                .into_iter()
+                .map(|key| PathSigner {
+                    member: match &key {
+                        PkOrF::XOnlyPubkey(key) if members.contains(key) => Some(*key),
+                        _ => None,
+                    },
+                    key,
+                    name: None,
+                })
+                .collect();
+
+            let satisfaction_weight: Option<usize> = if key_path {
+                Some(KEY_PATH_SATISFACTION_WEIGHT)
+            } else if internal_key.is_some() {
+                branch_satisfaction_weight(&branch, &leaves)
+            } else {
+                descriptor.max_satisfaction_weight().ok()
+            };
+
+            SpendingPath {
+                timelocks: timelocks(&branch),
+                key_path,
+                signatures,
+                signers,
+                satisfaction_weight,
+                branch,
+            }
+        })
+        .collect())
+}
+
+fn pk_or_f(key: &DescriptorPublicKey) -> PkOrF {
+    match key {
+        DescriptorPublicKey::Single(SinglePub {
+            key: SinglePubKey::XOnly(pk),
+            ..
+        }) => PkOrF::XOnlyPubkey(*pk),
+        DescriptorPublicKey::Single(SinglePub {
+            key: SinglePubKey::FullKey(pk),
+            ..
+        }) => PkOrF::Pubkey(*pk),
+        _ => PkOrF::Fingerprint(key.master_fingerprint()),
+    }
+}
+
+fn timelocks(branch: &PolicyBranch) -> Vec<Timelock> {
+    let mut timelocks: Vec<Timelock> = Vec::new();
+    if let Some(timelock) = branch.condition.timelock {
+        timelocks.push(match timelock {
+            LockTime::Blocks(value) => Timelock::Height(value.to_consensus_u32()),
+            LockTime::Seconds(value) => Timelock::Time(value.to_consensus_u32() as u64),
+        });
+    }
+    if let Some(csv) = branch.condition.csv {
+        let value: u32 = csv.to_consensus_u32() & 0xFFFF;
+        timelocks.push(if csv.is_time_locked() {
+            Timelock::Seconds(value as u64 * 512)
+        } else {
+            Timelock::Blocks(value)
+        });
+    }
+    timelocks
+}
+
+/// Tap leaves of a taproot descriptor, with the weight of their satisfaction
+/// (computed like [`Descriptor::max_satisfaction_weight`])
+fn tap_leaves(descriptor: &Descriptor<DescriptorPublicKey>) -> Vec<Leaf> {
+    let tr = match descriptor {
+        Descriptor::Tr(tr) => tr,
+        _ => return Vec::new(),
+    };
+
+    tr.iter_scripts()
+        .map(|(depth, ms)| {
+            let mut keys: Vec<PkOrF> = Vec::new();
+            for key in ms.iter_pk() {
+                let key = pk_or_f(&key);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+
+            let mut after: Vec<LockTime> = Vec::new();
+            let mut older: Vec<Sequence> = Vec::new();
+            for node in ms.iter() {
+                match &node.node {
+                    Terminal::After(value) => after.push(LockTime::from(*value)),
+                    Terminal::Older(value) => older.push(*value),
+                    _ => (),
+                }
+            }
+
+            let satisfaction_weight = match (
+                ms.max_satisfaction_witness_elements(),
+                ms.max_satisfaction_size(),
+            ) {
+                (Ok(elements), Ok(size)) => {
+                    let script_size = ms.script_size();
+                    let control_block_size = 33 + 32 * depth as usize;
+                    Some(
+                        4 + VarInt(elements as u64 + 2).len()
+                            + size
+                            + VarInt(script_size as u64).len()
+                            + script_size
+                            + VarInt(control_block_size as u64).len()
+                            + control_block_size,
+                    )
+                }
+                _ => None,
+            };
+
+            Leaf {
+                keys,
+                after,
+                older,
+                satisfaction_weight,
+            }
+        })
+        .collect()
+}
+
+/// Weight of the satisfaction of the smallest leaf with the keys and the timelocks of the branch
+fn branch_satisfaction_weight(branch: &PolicyBranch, leaves: &[Leaf]) -> Option<usize> {
+    leaves
+        .iter()
+        .filter(|leaf| {
+            branch.keys.iter().all(|key| leaf.keys.contains(key))
+                && branch
+                    .condition
+                    .timelock
+                    .map_or(true, |timelock| leaf.after.contains(&timelock))
+                && branch
+                    .condition
+                    .csv
+                    .map_or(true, |csv| leaf.older.contains(&csv))
+        })
+        .min_by_key(|leaf| (leaf.keys.len(), leaf.satisfaction_weight))
+        .and_then(|leaf| leaf.satisfaction_weight)
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::KeychainKind;
+
+    use super::*;
+    use crate::policy::path;
+
+    #[test]
+    fn test_spending_paths() {
+        let (policy, wallet) = path::test::wallet("or(pk(e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc),and(thresh(2,pk(101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df),pk(7b9eda7669b1075c0eb4b117a34de19be4b3c8b0d5537b5de7fa9793b0a8e9ff)),older(52560)))");
+        let spending_policy = wallet.policies(KeychainKind::External).unwrap().unwrap();
+        let branches: Vec<PolicyBranch> = path::branches(&spending_policy)
+            .into_iter()
+            .filter(|branch| !policy.is_unspendable_key_path(branch))
+            .collect();
+        let descriptor = wallet.get_descriptor_for_keychain(KeychainKind::External);
+        let paths = spending_paths(&policy, descriptor, branches).unwrap();
+
+        // 2 script paths: the key path is unspendable
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|p| !p.key_path));
+
+        let timelocked = paths.iter().find(|p| !p.timelocks.is_empty()).unwrap();
+        assert_eq!(timelocked.signatures, 2);
+        assert_eq!(timelocked.signers.len(), 2);
+        assert_eq!(timelocked.timelocks, vec![Timelock::Blocks(52560)]);
+        assert_eq!(
+            timelocked.timelocks[0].to_string(),
+            "52 560 blocks (~1 year) after confirmation"
+        );
+
+        // Two signatures weight more than one
+        let single = paths.iter().find(|p| p.timelocks.is_empty()).unwrap();
+        assert!(single.satisfaction_weight.unwrap() < timelocked.satisfaction_weight.unwrap());
+        assert!(single.estimated_vsize().unwrap() < timelocked.estimated_vsize().unwrap());
+        let fee_rate = FeeRate::from_sat_per_vb(2.0);
+        assert_eq!(
+            single.estimated_fee(&fee_rate),
+            single.estimated_vsize().map(|vsize| 2 * vsize as u64)
+        );
+    }
+}
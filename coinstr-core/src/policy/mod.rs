@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use bdk::bitcoin::hashes::{hash160, ripemd160, sha256};
+use bdk::bitcoin::util::taproot::TapBranchHash;
+use bdk::bitcoin::XOnlyPublicKey;
+use bdk::descriptor::policy::PkOrF;
+use bdk::miniscript::descriptor::{DescriptorPublicKey, DescriptorType};
+use bdk::miniscript::policy::Concrete;
+use bdk::miniscript::{hash256, Descriptor, ForEachKey, Translator};
+use keechain_core::bitcoin::secp256k1::SecretKey;
+
+pub mod analysis;
+pub mod path;
+pub mod template;
+
+pub use self::analysis::{PathSigner, SpendingPath, Timelock};
+pub use self::path::{Maturity, PolicyBranch, PolicyPath, UtxoMaturity};
+pub use self::template::{Locktime, PolicyTemplate};
+use crate::frost::FrostGroup;
+use crate::musig2::{self, KeyAggContext};
+use crate::signer::{self, SignerKey};
+use crate::util::{self, DescriptorKeyInfo, Unspendable};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Miniscript(#[from] bdk::miniscript::Error),
+    #[error(transparent)]
+    Policy(#[from] bdk::miniscript::policy::compiler::CompilerError),
+    #[error("{0}, {1}")]
+    DescOrPolicy(Box<Self>, Box<Self>),
+    #[error(transparent)]
+    Util(#[from] util::Error),
+    #[error(transparent)]
+    Template(#[from] template::Error),
+    #[error(transparent)]
+    MuSig2(#[from] musig2::Error),
+    #[error(transparent)]
+    Signer(#[from] signer::Error),
+    #[error("must be a taproot descriptor")]
+    NotTaprootDescriptor,
+    #[error("the internal key isn't the tweaked NUMS point")]
+    InvalidNumsTweak,
+    #[error("MuSig2 policies must use the nostr public keys of the members, not extended keys")]
+    MuSig2ExtendedKeys,
+    #[error("{0} has no signer key")]
+    SignerKeyNotFound(XOnlyPublicKey),
+    #[error("a nostr public key is required for each MuSig2 key, in the same order")]
+    MuSig2MembersMismatch,
+    #[error("the internal key isn't the FROST group key of the members")]
+    InvalidFrostGroup,
+    #[error("FROST policies need a script path, to spend without the group")]
+    FrostScriptPathRequired,
+    #[error("the internal key isn't the MuSig2 aggregate key of the members")]
+    InvalidMuSig2Keys,
+    #[error("the nostr public keys of the members are required for policies with extended keys")]
+    MembersRequired,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    pub name: String,
+    pub description: String,
+    pub descriptor: Descriptor<String>,
+    /// Nostr public keys of the members, required when their signing keys aren't their nostr keys
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<XOnlyPublicKey>,
+    /// Tweak of the NUMS internal key, to verify it's unspendable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nums_tweak: Option<SecretKey>,
+    /// Keys aggregated with MuSig2 in the internal key, to spend with the key path,
+    /// in the order of the [`Policy::members`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub musig2_keys: Vec<XOnlyPublicKey>,
+    /// FROST group of the [`Policy::members`], with the group key as internal key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frost: Option<FrostGroup>,
+}
+
+impl Policy {
+    pub fn new<S>(name: S, description: S, descriptor: Descriptor<String>) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        if let DescriptorType::Tr = descriptor.desc_type() {
+            // Check the multipath keys
+            util::split_multipath_descriptor(descriptor.to_string())?;
+            Ok(Self {
+                name: name.into(),
+                description: description.into(),
+                descriptor,
+                members: Vec::new(),
+                nums_tweak: None,
+                musig2_keys: Vec::new(),
+                frost: None,
+            })
+        } else {
+            Err(Error::NotTaprootDescriptor)
+        }
+    }
+
+    pub fn from_descriptor<S>(name: S, description: S, descriptor: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let descriptor = Descriptor::from_str(&descriptor.into())?;
+        Self::new(name, description, descriptor)
+    }
+
+    /// Compile a miniscript policy, with the NUMS point `H` as internal key
+    pub fn from_miniscript_policy<S>(name: S, description: S, policy: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let policy = Concrete::<String>::from_str(&policy.into())?;
+        let unspendable_pk = XOnlyPublicKey::unspendable();
+        let descriptor = policy.compile_tr(Some(unspendable_pk.to_string()))?;
+        Self::new(name, description, descriptor)
+    }
+
+    /// Compile a miniscript policy, with the NUMS point `H` tweaked with `tweak` as internal key
+    ///
+    /// The tweak is kept in the policy, so the members can verify the internal key.
+    pub fn from_miniscript_policy_with_tweak<S>(
+        name: S,
+        description: S,
+        policy: S,
+        tweak: SecretKey,
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let policy = Concrete::<String>::from_str(&policy.into())?;
+        let unspendable_pk = XOnlyPublicKey::unspendable_with_tweak(&tweak)?;
+        let descriptor = policy.compile_tr(Some(unspendable_pk.to_string()))?;
+        let mut policy = Self::new(name, description, descriptor)?;
+        policy.nums_tweak = Some(tweak);
+        Ok(policy)
+    }
+
+    /// Compile a miniscript policy of the nostr public keys of the members, with the MuSig2
+    /// aggregate of their keys as internal key
+    ///
+    /// Each nostr public key is replaced with the fixed key of the member signer key
+    /// (check [`SignerKey::fixed_descriptor_key`]), so the members sign with their seed.
+    /// All the members can spend together with the key path, with a single signature,
+    /// while the script paths are still available when some of them aren't online.
+    pub fn from_miniscript_policy_with_musig2<S>(
+        name: S,
+        description: S,
+        policy: S,
+        signers: &HashMap<XOnlyPublicKey, SignerKey>,
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let policy_str: String = policy.into();
+        if !Concrete::<String>::from_str(&policy_str)?
+            .for_each_key(|key| XOnlyPublicKey::from_str(key).is_ok())
+        {
+            return Err(Error::MuSig2ExtendedKeys);
+        }
+        let policy = Concrete::<XOnlyPublicKey>::from_str(&policy_str)?;
+        let mut members: Vec<XOnlyPublicKey> = Vec::new();
+        policy.for_each_key(|key| {
+            if !members.contains(key) {
+                members.push(*key);
+            }
+            true
+        });
+
+        let mut keys: Vec<XOnlyPublicKey> = Vec::with_capacity(members.len());
+        for member in members.iter() {
+            let signer = signers
+                .get(member)
+                .ok_or(Error::SignerKeyNotFound(*member))?;
+            keys.push(signer.fixed_public_key()?);
+        }
+
+        let policy = policy.translate_pk(&mut FixedKeyTranslator { signers })?;
+        let aggregate_key = KeyAggContext::new(&keys)?.aggregate_key();
+        let descriptor = policy.compile_tr(Some(aggregate_key.to_string()))?;
+        let mut policy = Self::new(name, description, descriptor)?.with_members(members);
+        policy.musig2_keys = keys;
+        if !policy.has_musig2_internal_key() {
+            return Err(Error::InvalidMuSig2Keys);
+        }
+        Ok(policy)
+    }
+
+    /// Policy with the group key of a FROST group as key path, and a `threshold`-of-n multisig
+    /// of the fixed signer keys of the `members` as script path, to spend if the group can't
+    /// sign (like when a key package is lost)
+    ///
+    /// The `members` are in the order of the indexes of the group.
+    pub fn from_frost_group<S>(
+        name: S,
+        description: S,
+        group: FrostGroup,
+        members: Vec<XOnlyPublicKey>,
+        signers: &HashMap<XOnlyPublicKey, SignerKey>,
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let mut translator = FixedKeyTranslator { signers };
+        let keys: Vec<String> = members
+            .iter()
+            .map(|m| translator.pk(m))
+            .collect::<Result<_, _>>()?;
+        let descriptor = Descriptor::from_str(&format!(
+            "tr({},multi_a({},{}))",
+            group.x_only_public_key(),
+            group.threshold,
+            keys.join(",")
+        ))?;
+        let mut policy = Self::new(name, description, descriptor)?.with_members(members);
+        policy.frost = Some(group);
+        if !policy.has_frost_internal_key() {
+            return Err(Error::InvalidFrostGroup);
+        }
+        Ok(policy)
+    }
+
+    /// Build the policy from a template, with the `keys` in the order of the template roles
+    pub fn from_template<S, K>(
+        name: S,
+        description: S,
+        template: &PolicyTemplate,
+        keys: &[K],
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+        K: AsRef<str>,
+    {
+        let policy = template.build(keys)?;
+        let unspendable_pk = XOnlyPublicKey::unspendable();
+        let descriptor = policy.compile_tr(Some(unspendable_pk.to_string()))?;
+        Self::new(name, description, descriptor)
+    }
+
+    pub fn from_desc_or_policy<N, D, P>(
+        name: N,
+        description: D,
+        desc_or_policy: P,
+    ) -> Result<Self, Error>
+    where
+        N: Into<String>,
+        D: Into<String>,
+        P: Into<String>,
+    {
+        let name = &name.into();
+        let description = &description.into();
+        let desc_or_policy = &desc_or_policy.into();
+        match Self::from_descriptor(name, description, desc_or_policy) {
+            Ok(policy) => Ok(policy),
+            Err(desc_e) => match Self::from_miniscript_policy(name, description, desc_or_policy) {
+                Ok(policy) => Ok(policy),
+                Err(policy_e) => Err(Error::DescOrPolicy(Box::new(desc_e), Box::new(policy_e))),
+            },
+        }
+    }
+
+    /// Set the nostr public keys of the members
+    pub fn with_members(mut self, members: Vec<XOnlyPublicKey>) -> Self {
+        self.members = members;
+        self
+    }
+
+    /// Get the keys of the descriptor owned by the members: the unspendable internal key,
+    /// the MuSig2 aggregate key and the FROST group key are excluded
+    pub fn member_keys(&self) -> Result<Vec<DescriptorKeyInfo>, Error> {
+        let mut keys = util::extract_descriptor_keys(self.descriptor.to_string())?;
+        keys.retain(|key| !key.public_key.is_unspendable(self.nums_tweak.as_ref()));
+        if let Some(aggregate_key) = self.musig2_aggregate_key() {
+            keys.retain(|key| key.public_key != aggregate_key);
+        }
+        if let Some(group) = &self.frost {
+            keys.retain(|key| key.public_key != group.x_only_public_key());
+        }
+        Ok(keys)
+    }
+
+    /// Get the nostr public keys of the members: the [`Policy::members`], if set, else the
+    /// x-only keys of the descriptor (check [`Policy::member_keys`]), used as nostr keys
+    ///
+    /// The members of policies with extended keys must be set.
+    pub fn member_pubkeys(&self) -> Result<Vec<XOnlyPublicKey>, Error> {
+        if !self.members.is_empty() {
+            return Ok(self.members.clone());
+        }
+        let keys = self.member_keys()?;
+        if keys.iter().any(|key| key.xpub.is_some()) {
+            return Err(Error::MembersRequired);
+        }
+        Ok(keys.into_iter().map(|key| key.public_key).collect())
+    }
+
+    /// Check if `public_key` is the nostr public key of a member (check [`Policy::member_pubkeys`])
+    pub fn is_member(&self, public_key: &XOnlyPublicKey) -> Result<bool, Error> {
+        Ok(self.member_pubkeys()?.contains(public_key))
+    }
+
+    /// Check if the internal key is provably unspendable: the NUMS point `H`,
+    /// or `H` tweaked with the tweak of the policy
+    pub fn has_unspendable_internal_key(&self) -> bool {
+        if let Descriptor::Tr(tr) = &self.descriptor {
+            if let Ok(internal_key) = XOnlyPublicKey::from_str(tr.internal_key()) {
+                return internal_key.is_unspendable(self.nums_tweak.as_ref());
+            }
+        }
+        false
+    }
+
+    /// Get the MuSig2 key of a member, if any
+    pub fn musig2_key_of(&self, member: &XOnlyPublicKey) -> Option<XOnlyPublicKey> {
+        self.members
+            .iter()
+            .position(|m| m == member)
+            .and_then(|pos| self.musig2_keys.get(pos))
+            .copied()
+    }
+
+    /// Get the MuSig2 aggregate key of the [`Policy::musig2_keys`], if any
+    pub fn musig2_aggregate_key(&self) -> Option<XOnlyPublicKey> {
+        if self.musig2_keys.is_empty() {
+            return None;
+        }
+        KeyAggContext::new(&self.musig2_keys)
+            .ok()
+            .map(|ctx| ctx.aggregate_key())
+    }
+
+    /// Merkle root of the script paths, that tweaks the internal key of the key path
+    ///
+    /// The policies with a MuSig2 or FROST key path use fixed keys only,
+    /// so the merkle root is the same for all the addresses.
+    pub fn tap_merkle_root(&self) -> Result<Option<TapBranchHash>, Error> {
+        let (descriptor, _internal) =
+            util::split_multipath_descriptor(self.descriptor.to_string())?;
+        match Descriptor::<DescriptorPublicKey>::from_str(&descriptor)?.at_derivation_index(0) {
+            Descriptor::Tr(tr) => Ok(tr.spend_info().merkle_root()),
+            _ => Err(Error::NotTaprootDescriptor),
+        }
+    }
+
+    /// Check if the internal key is the MuSig2 aggregate key of the [`Policy::musig2_keys`]
+    pub fn has_musig2_internal_key(&self) -> bool {
+        if let (Descriptor::Tr(tr), Some(aggregate_key)) =
+            (&self.descriptor, self.musig2_aggregate_key())
+        {
+            return tr.internal_key() == &aggregate_key.to_string();
+        }
+        false
+    }
+
+    /// Check if the descriptor has script paths, to spend without the key path
+    pub fn has_script_paths(&self) -> bool {
+        matches!(&self.descriptor, Descriptor::Tr(tr) if tr.taptree().is_some())
+    }
+
+    /// Check if the internal key is the group key of the [`Policy::frost`] group,
+    /// with a verification share for each member
+    pub fn has_frost_internal_key(&self) -> bool {
+        if let (Descriptor::Tr(tr), Some(group)) = (&self.descriptor, &self.frost) {
+            return tr.internal_key() == &group.x_only_public_key().to_string()
+                && group.verification_shares.len() == self.members.len();
+        }
+        false
+    }
+
+    /// Index of a member in the [`Policy::frost`] group, starting from `1`
+    pub fn frost_index_of(&self, member: &XOnlyPublicKey) -> Option<u32> {
+        self.frost.as_ref()?;
+        self.members
+            .iter()
+            .position(|m| m == member)
+            .map(|pos| pos as u32 + 1)
+    }
+
+    /// Check if the branch is the key path of a descriptor with script paths
+    ///
+    /// Policies compiled by Coinstr use an unspendable internal key,
+    /// so their key path can't be used to spend.
+    /// Older policies used a random key instead of the NUMS point: check
+    /// [`Policy::has_unspendable_internal_key`] to know if the key path is provably unspendable.
+    /// The key path of the MuSig2 and FROST policies is spendable by the members together.
+    pub fn is_unspendable_key_path(&self, branch: &PolicyBranch) -> bool {
+        if !self.musig2_keys.is_empty() || self.frost.is_some() {
+            return false;
+        }
+        if let Descriptor::Tr(tr) = &self.descriptor {
+            if tr.taptree().is_some() && branch.keys.len() == 1 {
+                if let PkOrF::XOnlyPubkey(key) = &branch.keys[0] {
+                    return &key.to_string() == tr.internal_key();
+                }
+            }
+        }
+        false
+    }
+
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let policy: Self = serde_json::from_str(&json.into())?;
+        if policy.nums_tweak.is_some() && !policy.has_unspendable_internal_key() {
+            return Err(Error::InvalidNumsTweak);
+        }
+        if !policy.musig2_keys.is_empty() {
+            if !policy.has_musig2_internal_key() {
+                return Err(Error::InvalidMuSig2Keys);
+            }
+            if policy.members.len() != policy.musig2_keys.len() {
+                return Err(Error::MuSig2MembersMismatch);
+            }
+        }
+        if policy.frost.is_some() {
+            if !policy.has_frost_internal_key() {
+                return Err(Error::InvalidFrostGroup);
+            }
+            if !policy.has_script_paths() {
+                return Err(Error::FrostScriptPathRequired);
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Replace the nostr public keys of the members with the fixed keys of their signer keys
+struct FixedKeyTranslator<'a> {
+    signers: &'a HashMap<XOnlyPublicKey, SignerKey>,
+}
+
+impl<'a> Translator<XOnlyPublicKey, String, Error> for FixedKeyTranslator<'a> {
+    fn pk(&mut self, pk: &XOnlyPublicKey) -> Result<String, Error> {
+        let signer = self.signers.get(pk).ok_or(Error::SignerKeyNotFound(*pk))?;
+        Ok(signer.fixed_descriptor_key()?.to_string())
+    }
+
+    fn sha256(&mut self, sha256: &sha256::Hash) -> Result<String, Error> {
+        Ok(sha256.to_string())
+    }
+
+    fn hash256(&mut self, hash256: &hash256::Hash) -> Result<String, Error> {
+        Ok(hash256.to_string())
+    }
+
+    fn ripemd160(&mut self, ripemd160: &ripemd160::Hash) -> Result<String, Error> {
+        Ok(ripemd160.to_string())
+    }
+
+    fn hash160(&mut self, hash160: &hash160::Hash) -> Result<String, Error> {
+        Ok(hash160.to_string())
+    }
+}
@@ -0,0 +1,582 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bdk::bitcoin::{LockTime, OutPoint, Transaction, XOnlyPublicKey};
+use bdk::descriptor::policy::{Condition, PkOrF, Policy as SpendingPolicy, SatisfiableItem};
+use bdk::BlockTime;
+
+/// Selected items for each threshold node of a spending policy, indexed by node id
+///
+/// Check `bdk::wallet::tx_builder::TxBuilder::policy_path` for more details.
+pub type PolicyPath = BTreeMap<String, Vec<usize>>;
+
+/// Spending branch of a policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyBranch {
+    /// Path to select this branch
+    pub path: PolicyPath,
+    /// Keys that can provide a signature
+    pub keys: Vec<PkOrF>,
+    /// Number of signatures needed
+    pub signatures: usize,
+    /// Timelocks required by the branch
+    pub condition: Condition,
+}
+
+impl PolicyBranch {
+    /// Check if the timelocks of the branch are satisfied at `height` and `timestamp`
+    ///
+    /// `utxos` are the confirmation times of the coins that will be spent.
+    pub fn is_satisfiable(&self, height: u32, timestamp: u64, utxos: &[Option<BlockTime>]) -> bool {
+        if let Some(timelock) = self.condition.timelock {
+            let satisfied = match timelock {
+                LockTime::Blocks(value) => value.to_consensus_u32() <= height,
+                LockTime::Seconds(value) => value.to_consensus_u32() as u64 <= timestamp,
+            };
+            if !satisfied {
+                return false;
+            }
+        }
+
+        if let Some(csv) = self.condition.csv {
+            let value: u32 = csv.to_consensus_u32() & 0xFFFF;
+            return utxos.iter().all(|confirmation| match confirmation {
+                Some(confirmation) => {
+                    if csv.is_time_locked() {
+                        timestamp.saturating_sub(confirmation.timestamp) >= value as u64 * 512
+                    } else {
+                        height.saturating_sub(confirmation.height) + 1 >= value
+                    }
+                }
+                None => false,
+            });
+        }
+
+        true
+    }
+
+    /// Check if the branch requires a timelock
+    pub fn has_timelock(&self) -> bool {
+        self.condition.timelock.is_some() || self.condition.csv.is_some()
+    }
+
+    /// Check if the branch has a time based relative timelock
+    pub fn has_time_based_csv(&self) -> bool {
+        self.condition.csv.map_or(false, |csv| csv.is_time_locked())
+    }
+
+    /// When the timelocks of the branch are satisfied for a coin confirmed at `confirmation`
+    ///
+    /// `None` if the branch has a relative timelock and the coin is unconfirmed.
+    /// Time based relative timelocks are measured from the timestamp of the confirmation block,
+    /// not from the median time past of the previous block like the consensus rules: their
+    /// maturity is only an estimate.
+    pub fn maturity(&self, confirmation: Option<&BlockTime>) -> Option<Maturity> {
+        let mut maturity = Maturity::default();
+
+        if let Some(timelock) = self.condition.timelock {
+            match timelock {
+                LockTime::Blocks(value) => maturity.height = Some(value.to_consensus_u32()),
+                LockTime::Seconds(value) => {
+                    maturity.timestamp = Some(value.to_consensus_u32() as u64)
+                }
+            }
+        }
+
+        if let Some(csv) = self.condition.csv {
+            let confirmation = confirmation?;
+            let value: u32 = csv.to_consensus_u32() & 0xFFFF;
+            if csv.is_time_locked() {
+                let timestamp = confirmation.timestamp + value as u64 * 512;
+                maturity.timestamp = maturity.timestamp.max(Some(timestamp));
+            } else {
+                let height = (confirmation.height + value).saturating_sub(1);
+                maturity.height = maturity.height.max(Some(height));
+            }
+        }
+
+        Some(maturity)
+    }
+}
+
+/// Block height and timestamp from which the timelocks of a branch are satisfied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Maturity {
+    pub height: Option<u32>,
+    pub timestamp: Option<u64>,
+}
+
+impl Maturity {
+    /// Check if the timelocks are satisfied at `height` and `timestamp`
+    pub fn is_mature(&self, height: u32, timestamp: u64) -> bool {
+        self.height.map_or(true, |h| h <= height) && self.timestamp.map_or(true, |t| t <= timestamp)
+    }
+}
+
+impl fmt::Display for Maturity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.height, self.timestamp) {
+            (Some(height), Some(timestamp)) => {
+                write!(f, "block {height} and timestamp {timestamp}")
+            }
+            (Some(height), None) => write!(f, "block {height}"),
+            (None, Some(timestamp)) => write!(f, "timestamp {timestamp}"),
+            (None, None) => write!(f, "now"),
+        }
+    }
+}
+
+/// Maturity of the timelocked branches of a policy for a coin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UtxoMaturity {
+    pub outpoint: OutPoint,
+    pub value: u64,
+    pub confirmation: Option<BlockTime>,
+    /// Timelocked branches, with their maturity (`None` until the coin is confirmed)
+    pub branches: Vec<(PolicyBranch, Option<Maturity>)>,
+}
+
+impl UtxoMaturity {
+    /// Blocks left at `height` before the first path with a block based relative timelock activates
+    ///
+    /// `None` if there are no such paths or the coin is unconfirmed. The time based relative
+    /// timelocks aren't tracked (see [`PolicyBranch::has_time_based_csv`]).
+    pub fn blocks_left(&self, height: u32) -> Option<u32> {
+        self.branches
+            .iter()
+            .filter(|(branch, _)| {
+                branch
+                    .condition
+                    .csv
+                    .map_or(false, |csv| csv.is_height_locked())
+            })
+            .filter_map(|(_, maturity)| maturity.and_then(|m| m.height))
+            .min()
+            .map(|h| h.saturating_sub(height))
+    }
+}
+
+/// nLockTime required by `condition` for a transaction built at `height`
+///
+/// Block based timelocks already satisfied are raised to `height`, to discourage fee sniping.
+pub fn lock_time(condition: &Condition, height: u32) -> Option<LockTime> {
+    match condition.timelock? {
+        LockTime::Blocks(value) if value.to_consensus_u32() < height => {
+            LockTime::from_height(height).ok()
+        }
+        timelock => Some(timelock),
+    }
+}
+
+/// Check if the nLockTime and the nSequence of the inputs of `tx` satisfy the timelocks of `condition`
+pub fn has_timelocks(condition: &Condition, tx: &Transaction) -> bool {
+    if let Some(timelock) = condition.timelock {
+        let lock_time = LockTime::from(tx.lock_time);
+        if !timelock.is_same_unit(lock_time)
+            || lock_time.to_consensus_u32() < timelock.to_consensus_u32()
+            || !tx
+                .input
+                .iter()
+                .all(|txin| txin.sequence.enables_absolute_lock_time())
+        {
+            return false;
+        }
+    }
+
+    if let Some(csv) = condition.csv {
+        let value: u32 = csv.to_consensus_u32() & 0xFFFF;
+        return tx.version >= 2
+            && tx.input.iter().all(|txin| {
+                txin.sequence.is_relative_lock_time()
+                    && txin.sequence.is_time_locked() == csv.is_time_locked()
+                    && txin.sequence.to_consensus_u32() & 0xFFFF >= value
+            });
+    }
+
+    true
+}
+
+impl fmt::Display for PolicyBranch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} signatures", self.signatures, self.keys.len())?;
+        if let Some(timelock) = self.condition.timelock {
+            match timelock {
+                LockTime::Blocks(value) => write!(f, ", after block {}", value.to_consensus_u32())?,
+                LockTime::Seconds(value) => {
+                    write!(f, ", after timestamp {}", value.to_consensus_u32())?
+                }
+            }
+        }
+        if let Some(csv) = self.condition.csv {
+            let value: u32 = csv.to_consensus_u32() & 0xFFFF;
+            if csv.is_time_locked() {
+                write!(f, ", {} seconds after confirmation", value * 512)?;
+            } else {
+                write!(f, ", {value} blocks after confirmation")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct PartialBranch {
+    path: PolicyPath,
+    keys: Vec<PkOrF>,
+    signatures: usize,
+}
+
+impl PartialBranch {
+    fn merge(&self, other: &Self) -> Self {
+        let mut branch = self.clone();
+        branch.path.extend(other.path.clone());
+        for key in other.keys.iter() {
+            if !branch.keys.contains(key) {
+                branch.keys.push(key.clone());
+            }
+        }
+        branch.signatures += other.signatures;
+        branch
+    }
+}
+
+/// Enumerate the spending branches of a policy
+///
+/// Threshold nodes without timelocks below them are not expanded, since the
+/// selected items don't change the transaction to build.
+pub fn branches(policy: &SpendingPolicy) -> Vec<PolicyBranch> {
+    expand(policy)
+        .into_iter()
+        .filter_map(|branch| {
+            let condition = policy.get_condition(&branch.path).ok()?;
+            Some(PolicyBranch {
+                path: branch.path,
+                keys: branch.keys,
+                signatures: branch.signatures,
+                condition,
+            })
+        })
+        .collect()
+}
+
+/// Select the cheapest branch that can be satisfied now
+///
+/// Branches with less signatures are preferred and, between them, the ones without timelocks.
+pub fn select_branch<'a>(
+    branches: &'a [PolicyBranch],
+    height: u32,
+    timestamp: u64,
+    utxos: &[Option<BlockTime>],
+) -> Option<&'a PolicyBranch> {
+    branches
+        .iter()
+        .filter(|branch| branch.is_satisfiable(height, timestamp, utxos))
+        .min_by_key(|branch| (branch.signatures, branch.has_timelock()))
+}
+
+/// Check if the signatures of `signers` satisfy the policy
+///
+/// If `path` is set, only the selected items of its threshold nodes are evaluated.
+/// Timelocks are considered satisfied, since they are enforced when building the transaction.
+pub fn is_satisfied(
+    policy: &SpendingPolicy,
+    path: Option<&PolicyPath>,
+    signers: &[XOnlyPublicKey],
+) -> bool {
+    let is_signer = |key: &PkOrF| match key {
+        PkOrF::XOnlyPubkey(key) => signers.contains(key),
+        PkOrF::Pubkey(key) => signers.contains(&key.inner.x_only_public_key().0),
+        PkOrF::Fingerprint(..) => false,
+    };
+
+    match &policy.item {
+        SatisfiableItem::EcdsaSignature(key) | SatisfiableItem::SchnorrSignature(key) => {
+            is_signer(key)
+        }
+        SatisfiableItem::Multisig { keys, threshold } => {
+            keys.iter().filter(|key| is_signer(key)).count() >= *threshold
+        }
+        SatisfiableItem::Thresh { items, threshold } => {
+            match path.and_then(|path| path.get(&policy.id)) {
+                Some(selected) => selected.iter().all(|index| {
+                    items
+                        .get(*index)
+                        .map(|item| is_satisfied(item, path, signers))
+                        .unwrap_or(false)
+                }),
+                None => {
+                    items
+                        .iter()
+                        .filter(|item| is_satisfied(item, path, signers))
+                        .count()
+                        >= *threshold
+                }
+            }
+        }
+        SatisfiableItem::AbsoluteTimelock { .. } | SatisfiableItem::RelativeTimelock { .. } => true,
+        _ => false,
+    }
+}
+
+fn expand(policy: &SpendingPolicy) -> Vec<PartialBranch> {
+    match &policy.item {
+        SatisfiableItem::EcdsaSignature(key) | SatisfiableItem::SchnorrSignature(key) => {
+            vec![PartialBranch {
+                keys: vec![key.clone()],
+                signatures: 1,
+                ..Default::default()
+            }]
+        }
+        SatisfiableItem::Multisig { keys, threshold } => vec![PartialBranch {
+            keys: keys.clone(),
+            signatures: *threshold,
+            ..Default::default()
+        }],
+        SatisfiableItem::Thresh { items, threshold } => {
+            if items.iter().any(|i| has_timelock(&i.item)) {
+                let mut branches: Vec<PartialBranch> = Vec::new();
+                for selected in combinations(items.len(), *threshold).into_iter() {
+                    let mut partials: Vec<PartialBranch> = vec![PartialBranch::default()];
+                    for index in selected.iter() {
+                        let children: Vec<PartialBranch> = expand(&items[*index]);
+                        partials = partials
+                            .iter()
+                            .flat_map(|p| children.iter().map(move |c| p.merge(c)))
+                            .collect();
+                    }
+                    for partial in partials.iter_mut() {
+                        partial.path.insert(policy.id.clone(), selected.clone());
+                    }
+                    branches.extend(partials);
+                }
+                branches
+            } else {
+                let mut signatures: Vec<usize> = items.iter().map(min_signatures).collect();
+                signatures.sort();
+                let mut keys: Vec<PkOrF> = Vec::new();
+                for item in items.iter() {
+                    collect_keys(item, &mut keys);
+                }
+                vec![PartialBranch {
+                    keys,
+                    signatures: signatures.into_iter().take(*threshold).sum(),
+                    ..Default::default()
+                }]
+            }
+        }
+        _ => vec![PartialBranch::default()],
+    }
+}
+
+fn has_timelock(item: &SatisfiableItem) -> bool {
+    match item {
+        SatisfiableItem::AbsoluteTimelock { .. } | SatisfiableItem::RelativeTimelock { .. } => true,
+        SatisfiableItem::Thresh { items, .. } => items.iter().any(|i| has_timelock(&i.item)),
+        _ => false,
+    }
+}
+
+fn min_signatures(policy: &SpendingPolicy) -> usize {
+    match &policy.item {
+        SatisfiableItem::EcdsaSignature(..) | SatisfiableItem::SchnorrSignature(..) => 1,
+        SatisfiableItem::Multisig { threshold, .. } => *threshold,
+        SatisfiableItem::Thresh { items, threshold } => {
+            let mut signatures: Vec<usize> = items.iter().map(min_signatures).collect();
+            signatures.sort();
+            signatures.into_iter().take(*threshold).sum()
+        }
+        _ => 0,
+    }
+}
+
+fn collect_keys(policy: &SpendingPolicy, keys: &mut Vec<PkOrF>) {
+    match &policy.item {
+        SatisfiableItem::EcdsaSignature(key) | SatisfiableItem::SchnorrSignature(key) => {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        SatisfiableItem::Multisig { keys: items, .. } => {
+            for key in items.iter() {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        SatisfiableItem::Thresh { items, .. } => {
+            for item in items.iter() {
+                collect_keys(item, keys);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// All the `k`-sized combinations of the indexes `0..n`
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if k > n {
+        return Vec::new();
+    }
+    let mut list: Vec<Vec<usize>> = combinations(n - 1, k - 1)
+        .into_iter()
+        .map(|mut c| {
+            c.push(n - 1);
+            c
+        })
+        .collect();
+    list.extend(combinations(n - 1, k));
+    list.sort();
+    list
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::str::FromStr;
+
+    use bdk::bitcoin::Network;
+    use bdk::database::MemoryDatabase;
+    use bdk::{KeychainKind, Wallet};
+
+    use super::*;
+    use crate::policy::Policy;
+
+    /// Policy built from a miniscript `policy` and its testnet wallet
+    pub(crate) fn wallet(policy: &str) -> (Policy, Wallet<MemoryDatabase>) {
+        let policy = Policy::from_miniscript_policy("Test", "Test", policy).unwrap();
+        let wallet = Wallet::new(
+            &policy.descriptor.to_string(),
+            None,
+            Network::Testnet,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        (policy, wallet)
+    }
+
+    #[test]
+    fn test_combinations() {
+        assert_eq!(combinations(3, 1), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(combinations(3, 2), vec![vec![0, 1], vec![0, 2], vec![1, 2]]);
+        assert_eq!(combinations(2, 2), vec![vec![0, 1]]);
+        assert!(combinations(1, 2).is_empty());
+    }
+
+    #[test]
+    fn test_timelock_branches() {
+        let (_, wallet) = wallet("or(pk(e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc),and(thresh(2,pk(101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df),pk(7b9eda7669b1075c0eb4b117a34de19be4b3c8b0d5537b5de7fa9793b0a8e9ff)),after(52560)))");
+        let spending_policy = wallet.policies(KeychainKind::External).unwrap().unwrap();
+        let branches = branches(&spending_policy);
+
+        // Key path + 2 script paths
+        assert_eq!(branches.len(), 3);
+
+        let timelocked: Vec<&PolicyBranch> = branches.iter().filter(|b| b.has_timelock()).collect();
+        assert_eq!(timelocked.len(), 1);
+        assert_eq!(timelocked[0].signatures, 2);
+        assert_eq!(
+            timelocked[0].condition.timelock,
+            Some(LockTime::from_consensus(52560))
+        );
+
+        assert!(!timelocked[0].is_satisfiable(52559, 0, &[]));
+        assert!(timelocked[0].is_satisfiable(52560, 0, &[]));
+
+        let maturity = timelocked[0].maturity(None).unwrap();
+        assert_eq!(maturity.height, Some(52560));
+        assert!(maturity.is_mature(52560, 0));
+        assert_eq!(
+            lock_time(&timelocked[0].condition, 60000),
+            Some(LockTime::from_consensus(60000))
+        );
+    }
+
+    #[test]
+    fn test_relative_timelock_maturity() {
+        let (_, wallet) = wallet("or(pk(e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc),and(pk(101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df),older(144)))");
+        let spending_policy = wallet.policies(KeychainKind::External).unwrap().unwrap();
+        let branches = branches(&spending_policy);
+        let timelocked = branches.iter().find(|b| b.has_timelock()).unwrap();
+
+        assert_eq!(timelocked.maturity(None), None);
+
+        let confirmation = BlockTime {
+            height: 1000,
+            timestamp: 0,
+        };
+        let maturity = timelocked.maturity(Some(&confirmation)).unwrap();
+        assert_eq!(maturity.height, Some(1143));
+        assert_eq!(
+            maturity.is_mature(1143, 0),
+            timelocked.is_satisfiable(1143, 0, &[Some(confirmation.clone())])
+        );
+        assert!(!maturity.is_mature(1142, 0));
+
+        let utxo = UtxoMaturity {
+            outpoint: OutPoint::default(),
+            value: 10_000,
+            confirmation: Some(confirmation),
+            branches: vec![(timelocked.clone(), Some(maturity))],
+        };
+        assert_eq!(utxo.blocks_left(1100), Some(43));
+        assert_eq!(utxo.blocks_left(1200), Some(0));
+    }
+
+    #[test]
+    fn test_time_based_relative_timelock_not_tracked() {
+        // older(4194305): 512 seconds
+        let (_, wallet) = wallet("or(pk(e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc),and(pk(101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df),older(4194305)))");
+        let spending_policy = wallet.policies(KeychainKind::External).unwrap().unwrap();
+        let branches = branches(&spending_policy);
+        let timelocked = branches.iter().find(|b| b.has_timelock()).unwrap();
+        assert!(timelocked.has_time_based_csv());
+
+        let confirmation = BlockTime {
+            height: 1000,
+            timestamp: 1_000_000,
+        };
+        let maturity = timelocked.maturity(Some(&confirmation)).unwrap();
+        assert_eq!(maturity.timestamp, Some(1_000_512));
+
+        let utxo = UtxoMaturity {
+            outpoint: OutPoint::default(),
+            value: 10_000,
+            confirmation: Some(confirmation),
+            branches: vec![(timelocked.clone(), Some(maturity))],
+        };
+        assert_eq!(utxo.blocks_left(1000), None);
+    }
+
+    #[test]
+    fn test_is_satisfied() {
+        let (_, wallet) = wallet("or(pk(e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc),and(thresh(2,pk(101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df),pk(7b9eda7669b1075c0eb4b117a34de19be4b3c8b0d5537b5de7fa9793b0a8e9ff)),after(52560)))");
+        let spending_policy = wallet.policies(KeychainKind::External).unwrap().unwrap();
+        let branches = branches(&spending_policy);
+        let timelocked = branches.iter().find(|b| b.has_timelock()).unwrap();
+
+        let key1 = XOnlyPublicKey::from_str(
+            "101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df",
+        )
+        .unwrap();
+        let key2 = XOnlyPublicKey::from_str(
+            "7b9eda7669b1075c0eb4b117a34de19be4b3c8b0d5537b5de7fa9793b0a8e9ff",
+        )
+        .unwrap();
+
+        assert!(!is_satisfied(
+            &spending_policy,
+            Some(&timelocked.path),
+            &[key1]
+        ));
+        assert!(is_satisfied(
+            &spending_policy,
+            Some(&timelocked.path),
+            &[key1, key2]
+        ));
+    }
+}
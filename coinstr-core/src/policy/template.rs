@@ -0,0 +1,230 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+//! Policy templates
+//!
+//! Common custody shapes, built from an ordered list of keys.
+
+use std::fmt;
+use std::str::FromStr;
+
+use bdk::bitcoin::hashes::sha256;
+use bdk::miniscript::policy::Concrete;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Miniscript(#[from] bdk::miniscript::Error),
+    #[error("invalid threshold: {threshold} of {keys} keys")]
+    InvalidThreshold { threshold: usize, keys: usize },
+    #[error("the threshold must be greater than the number of timelocks")]
+    TooManyTimelocks,
+    #[error("not enough keys: expected at least {0}")]
+    NotEnoughKeys(usize),
+}
+
+/// Timelock of a template branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locktime {
+    /// Relative timelock, in blocks since the UTXO confirmation (`older`)
+    Older(u32),
+    /// Absolute timelock, as block height or timestamp (`after`)
+    After(u32),
+}
+
+impl fmt::Display for Locktime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Older(blocks) => write!(f, "older({blocks})"),
+            Self::After(value) => write!(f, "after({value})"),
+        }
+    }
+}
+
+/// Policy template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PolicyTemplate {
+    /// `threshold`-of-n multisig
+    Multisig { threshold: usize },
+    /// `threshold`-of-n multisig, with the last key that can spend alone after the `locktime`
+    Recovery {
+        threshold: usize,
+        locktime: Locktime,
+    },
+    /// `threshold`-of-n multisig, where the threshold decreases by one after each of the `locktimes`
+    Decaying {
+        threshold: usize,
+        locktimes: Vec<Locktime>,
+    },
+    /// `threshold`-of-n multisig of the owners, with the last `heirs` keys that can spend
+    /// with `heirs_threshold` signatures after the `locktime`
+    Inheritance {
+        threshold: usize,
+        heirs: usize,
+        heirs_threshold: usize,
+        locktime: Locktime,
+    },
+    /// The first key spends alone, while the other keys (the guardians) can spend with
+    /// `guardians_threshold` signatures and the preimage of `hash`
+    SocialRecovery {
+        guardians_threshold: usize,
+        hash: sha256::Hash,
+    },
+}
+
+impl PolicyTemplate {
+    /// Build the miniscript policy with the `keys`, in the order of the template roles
+    pub fn build<S>(&self, keys: &[S]) -> Result<Concrete<String>, Error>
+    where
+        S: AsRef<str>,
+    {
+        let policy: String = match self {
+            Self::Multisig { threshold } => multisig(*threshold, keys)?,
+            Self::Recovery {
+                threshold,
+                locktime,
+            } => inheritance(*threshold, 1, 1, locktime, keys)?,
+            Self::Decaying {
+                threshold,
+                locktimes,
+            } => {
+                if *threshold <= locktimes.len() {
+                    return Err(Error::TooManyTimelocks);
+                }
+                if *threshold > keys.len() {
+                    return Err(Error::InvalidThreshold {
+                        threshold: *threshold,
+                        keys: keys.len(),
+                    });
+                }
+                let mut items: Vec<String> =
+                    keys.iter().map(|k| format!("pk({})", k.as_ref())).collect();
+                items.extend(locktimes.iter().map(|l| l.to_string()));
+                format!("thresh({threshold},{})", items.join(","))
+            }
+            Self::Inheritance {
+                threshold,
+                heirs,
+                heirs_threshold,
+                locktime,
+            } => inheritance(*threshold, *heirs, *heirs_threshold, locktime, keys)?,
+            Self::SocialRecovery {
+                guardians_threshold,
+                hash,
+            } => {
+                let (owner, guardians) = keys.split_first().ok_or(Error::NotEnoughKeys(2))?;
+                format!(
+                    "or(9@pk({}),1@and({},sha256({hash})))",
+                    owner.as_ref(),
+                    multisig(*guardians_threshold, guardians)?
+                )
+            }
+        };
+
+        Ok(Concrete::from_str(&policy)?)
+    }
+}
+
+/// Owners multisig, or heirs multisig after the `locktime`
+fn inheritance<S>(
+    threshold: usize,
+    heirs: usize,
+    heirs_threshold: usize,
+    locktime: &Locktime,
+    keys: &[S],
+) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    if heirs == 0 || keys.len() <= heirs {
+        return Err(Error::NotEnoughKeys(heirs + 1));
+    }
+    let (owners, heirs) = keys.split_at(keys.len() - heirs);
+    Ok(format!(
+        "or(9@{},1@and({},{locktime}))",
+        multisig(threshold, owners)?,
+        multisig(heirs_threshold, heirs)?
+    ))
+}
+
+fn multisig<S>(threshold: usize, keys: &[S]) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    if threshold == 0 || threshold > keys.len() {
+        return Err(Error::InvalidThreshold {
+            threshold,
+            keys: keys.len(),
+        });
+    }
+
+    if keys.len() == 1 {
+        return Ok(format!("pk({})", keys[0].as_ref()));
+    }
+
+    let keys: Vec<String> = keys.iter().map(|k| format!("pk({})", k.as_ref())).collect();
+    Ok(format!("thresh({threshold},{})", keys.join(",")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEYS: [&str; 4] = [
+        "7eb7beb4ec0ac0e7ddf5c2f9b88e3b8a9e55e0f6e0b6e3f7b6cf06f3a8b0f0a1",
+        "e6e4f4a6f1e1dbd4d8f6a2ad5d1ed0e57a35ea0bfb2bb0f6c1f79bba3b1a3b44",
+        "f3bd34e2f4bce2e02e53f0e1c0c8f2b0e3f1e6d2c3d4b5a6978a9b0c1d2e3f40",
+        "a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90",
+    ];
+
+    #[test]
+    fn test_multisig_template() {
+        let template = PolicyTemplate::Multisig { threshold: 2 };
+        let policy = template.build(&KEYS[..3]).unwrap();
+        assert_eq!(
+            policy.to_string(),
+            format!("thresh(2,pk({}),pk({}),pk({}))", KEYS[0], KEYS[1], KEYS[2])
+        );
+
+        let template = PolicyTemplate::Multisig { threshold: 4 };
+        assert!(template.build(&KEYS[..3]).is_err());
+    }
+
+    #[test]
+    fn test_decaying_template() {
+        let template = PolicyTemplate::Decaying {
+            threshold: 3,
+            locktimes: vec![Locktime::After(800000), Locktime::After(850000)],
+        };
+        let policy = template.build(&KEYS[..3]).unwrap();
+        assert_eq!(
+            policy.to_string(),
+            format!(
+                "thresh(3,pk({}),pk({}),pk({}),after(800000),after(850000))",
+                KEYS[0], KEYS[1], KEYS[2]
+            )
+        );
+
+        let template = PolicyTemplate::Decaying {
+            threshold: 2,
+            locktimes: vec![Locktime::After(800000), Locktime::After(850000)],
+        };
+        assert!(template.build(&KEYS[..3]).is_err());
+    }
+
+    #[test]
+    fn test_recovery_template() {
+        let template = PolicyTemplate::Recovery {
+            threshold: 2,
+            locktime: Locktime::Older(52560),
+        };
+        let policy = template.build(&KEYS).unwrap().to_string();
+        assert!(policy.contains(&format!("and(pk({}),older(52560))", KEYS[3])));
+        assert!(policy.contains(&format!(
+            "thresh(2,pk({}),pk({}),pk({}))",
+            KEYS[0], KEYS[1], KEYS[2]
+        )));
+    }
+}
@@ -1,39 +1,156 @@
+use std::fmt;
 use std::str::FromStr;
 
+use bdk::descriptor::policy::PkOrF;
 use keechain_core::bitcoin::psbt::PartiallySignedTransaction;
-use keechain_core::bitcoin::Address;
+use keechain_core::bitcoin::{Address, Txid, XOnlyPublicKey};
+use nostr_sdk::{EventId, Timestamp};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::policy::{PolicyBranch, PolicyPath};
+
+/// Output of a spending proposal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recipient {
+    pub address: Address,
+    /// Amount in sats
+    pub amount: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Recipient {
+    pub fn new(address: Address, amount: u64) -> Self {
+        Self {
+            address,
+            amount,
+            label: None,
+        }
+    }
+
+    pub fn label<S>(self, label: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+}
+
+/// Fee bump of a stuck transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeBump {
+    /// Replacement of the transaction (BIP125)
+    Rbf(Txid),
+    /// Child transaction spending an output of the stuck one
+    Cpfp(Txid),
+}
+
+impl FeeBump {
+    /// Txid of the stuck transaction
+    pub fn txid(&self) -> Txid {
+        match self {
+            Self::Rbf(txid) | Self::Cpfp(txid) => *txid,
+        }
+    }
+}
+
+impl fmt::Display for FeeBump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rbf(txid) => write!(f, "RBF of {txid}"),
+            Self::Cpfp(txid) => write!(f, "CPFP of {txid}"),
+        }
+    }
+}
+
+/// Expiry of a spending proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expiry {
+    /// Unix timestamp
+    Time(u64),
+    /// Block height
+    Height(u32),
+}
+
+impl Expiry {
+    /// Check if expired at the current time and `height`
+    pub fn is_expired(&self, height: u32) -> bool {
+        match self {
+            Self::Time(timestamp) => Timestamp::now().as_u64() >= *timestamp,
+            Self::Height(expiry_height) => height >= *expiry_height,
+        }
+    }
+}
+
+impl fmt::Display for Expiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Time(timestamp) => write!(f, "at timestamp {timestamp}"),
+            Self::Height(height) => write!(f, "at block {height}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawSpendingProposal")]
 pub struct SpendingProposal {
-    pub to_address: Address,
-    pub amount: u64,
+    pub recipients: Vec<Recipient>,
     pub memo: String,
-    #[serde(
-        serialize_with = "serialize_psbt",
-        deserialize_with = "deserialize_psbt"
-    )]
+    #[serde(serialize_with = "serialize_psbt")]
     pub psbt: PartiallySignedTransaction,
+    /// Spending path selected to build the PSBT
+    #[serde(default)]
+    pub policy_path: Option<PolicyPath>,
+    /// Fee paid by the transaction, in sats
+    #[serde(default)]
+    pub fee: Option<u64>,
+    /// Stuck transaction bumped by this proposal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_bump: Option<FeeBump>,
+    /// After this the proposal can be deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<Expiry>,
 }
 
 impl SpendingProposal {
     pub fn new<S>(
-        to_address: Address,
-        amount: u64,
+        recipients: Vec<Recipient>,
         memo: S,
         psbt: PartiallySignedTransaction,
+        policy_path: Option<PolicyPath>,
+        fee: Option<u64>,
     ) -> Self
     where
         S: Into<String>,
     {
         Self {
-            to_address,
-            amount,
+            recipients,
             memo: memo.into(),
             psbt,
+            policy_path,
+            fee,
+            fee_bump: None,
+            expiry: None,
         }
     }
 
+    /// Total amount sent to the recipients, in sats
+    pub fn amount(&self) -> u64 {
+        self.recipients.iter().map(|r| r.amount).sum()
+    }
+
+    /// Check if the proposal is expired at the current time and `height`
+    pub fn is_expired(&self, height: u32) -> bool {
+        self.expiry
+            .map(|expiry| expiry.is_expired(height))
+            .unwrap_or(false)
+    }
+
     /// Deserialize from `JSON` string
     pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
     where
@@ -48,6 +165,117 @@ impl SpendingProposal {
     }
 }
 
+/// Approval of a spending proposal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approval {
+    pub approval_id: EventId,
+    /// Member that approved the proposal
+    pub author: XOnlyPublicKey,
+    /// PSBT signed by the member
+    pub psbt: PartiallySignedTransaction,
+    pub timestamp: Timestamp,
+}
+
+/// Rejection of a spending proposal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub rejection_id: EventId,
+    /// Member that rejected the proposal
+    pub author: XOnlyPublicKey,
+    pub reason: String,
+    pub timestamp: Timestamp,
+}
+
+/// Approval status of a spending proposal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprovalStatus {
+    /// Keys that signed the PSBT
+    pub signers: Vec<XOnlyPublicKey>,
+    /// Spending path of the proposal
+    pub branch: Option<PolicyBranch>,
+    /// Keys of the spending path that haven't signed yet
+    pub missing: Vec<XOnlyPublicKey>,
+    /// If the signatures satisfy the policy, so the transaction can be broadcasted
+    pub is_satisfied: bool,
+    /// Members that rejected the proposal
+    pub rejected_by: Vec<XOnlyPublicKey>,
+    /// If the rejections prevent the policy to be satisfied
+    pub is_unsatisfiable: bool,
+}
+
+impl ApprovalStatus {
+    /// Number of signatures collected for the spending path
+    pub fn signatures(&self) -> usize {
+        match &self.branch {
+            Some(branch) => branch
+                .keys
+                .iter()
+                .filter(|key| match key {
+                    PkOrF::XOnlyPubkey(key) => self.signers.contains(key),
+                    _ => false,
+                })
+                .count(),
+            None => self.signers.len(),
+        }
+    }
+}
+
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.branch {
+            Some(branch) => write!(f, "{} of {} signed", self.signatures(), branch.signatures)?,
+            None => write!(f, "{} signed", self.signatures())?,
+        }
+        if !self.rejected_by.is_empty() {
+            write!(f, ", {} rejected", self.rejected_by.len())?;
+        }
+        if self.is_satisfied {
+            write!(f, ", ready to broadcast")?;
+        } else if self.is_unsatisfiable {
+            write!(f, ", can't be satisfied")?;
+        }
+        Ok(())
+    }
+}
+
+/// Spending proposal as published, including the single recipient format
+#[derive(Deserialize)]
+struct RawSpendingProposal {
+    #[serde(default)]
+    recipients: Vec<Recipient>,
+    to_address: Option<Address>,
+    amount: Option<u64>,
+    memo: String,
+    #[serde(deserialize_with = "deserialize_psbt")]
+    psbt: PartiallySignedTransaction,
+    #[serde(default)]
+    policy_path: Option<PolicyPath>,
+    #[serde(default)]
+    fee: Option<u64>,
+    #[serde(default)]
+    fee_bump: Option<FeeBump>,
+    #[serde(default)]
+    expiry: Option<Expiry>,
+}
+
+impl From<RawSpendingProposal> for SpendingProposal {
+    fn from(raw: RawSpendingProposal) -> Self {
+        let mut recipients = raw.recipients;
+        if let (Some(address), Some(amount)) = (raw.to_address, raw.amount) {
+            recipients.push(Recipient::new(address, amount));
+        }
+        Self {
+            recipients,
+            memo: raw.memo,
+            psbt: raw.psbt,
+            policy_path: raw.policy_path,
+            fee: raw.fee,
+            fee_bump: raw.fee_bump,
+            expiry: raw.expiry,
+        }
+    }
+}
+
 fn serialize_psbt<S>(psbt: &PartiallySignedTransaction, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -0,0 +1,229 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+//! Local store of the secrets that must survive a restart, saved next to the keychain file
+//!
+//! Each secret is encrypted to the own nostr key, with NIP-44.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use nostr_sdk::{EventId, Keys};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::frost::{KeyPackage, SigningNonces};
+use crate::musig2::SecretNonce;
+use crate::util::nip44;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error(transparent)]
+    Keys(#[from] nostr_sdk::key::Error),
+    #[error(transparent)]
+    JSON(#[from] serde_json::Error),
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Secrets {
+    /// MuSig2 secret nonces, by proposal
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    musig2_nonces: HashMap<String, String>,
+    /// FROST signing nonces, by proposal
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    frost_nonces: HashMap<String, String>,
+    /// FROST key packages, by key generation session
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    frost_key_packages: HashMap<String, String>,
+}
+
+/// Secret store
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+    path: Option<PathBuf>,
+    keys: Keys,
+    secrets: Arc<Mutex<Secrets>>,
+}
+
+impl SecretStore {
+    /// Store kept only in memory: the secrets are lost on restart
+    pub fn memory(keys: Keys) -> Self {
+        Self {
+            path: None,
+            keys,
+            secrets: Arc::new(Mutex::new(Secrets::default())),
+        }
+    }
+
+    /// Open the store at `path`, or create it on the first write
+    pub fn open<P>(path: P, keys: Keys) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let secrets: Secrets = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            Secrets::default()
+        };
+        Ok(Self {
+            path: Some(path),
+            keys,
+            secrets: Arc::new(Mutex::new(secrets)),
+        })
+    }
+
+    /// Save the MuSig2 secret nonces of a proposal, replacing the previous ones
+    pub fn save_musig2_nonces(
+        &self,
+        proposal_id: EventId,
+        secnonces: &[SecretNonce],
+    ) -> Result<(), Error> {
+        let payload = self.encrypt(secnonces)?;
+        self.update(|secrets| {
+            secrets
+                .musig2_nonces
+                .insert(proposal_id.to_string(), payload);
+        })
+    }
+
+    /// Remove the MuSig2 secret nonces of a proposal, so they can't be used twice
+    pub fn take_musig2_nonces(
+        &self,
+        proposal_id: EventId,
+    ) -> Result<Option<Vec<SecretNonce>>, Error> {
+        let mut payload: Option<String> = None;
+        self.update(|secrets| {
+            payload = secrets.musig2_nonces.remove(&proposal_id.to_string());
+        })?;
+        payload.map(|payload| self.decrypt(payload)).transpose()
+    }
+
+    /// Save the FROST signing nonces of a proposal, replacing the previous ones
+    pub fn save_frost_nonces(
+        &self,
+        proposal_id: EventId,
+        nonces: &[SigningNonces],
+    ) -> Result<(), Error> {
+        let payload = self.encrypt(nonces)?;
+        self.update(|secrets| {
+            secrets
+                .frost_nonces
+                .insert(proposal_id.to_string(), payload);
+        })
+    }
+
+    /// Remove the FROST signing nonces of a proposal, so they can't be used twice
+    pub fn take_frost_nonces(
+        &self,
+        proposal_id: EventId,
+    ) -> Result<Option<Vec<SigningNonces>>, Error> {
+        let mut payload: Option<String> = None;
+        self.update(|secrets| {
+            payload = secrets.frost_nonces.remove(&proposal_id.to_string());
+        })?;
+        payload.map(|payload| self.decrypt(payload)).transpose()
+    }
+
+    /// Save the FROST key package of a key generation session
+    pub fn save_frost_key_package(
+        &self,
+        session_id: EventId,
+        key_package: &KeyPackage,
+    ) -> Result<(), Error> {
+        let payload = self.encrypt(&(session_id, key_package))?;
+        self.update(|secrets| {
+            secrets
+                .frost_key_packages
+                .insert(session_id.to_string(), payload);
+        })
+    }
+
+    /// Get the FROST key packages, by key generation session
+    pub fn frost_key_packages(&self) -> Result<HashMap<EventId, KeyPackage>, Error> {
+        let payloads: Vec<String> = self
+            .secrets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .frost_key_packages
+            .values()
+            .cloned()
+            .collect();
+        payloads
+            .into_iter()
+            .map(|payload| self.decrypt(payload))
+            .collect()
+    }
+
+    fn encrypt<T>(&self, secret: &T) -> Result<String, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(nip44::encrypt(
+            &self.keys.secret_key()?,
+            &self.keys.public_key(),
+            serde_json::to_string(secret)?,
+        )?)
+    }
+
+    fn decrypt<T>(&self, payload: String) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let json = nip44::decrypt(&self.keys.secret_key()?, &self.keys.public_key(), payload)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Apply `f` to the secrets and write them to the file, if any
+    fn update<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Secrets),
+    {
+        let mut secrets = self.secrets.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut secrets);
+        if let Some(path) = &self.path {
+            // Write a temporary file first, so a crash can't leave a truncated store
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_vec(&*secrets)?)?;
+            fs::rename(tmp, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nostr_sdk::EventBuilder;
+
+    use super::*;
+    use crate::constants::MUSIG2_NONCE_KIND;
+
+    #[test]
+    fn test_musig2_nonces_survive_restart() {
+        let keys = Keys::generate();
+        let path = std::env::temp_dir().join(format!("coinstr-{}.secrets", keys.public_key()));
+        let proposal_id = EventBuilder::new(MUSIG2_NONCE_KIND, "", &[])
+            .to_event(&keys)
+            .unwrap()
+            .id;
+
+        let secnonces = vec![SecretNonce::generate(), SecretNonce::generate()];
+        let pubnonces: Vec<_> = secnonces.iter().map(|n| n.public_nonce()).collect();
+        let store = SecretStore::open(&path, keys.clone()).unwrap();
+        store.save_musig2_nonces(proposal_id, &secnonces).unwrap();
+
+        let store = SecretStore::open(&path, keys).unwrap();
+        let restored = store.take_musig2_nonces(proposal_id).unwrap().unwrap();
+        let restored: Vec<_> = restored.iter().map(|n| n.public_nonce()).collect();
+        assert_eq!(restored, pubnonces);
+        assert!(store.take_musig2_nonces(proposal_id).unwrap().is_none());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bdk::miniscript::descriptor::{DescriptorPublicKey, DescriptorXKey, Wildcard};
+use bdk::miniscript::{Descriptor, ForEachKey};
+use keechain_core::bitcoin::secp256k1::{schnorr, KeyPair, Message, XOnlyPublicKey, SECP256K1};
+use keechain_core::bitcoin::util::bip32::{
+    ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint,
+};
+use keechain_core::bitcoin::Network;
+
+use crate::musig2::tagged_hash;
+use crate::util;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Bip32(#[from] keechain_core::bitcoin::util::bip32::Error),
+    #[error(transparent)]
+    Miniscript(#[from] bdk::miniscript::Error),
+    #[error(transparent)]
+    Secp256k1(#[from] keechain_core::bitcoin::secp256k1::Error),
+    #[error(transparent)]
+    Util(#[from] util::Error),
+}
+
+/// Derivation path of the fixed keys from the account key
+const FIXED_KEY_PATH: &str = "m/0/0";
+
+/// Derivation scheme of a signer key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerPurpose {
+    /// BIP-86 account (`m/86'/coin'/account'`)
+    Bip86,
+    /// BIP-48 multisig account (`m/48'/coin'/account'/2'`)
+    Bip48,
+}
+
+impl SignerPurpose {
+    /// Derivation path of the account
+    pub fn derivation_path(&self, network: Network, account: u32) -> Result<DerivationPath, Error> {
+        let coin: u32 = match network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+        let mut path: Vec<ChildNumber> = match self {
+            Self::Bip86 => vec![ChildNumber::from_hardened_idx(86)?],
+            Self::Bip48 => vec![ChildNumber::from_hardened_idx(48)?],
+        };
+        path.push(ChildNumber::from_hardened_idx(coin)?);
+        path.push(ChildNumber::from_hardened_idx(account)?);
+        if let Self::Bip48 = self {
+            path.push(ChildNumber::from_hardened_idx(2)?);
+        }
+        Ok(DerivationPath::from(path))
+    }
+
+    /// Get the purpose of an account derivation path, if known
+    pub fn from_derivation_path(path: &DerivationPath) -> Option<Self> {
+        match path.into_iter().next() {
+            Some(ChildNumber::Hardened { index: 86 }) => Some(Self::Bip86),
+            Some(ChildNumber::Hardened { index: 48 }) => Some(Self::Bip48),
+            _ => None,
+        }
+    }
+}
+
+/// Bitcoin signing key of a member, derived from its seed
+///
+/// Announced with the nostr identity key, so the other members can use it in the policies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerKey {
+    pub fingerprint: Fingerprint,
+    pub derivation_path: DerivationPath,
+    pub xpub: ExtendedPubKey,
+}
+
+impl SignerKey {
+    /// Derive the signer key of the `account` from the seed root key
+    pub fn from_root_key(
+        root_key: &ExtendedPrivKey,
+        purpose: SignerPurpose,
+        account: u32,
+    ) -> Result<Self, Error> {
+        let (signer, _xprv) = Self::derive(root_key, purpose, account)?;
+        Ok(signer)
+    }
+
+    fn derive(
+        root_key: &ExtendedPrivKey,
+        purpose: SignerPurpose,
+        account: u32,
+    ) -> Result<(Self, ExtendedPrivKey), Error> {
+        let derivation_path = purpose.derivation_path(root_key.network, account)?;
+        let xprv = root_key.derive_priv(SECP256K1, &derivation_path)?;
+        let signer = Self {
+            fingerprint: root_key.fingerprint(SECP256K1),
+            derivation_path,
+            xpub: ExtendedPubKey::from_priv(SECP256K1, &xprv),
+        };
+        Ok((signer, xprv))
+    }
+
+    /// Message signed to prove that the signer key is owned by the nostr public key `owner`
+    fn ownership_message(&self, owner: &XOnlyPublicKey) -> Result<Message, Error> {
+        let hash = tagged_hash(
+            "Coinstr/signer-key",
+            &[&owner.serialize(), &self.xpub.encode()],
+        );
+        Ok(Message::from_slice(&hash)?)
+    }
+
+    /// Descriptor key, with origin (`[fingerprint/path]xpub/*`)
+    pub fn descriptor_key(&self) -> DescriptorPublicKey {
+        DescriptorPublicKey::XPub(DescriptorXKey {
+            origin: Some((self.fingerprint, self.derivation_path.clone())),
+            xkey: self.xpub,
+            derivation_path: DerivationPath::default(),
+            wildcard: Wildcard::Unhardened,
+        })
+    }
+
+    /// Fixed descriptor key, with origin (`[fingerprint/path]xpub/0/0`), for the policies
+    /// whose keys are aggregated in the internal key (MuSig2 and FROST)
+    pub fn fixed_descriptor_key(&self) -> Result<DescriptorPublicKey, Error> {
+        Ok(DescriptorPublicKey::XPub(DescriptorXKey {
+            origin: Some((self.fingerprint, self.derivation_path.clone())),
+            xkey: self.xpub,
+            derivation_path: DerivationPath::from_str(FIXED_KEY_PATH)?,
+            wildcard: Wildcard::None,
+        }))
+    }
+
+    /// Public key of the [`SignerKey::fixed_descriptor_key`]
+    pub fn fixed_public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        let path = DerivationPath::from_str(FIXED_KEY_PATH)?;
+        Ok(self
+            .xpub
+            .derive_pub(SECP256K1, &path)?
+            .public_key
+            .x_only_public_key()
+            .0)
+    }
+
+    /// Multipath descriptor key, with origin (`[fingerprint/path]xpub/<0;1>/*`),
+    /// for the external and the internal descriptors
+    pub fn multipath_descriptor_key(&self) -> String {
+        let key = self.descriptor_key().to_string();
+        format!("{}/<0;1>/*", key.trim_end_matches("/*"))
+    }
+
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Signer key announced by a member, with the proof that the member owns it
+///
+/// Without the proof, a member could claim the signer key of another one,
+/// to be matched with its keys in the policies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerAnnouncement {
+    #[serde(flatten)]
+    pub signer: SignerKey,
+    /// Signature of the nostr public key of the owner, made with the signer key
+    pub proof: schnorr::Signature,
+}
+
+impl SignerAnnouncement {
+    /// Derive the signer key of the `account` from the seed root key and sign with it
+    /// the nostr public key of the `owner`
+    pub fn new(
+        root_key: &ExtendedPrivKey,
+        purpose: SignerPurpose,
+        account: u32,
+        owner: &XOnlyPublicKey,
+    ) -> Result<Self, Error> {
+        let (signer, xprv) = SignerKey::derive(root_key, purpose, account)?;
+        let keypair = KeyPair::from_secret_key(SECP256K1, &xprv.private_key);
+        let proof = SECP256K1.sign_schnorr_no_aux_rand(&signer.ownership_message(owner)?, &keypair);
+        Ok(Self { signer, proof })
+    }
+
+    /// Verify that the signer key is owned by the nostr public key `owner`
+    pub fn verify(&self, owner: &XOnlyPublicKey) -> Result<(), Error> {
+        let (public_key, _parity) = self.signer.xpub.public_key.x_only_public_key();
+        Ok(SECP256K1.verify_schnorr(
+            &self.proof,
+            &self.signer.ownership_message(owner)?,
+            &public_key,
+        )?)
+    }
+
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+/// Derive from the seed root key the private keys of the `descriptor` extended keys
+///
+/// Only the keys with an origin matching the root key fingerprint are returned,
+/// after checking that the derived key is the one in the descriptor.
+pub fn derive_descriptor_xprvs<S>(
+    root_key: &ExtendedPrivKey,
+    descriptor: S,
+) -> Result<Vec<DescriptorXKey<ExtendedPrivKey>>, Error>
+where
+    S: Into<String>,
+{
+    let fingerprint: Fingerprint = root_key.fingerprint(SECP256K1);
+    let (external, internal) = util::split_multipath_descriptor(descriptor)?;
+
+    let mut xpubs: Vec<DescriptorXKey<ExtendedPubKey>> = Vec::new();
+    for descriptor in std::iter::once(external).chain(internal) {
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(&descriptor)?;
+        descriptor.for_each_key(|key| {
+            if let DescriptorPublicKey::XPub(xpub) = key {
+                if matches!(&xpub.origin, Some((fp, _)) if *fp == fingerprint)
+                    && !xpubs.contains(xpub)
+                {
+                    xpubs.push(xpub.clone());
+                }
+            }
+            true
+        });
+    }
+
+    let mut xprvs: Vec<DescriptorXKey<ExtendedPrivKey>> = Vec::new();
+    for xpub in xpubs.into_iter() {
+        if let Some((fingerprint, path)) = xpub.origin {
+            let xkey = root_key.derive_priv(SECP256K1, &path)?;
+            if ExtendedPubKey::from_priv(SECP256K1, &xkey) == xpub.xkey {
+                xprvs.push(DescriptorXKey {
+                    origin: Some((fingerprint, path)),
+                    xkey,
+                    derivation_path: xpub.derivation_path,
+                    wildcard: xpub.wildcard,
+                });
+            }
+        }
+    }
+    Ok(xprvs)
+}
+
+#[cfg(test)]
+mod test {
+    use keechain_core::bitcoin::secp256k1::SecretKey;
+
+    use super::*;
+
+    #[test]
+    fn test_signer_announcement_proof() {
+        let root_key = ExtendedPrivKey::new_master(Network::Testnet, &[7u8; 32]).unwrap();
+        let owner = SecretKey::from_slice(&[1u8; 32])
+            .unwrap()
+            .x_only_public_key(SECP256K1)
+            .0;
+        let other = SecretKey::from_slice(&[2u8; 32])
+            .unwrap()
+            .x_only_public_key(SECP256K1)
+            .0;
+
+        let announcement =
+            SignerAnnouncement::new(&root_key, SignerPurpose::Bip86, 0, &owner).unwrap();
+        let announcement = SignerAnnouncement::from_json(announcement.as_json()).unwrap();
+        assert!(announcement.verify(&owner).is_ok());
+        assert!(announcement.verify(&other).is_err());
+
+        // The proof doesn't hold for another signer key
+        let mut forged = announcement;
+        forged.signer = SignerKey::from_root_key(&root_key, SignerPurpose::Bip86, 1).unwrap();
+        assert!(forged.verify(&owner).is_err());
+    }
+}
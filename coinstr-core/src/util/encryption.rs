@@ -0,0 +1,71 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use keechain_core::bitcoin::secp256k1::{SecretKey, XOnlyPublicKey};
+use nostr_sdk::nips::nip04;
+use nostr_sdk::{Event, Tag, TagKind};
+
+use super::nip44;
+
+const ENCRYPTION_TAG: &str = "encryption";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    NIP04(#[from] nip04::Error),
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
+}
+
+/// Encryption scheme of an event content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionVersion {
+    Nip04,
+    Nip44,
+}
+
+impl EncryptionVersion {
+    /// Get the encryption version from the event tags
+    ///
+    /// Events without the version tag are encrypted with NIP-04.
+    pub fn from_event(event: &Event) -> Self {
+        for tag in event.tags.iter() {
+            if let Tag::Generic(TagKind::Custom(kind), values) = tag {
+                if kind == ENCRYPTION_TAG && values.first().map(|v| v.as_str()) == Some("nip44") {
+                    return Self::Nip44;
+                }
+            }
+        }
+        Self::Nip04
+    }
+
+    /// Tag to add to the events encrypted with this version
+    pub fn tag(&self) -> Tag {
+        let version = match self {
+            Self::Nip04 => "nip04",
+            Self::Nip44 => "nip44",
+        };
+        Tag::Generic(
+            TagKind::Custom(ENCRYPTION_TAG.to_string()),
+            vec![version.to_string()],
+        )
+    }
+}
+
+/// Encrypt `content` with NIP-44
+///
+/// The events must include the [`EncryptionVersion::Nip44`] tag.
+pub fn encrypt<S>(sk: &SecretKey, pk: &XOnlyPublicKey, content: S) -> Result<String, Error>
+where
+    S: Into<String>,
+{
+    Ok(nip44::encrypt(sk, pk, content)?)
+}
+
+/// Decrypt the content of an event, according to its encryption version
+pub fn decrypt(sk: &SecretKey, pk: &XOnlyPublicKey, event: &Event) -> Result<String, Error> {
+    match EncryptionVersion::from_event(event) {
+        EncryptionVersion::Nip04 => Ok(nip04::decrypt(sk, pk, &event.content)?),
+        EncryptionVersion::Nip44 => Ok(nip44::decrypt(sk, pk, &event.content)?),
+    }
+}
@@ -43,6 +43,53 @@ pub fn big_number(num: u64) -> String {
     number
 }
 
+/// Approximate duration, like `~3 months`
+pub fn duration(seconds: u64) -> String {
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+    let (value, unit) = if seconds >= YEAR {
+        (seconds / YEAR, "year")
+    } else if seconds >= MONTH {
+        (seconds / MONTH, "month")
+    } else if seconds >= DAY {
+        (seconds / DAY, "day")
+    } else if seconds >= HOUR {
+        (seconds / HOUR, "hour")
+    } else {
+        (seconds / 60, "minute")
+    };
+    if value == 1 {
+        format!("~{value} {unit}")
+    } else {
+        format!("~{value} {unit}s")
+    }
+}
+
+/// UTC date of a unix timestamp, like `2023-05-01 12:00 UTC`
+pub fn date(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Civil date from the days since the unix epoch
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -66,4 +113,22 @@ mod test {
         assert_eq!(big_number(1_000_000), "1M".to_string());
         assert_eq!(big_number(1_000_000_000), "1Bn".to_string());
     }
+
+    #[test]
+    fn format_duration() {
+        assert_eq!(duration(144 * 600), "~1 day".to_string());
+        assert_eq!(duration(1008 * 600), "~7 days".to_string());
+        assert_eq!(duration(52560 * 600), "~1 year".to_string());
+        assert_eq!(duration(4320 * 600), "~1 month".to_string());
+        assert_eq!(duration(12960 * 600), "~3 months".to_string());
+        assert_eq!(duration(6 * 600), "~1 hour".to_string());
+        assert_eq!(duration(3 * 600), "~30 minutes".to_string());
+    }
+
+    #[test]
+    fn format_date() {
+        assert_eq!(date(0), "1970-01-01 00:00 UTC".to_string());
+        assert_eq!(date(1682942400), "2023-05-01 12:00 UTC".to_string());
+        assert_eq!(date(1709210096), "2024-02-29 12:34 UTC".to_string());
+    }
 }
@@ -1,73 +1,288 @@
 use std::str::FromStr;
 
+use bdk::bitcoin::hashes::{hash160, hex, ripemd160, sha256};
+use bdk::miniscript::descriptor::{DescriptorKeyParseError, DescriptorPublicKey, SinglePubKey};
+use bdk::miniscript::policy::Concrete;
+use bdk::miniscript::{hash256, Descriptor, ForEachKey, TranslatePk, Translator};
+use keechain_core::bitcoin::psbt::PartiallySignedTransaction;
 use keechain_core::bitcoin::secp256k1::rand::rngs::OsRng;
-use keechain_core::bitcoin::secp256k1::SECP256K1;
+use keechain_core::bitcoin::secp256k1::{Parity, PublicKey, SecretKey, SECP256K1};
+use keechain_core::bitcoin::util::bip32::{
+    ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint,
+};
 use keechain_core::bitcoin::XOnlyPublicKey;
 pub use keechain_core::util::*;
 use nostr_sdk::{Event, EventId, Tag};
 
+pub mod encryption;
 pub mod format;
+pub mod nip44;
 
-const XONLY_PUBLIC_KEY_LEN: usize = 64;
-const HEX_CHARS: &str = "ABCDEFabcdef0123456789";
+pub use self::encryption::EncryptionVersion;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
     Secp256k1(#[from] nostr_sdk::secp256k1::Error),
+    #[error(transparent)]
+    Miniscript(#[from] bdk::miniscript::Error),
+    #[error(transparent)]
+    Bip32(#[from] keechain_core::bitcoin::util::bip32::Error),
+    #[error(transparent)]
+    DescriptorKey(#[from] DescriptorKeyParseError),
+    #[error(transparent)]
+    Hex(#[from] hex::Error),
+    #[error("invalid multipath key: must have two paths (`<0;1>`)")]
+    InvalidMultipath,
 }
 
-pub fn extract_public_keys<S>(descriptor: S) -> Result<Vec<XOnlyPublicKey>, Error>
-where
-    S: Into<String>,
-{
-    let descriptor: String = descriptor.into();
-    let len: usize = descriptor.len();
-    let mut public_keys: Vec<XOnlyPublicKey> = Vec::new();
-    for (index, _char) in descriptor.char_indices() {
-        if len - index < XONLY_PUBLIC_KEY_LEN {
-            break;
-        }
-        if let Some(chunk) = descriptor.get(index..index + XONLY_PUBLIC_KEY_LEN) {
-            if maybe_xonly_pubkey(chunk) {
-                if let Ok(pubkey) = XOnlyPublicKey::from_str(chunk) {
-                    if !public_keys.contains(&pubkey) {
-                        public_keys.push(pubkey);
+/// Key of a descriptor or policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorKeyInfo {
+    /// For extended keys, the key derived at the derivation path (excluding the wildcard)
+    pub public_key: XOnlyPublicKey,
+    /// Fingerprint of the master key (or of the key itself, without origin)
+    pub fingerprint: Fingerprint,
+    /// Full derivation path from the master key
+    pub derivation_path: DerivationPath,
+    /// For extended keys, the extended key (without the derivation path)
+    pub xpub: Option<ExtendedPubKey>,
+}
+
+impl DescriptorKeyInfo {
+    fn from_descriptor_key(key: &DescriptorPublicKey) -> Result<Self, Error> {
+        let public_key: XOnlyPublicKey = match key {
+            DescriptorPublicKey::Single(single) => match &single.key {
+                SinglePubKey::FullKey(public_key) => public_key.inner.x_only_public_key().0,
+                SinglePubKey::XOnly(public_key) => *public_key,
+            },
+            DescriptorPublicKey::XPub(xpub) => {
+                xpub.xkey
+                    .derive_pub(SECP256K1, &xpub.derivation_path)?
+                    .public_key
+                    .x_only_public_key()
+                    .0
+            }
+        };
+        Ok(Self {
+            public_key,
+            fingerprint: key.master_fingerprint(),
+            derivation_path: key.full_derivation_path(),
+            xpub: match key {
+                DescriptorPublicKey::Single(..) => None,
+                DescriptorPublicKey::XPub(xpub) => Some(xpub.xkey),
+            },
+        })
+    }
+}
+
+/// Translate the multipath keys (`<0;1>`) of a descriptor to the keys of one of the paths
+///
+/// The multipath keys (BIP-389) aren't supported by miniscript: the path is selected and
+/// the resulting key is parsed as a [`DescriptorPublicKey`].
+struct MultipathTranslator {
+    /// Index of the path (`0` for the external, `1` for the internal)
+    index: usize,
+    /// If at least one multipath key was found
+    found: bool,
+}
+
+impl Translator<String, DescriptorPublicKey, Error> for MultipathTranslator {
+    fn pk(&mut self, pk: &String) -> Result<DescriptorPublicKey, Error> {
+        let mut multipath: usize = 0;
+        let mut steps: Vec<String> = Vec::new();
+        for step in pk.split('/') {
+            match step.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+                Some(paths) => {
+                    let paths = paths
+                        .split(';')
+                        .map(ChildNumber::from_str)
+                        .collect::<Result<Vec<ChildNumber>, _>>()?;
+                    if paths.len() != 2 {
+                        return Err(Error::InvalidMultipath);
                     }
+                    multipath += 1;
+                    steps.push(paths[self.index].to_string());
                 }
+                None => steps.push(step.to_string()),
             }
         }
+        if multipath > 1 {
+            return Err(Error::InvalidMultipath);
+        }
+        self.found |= multipath == 1;
+        Ok(DescriptorPublicKey::from_str(&steps.join("/"))?)
+    }
+
+    fn sha256(&mut self, sha256: &String) -> Result<sha256::Hash, Error> {
+        Ok(sha256::Hash::from_str(sha256)?)
+    }
+
+    fn hash256(&mut self, hash256: &String) -> Result<hash256::Hash, Error> {
+        Ok(hash256::Hash::from_str(hash256)?)
+    }
+
+    fn ripemd160(&mut self, ripemd160: &String) -> Result<ripemd160::Hash, Error> {
+        Ok(ripemd160::Hash::from_str(ripemd160)?)
+    }
+
+    fn hash160(&mut self, hash160: &String) -> Result<hash160::Hash, Error> {
+        Ok(hash160::Hash::from_str(hash160)?)
     }
-    Ok(public_keys)
 }
 
-fn maybe_xonly_pubkey(chunk: &str) -> bool {
-    if chunk.len() != XONLY_PUBLIC_KEY_LEN {
-        return false;
+/// Split a multipath descriptor (`<0;1>/*` keys) into the external and the internal descriptors
+///
+/// Descriptors without multipath keys are returned as external only.
+/// Policies (not descriptors) are returned as they are.
+pub fn split_multipath_descriptor<S>(descriptor: S) -> Result<(String, Option<String>), Error>
+where
+    S: Into<String>,
+{
+    let descriptor: String = descriptor.into();
+    let parsed = match Descriptor::<String>::from_str(&descriptor) {
+        Ok(parsed) => parsed,
+        Err(_) => return Ok((descriptor, None)),
+    };
+
+    let mut external = MultipathTranslator {
+        index: 0,
+        found: false,
+    };
+    let external_descriptor = parsed.translate_pk(&mut external)?;
+    if !external.found {
+        return Ok((external_descriptor.to_string(), None));
+    }
+
+    let mut internal = MultipathTranslator {
+        index: 1,
+        found: false,
+    };
+    let internal_descriptor = parsed.translate_pk(&mut internal)?;
+
+    Ok((
+        external_descriptor.to_string(),
+        Some(internal_descriptor.to_string()),
+    ))
+}
+
+/// Get the keys of a descriptor (or of a policy)
+///
+/// For multipath descriptors, the keys of the external descriptor are returned.
+pub fn extract_descriptor_keys<S>(descriptor: S) -> Result<Vec<DescriptorKeyInfo>, Error>
+where
+    S: Into<String>,
+{
+    let (descriptor, _internal) = split_multipath_descriptor(descriptor)?;
+
+    let mut keys: Vec<DescriptorPublicKey> = Vec::new();
+    match Descriptor::<DescriptorPublicKey>::from_str(&descriptor) {
+        Ok(descriptor) => {
+            descriptor.for_each_key(|key| {
+                keys.push(key.clone());
+                true
+            });
+        }
+        Err(_) => {
+            let policy = Concrete::<DescriptorPublicKey>::from_str(&descriptor)?;
+            policy.for_each_key(|key| {
+                keys.push(key.clone());
+                true
+            });
+        }
     }
 
-    for c in chunk.chars() {
-        if !HEX_CHARS.contains(c) {
-            return false;
+    let mut infos: Vec<DescriptorKeyInfo> = Vec::new();
+    for key in keys.iter() {
+        let info = DescriptorKeyInfo::from_descriptor_key(key)?;
+        if !infos.iter().any(|i| i.public_key == info.public_key) {
+            infos.push(info);
         }
     }
+    Ok(infos)
+}
 
-    true
+/// Get the x-only public keys of a descriptor (or of a policy)
+pub fn extract_public_keys<S>(descriptor: S) -> Result<Vec<XOnlyPublicKey>, Error>
+where
+    S: Into<String>,
+{
+    Ok(extract_descriptor_keys(descriptor)?
+        .into_iter()
+        .map(|info| info.public_key)
+        .collect())
 }
 
-pub trait Unspendable {
+/// BIP-341 NUMS point `H`: `lift_x(SHA256(G))`, with no known discrete logarithm
+pub const NUMS_H: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// Provably unspendable keys, to use as taproot internal key
+pub trait Unspendable: Sized {
+    /// The NUMS point `H`
     fn unspendable() -> Self;
+
+    /// The NUMS point `H` tweaked with `r` (`H + rG`)
+    ///
+    /// Once `r` is shared, anyone can verify the key is unspendable,
+    /// while it can't be recognized as such by who doesn't know `r`.
+    fn unspendable_with_tweak(tweak: &SecretKey) -> Result<Self, Error>;
+
+    /// Check if the key is `H`, or `H` tweaked with `tweak`
+    fn is_unspendable(&self, tweak: Option<&SecretKey>) -> bool;
 }
 
 impl Unspendable for XOnlyPublicKey {
     fn unspendable() -> Self {
-        let mut rng = OsRng::default();
-        let (_, public_key) = SECP256K1.generate_keypair(&mut rng);
-        let (public_key, _) = public_key.x_only_public_key();
-        public_key
+        XOnlyPublicKey::from_str(NUMS_H).expect("valid NUMS point")
+    }
+
+    fn unspendable_with_tweak(tweak: &SecretKey) -> Result<Self, Error> {
+        let h: PublicKey = Self::unspendable().public_key(Parity::Even);
+        let r: PublicKey = PublicKey::from_secret_key(SECP256K1, tweak);
+        Ok(h.combine(&r)?.x_only_public_key().0)
+    }
+
+    fn is_unspendable(&self, tweak: Option<&SecretKey>) -> bool {
+        match tweak {
+            Some(tweak) => match Self::unspendable_with_tweak(tweak) {
+                Ok(public_key) => self == &public_key,
+                Err(_) => false,
+            },
+            None => self == &Self::unspendable(),
+        }
     }
 }
 
+/// Generate a random tweak for the NUMS point
+pub fn random_nums_tweak() -> SecretKey {
+    SecretKey::new(&mut OsRng)
+}
+
+/// Get the keys that signed all the inputs of a PSBT
+///
+/// Both script path signatures and key path signatures (by the internal key) are considered.
+pub fn extract_psbt_signers(psbt: &PartiallySignedTransaction) -> Vec<XOnlyPublicKey> {
+    let mut signers: Option<Vec<XOnlyPublicKey>> = None;
+    for input in psbt.inputs.iter() {
+        let mut keys: Vec<XOnlyPublicKey> =
+            input.tap_script_sigs.keys().map(|(key, _)| *key).collect();
+        if let (Some(_), Some(internal_key)) = (input.tap_key_sig, input.tap_internal_key) {
+            keys.push(internal_key);
+        }
+        signers = Some(match signers {
+            Some(signers) => signers
+                .into_iter()
+                .filter(|key| keys.contains(key))
+                .collect(),
+            None => keys,
+        });
+    }
+    let mut signers = signers.unwrap_or_default();
+    signers.sort();
+    signers.dedup();
+    signers
+}
+
 pub fn extract_first_event_id(event: &Event) -> Option<EventId> {
     for tag in event.tags.iter() {
         if let Tag::Event(event_id, ..) = tag {
@@ -112,7 +327,7 @@ mod test {
 
     #[test]
     fn test_descriptor_extractor() {
-        let descriptor = "tr(0298e9fdeb06b3e9e49db3dbffe1a3a353bf359c54fe415769dd3f174f4ea610,multi_a(2,c04e8da91853b7fd215102e6aa48477d8e1ba6b3c16902371a153d3784a1b0f7,e8978cf935f7f912e77c57fcf03668a20cf4eacfbcdeb046613946266d8b8204))#37490v6l";
+        let descriptor = "tr(0298e9fdeb06b3e9e49db3dbffe1a3a353bf359c54fe415769dd3f174f4ea610,multi_a(2,c04e8da91853b7fd215102e6aa48477d8e1ba6b3c16902371a153d3784a1b0f7,e8978cf935f7f912e77c57fcf03668a20cf4eacfbcdeb046613946266d8b8204))#2l33gdcy";
         let pubkeys = extract_public_keys(descriptor).unwrap();
 
         assert_eq!(
@@ -133,4 +348,65 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn test_split_multipath_descriptor() {
+        let descriptor = "tr([73c5da0a/86'/1'/0']tpubDDfvzhdVV4unsoKt5aE6dcsNsfeWbTgmLZPi8LQDYU2xixrYemMfWJ3BaVneH3u7DBQePdTwhpybaKRU95pi6PMUtLPBJLVQRpzEnjfjZzX/<0;1>/*)";
+        let (external, internal) = split_multipath_descriptor(descriptor).unwrap();
+
+        assert_eq!(
+            Descriptor::<DescriptorPublicKey>::from_str(&external).unwrap(),
+            Descriptor::from_str("tr([73c5da0a/86'/1'/0']tpubDDfvzhdVV4unsoKt5aE6dcsNsfeWbTgmLZPi8LQDYU2xixrYemMfWJ3BaVneH3u7DBQePdTwhpybaKRU95pi6PMUtLPBJLVQRpzEnjfjZzX/0/*)").unwrap()
+        );
+        assert_eq!(
+            Descriptor::<DescriptorPublicKey>::from_str(&internal.unwrap()).unwrap(),
+            Descriptor::from_str("tr([73c5da0a/86'/1'/0']tpubDDfvzhdVV4unsoKt5aE6dcsNsfeWbTgmLZPi8LQDYU2xixrYemMfWJ3BaVneH3u7DBQePdTwhpybaKRU95pi6PMUtLPBJLVQRpzEnjfjZzX/1/*)").unwrap()
+        );
+
+        assert!(split_multipath_descriptor("tr([73c5da0a/86'/1'/0']tpubDDfvzhdVV4unsoKt5aE6dcsNsfeWbTgmLZPi8LQDYU2xixrYemMfWJ3BaVneH3u7DBQePdTwhpybaKRU95pi6PMUtLPBJLVQRpzEnjfjZzX/<0;1;2>/*)").is_err());
+        assert!(split_multipath_descriptor("tr([73c5da0a/86'/1'/0']tpubDDfvzhdVV4unsoKt5aE6dcsNsfeWbTgmLZPi8LQDYU2xixrYemMfWJ3BaVneH3u7DBQePdTwhpybaKRU95pi6PMUtLPBJLVQRpzEnjfjZzX/<0;1>/<0;1>/*)").is_err());
+    }
+
+    #[test]
+    fn test_hash_not_extracted() {
+        let policy = "and(pk(e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc),sha256(101e7953a54b18d0f41ea199b9adf2d7e643441b5af8e539531e6d7275cee1df))";
+        let pubkeys = extract_public_keys(policy).unwrap();
+
+        assert_eq!(
+            pubkeys,
+            vec![XOnlyPublicKey::from_str(
+                "e69d88524a5669723b473523cd2c6bfe76d6c289656c3ecd7981fa8fef784dcc"
+            )
+            .unwrap()]
+        )
+    }
+
+    #[test]
+    fn test_unspendable() {
+        let h = XOnlyPublicKey::unspendable();
+        assert!(h.is_unspendable(None));
+
+        let tweak = random_nums_tweak();
+        let tweaked = XOnlyPublicKey::unspendable_with_tweak(&tweak).unwrap();
+        assert_ne!(tweaked, h);
+        assert!(tweaked.is_unspendable(Some(&tweak)));
+        assert!(!tweaked.is_unspendable(None));
+        assert!(!h.is_unspendable(Some(&tweak)));
+    }
+
+    #[test]
+    fn test_xpub_with_origin() {
+        let descriptor = "tr([73c5da0a/86'/1'/0']tpubDDfvzhdVV4unsoKt5aE6dcsNsfeWbTgmLZPi8LQDYU2xixrYemMfWJ3BaVneH3u7DBQePdTwhpybaKRU95pi6PMUtLPBJLVQRpzEnjfjZzX/0/*)";
+        let keys = extract_descriptor_keys(descriptor).unwrap();
+
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            keys[0].fingerprint,
+            Fingerprint::from_str("73c5da0a").unwrap()
+        );
+        assert_eq!(
+            keys[0].derivation_path,
+            DerivationPath::from_str("m/86'/1'/0'/0").unwrap()
+        );
+    }
 }
@@ -0,0 +1,199 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use base64::engine::{general_purpose, Engine};
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use keechain_core::bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use keechain_core::bitcoin::hashes::{sha256, Hash, HashEngine};
+use keechain_core::bitcoin::secp256k1::rand::rngs::OsRng;
+use keechain_core::bitcoin::secp256k1::rand::RngCore;
+use keechain_core::bitcoin::secp256k1::{ecdh, Parity, SecretKey, XOnlyPublicKey};
+
+const VERSION: u8 = 2;
+const SALT: &[u8] = b"nip44-v2";
+const MIN_PLAINTEXT_LEN: usize = 1;
+const MAX_PLAINTEXT_LEN: usize = 65535;
+const MIN_PAYLOAD_LEN: usize = 99;
+const MAX_PAYLOAD_LEN: usize = 65603;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("invalid plaintext length")]
+    InvalidPlaintextLength,
+    #[error("invalid payload length")]
+    InvalidPayloadLength,
+    #[error("unknown version: {0}")]
+    UnknownVersion(u8),
+    #[error("invalid MAC")]
+    InvalidMac,
+    #[error("invalid padding")]
+    InvalidPadding,
+}
+
+/// Encrypt `content` from `sk` to `pk` with NIP-44 (version 2)
+pub fn encrypt<S>(sk: &SecretKey, pk: &XOnlyPublicKey, content: S) -> Result<String, Error>
+where
+    S: Into<String>,
+{
+    let mut nonce = [0u8; 32];
+    OsRng.fill_bytes(&mut nonce);
+    encrypt_with_nonce(&conversation_key(sk, pk), content.into().as_bytes(), nonce)
+}
+
+/// Decrypt `payload` sent from `pk` to `sk` (or from `sk` to `pk`)
+pub fn decrypt<S>(sk: &SecretKey, pk: &XOnlyPublicKey, payload: S) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let payload = general_purpose::STANDARD.decode(payload.as_ref())?;
+    if payload.len() < MIN_PAYLOAD_LEN || payload.len() > MAX_PAYLOAD_LEN {
+        return Err(Error::InvalidPayloadLength);
+    }
+    if payload[0] != VERSION {
+        return Err(Error::UnknownVersion(payload[0]));
+    }
+
+    let nonce = &payload[1..33];
+    let ciphertext = &payload[33..payload.len() - 32];
+    let mac = &payload[payload.len() - 32..];
+
+    let (chacha_key, chacha_nonce, hmac_key) = message_keys(&conversation_key(sk, pk), nonce);
+
+    // Verify the MAC before decrypting
+    let expected_mac = hmac(&hmac_key, &[nonce, ciphertext]);
+    let diff = expected_mac
+        .iter()
+        .zip(mac.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(Error::InvalidMac);
+    }
+
+    let mut padded = ciphertext.to_vec();
+    ChaCha20::new(&chacha_key.into(), &chacha_nonce.into()).apply_keystream(&mut padded);
+
+    let len = u16::from_be_bytes([padded[0], padded[1]]) as usize;
+    if len < MIN_PLAINTEXT_LEN || padded.len() != 2 + calc_padded_len(len) {
+        return Err(Error::InvalidPadding);
+    }
+
+    Ok(String::from_utf8(padded[2..2 + len].to_vec())?)
+}
+
+fn encrypt_with_nonce(
+    conversation_key: &[u8; 32],
+    plaintext: &[u8],
+    nonce: [u8; 32],
+) -> Result<String, Error> {
+    let len = plaintext.len();
+    if !(MIN_PLAINTEXT_LEN..=MAX_PLAINTEXT_LEN).contains(&len) {
+        return Err(Error::InvalidPlaintextLength);
+    }
+
+    // Pad the plaintext, prefixed by its length
+    let mut padded = vec![0u8; 2 + calc_padded_len(len)];
+    padded[..2].copy_from_slice(&(len as u16).to_be_bytes());
+    padded[2..2 + len].copy_from_slice(plaintext);
+
+    let (chacha_key, chacha_nonce, hmac_key) = message_keys(conversation_key, &nonce);
+    ChaCha20::new(&chacha_key.into(), &chacha_nonce.into()).apply_keystream(&mut padded);
+    let mac = hmac(&hmac_key, &[&nonce, &padded]);
+
+    let mut payload: Vec<u8> = Vec::with_capacity(1 + 32 + padded.len() + 32);
+    payload.push(VERSION);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&padded);
+    payload.extend_from_slice(&mac);
+
+    Ok(general_purpose::STANDARD.encode(payload))
+}
+
+/// HKDF-extract of the ECDH shared x coordinate
+fn conversation_key(sk: &SecretKey, pk: &XOnlyPublicKey) -> [u8; 32] {
+    let point = ecdh::shared_secret_point(&pk.public_key(Parity::Even), sk);
+    hmac(SALT, &[&point[..32]])
+}
+
+/// HKDF-expand of the conversation key into the ChaCha20 key and nonce and the HMAC key
+fn message_keys(conversation_key: &[u8; 32], nonce: &[u8]) -> ([u8; 32], [u8; 12], [u8; 32]) {
+    let mut okm: Vec<u8> = Vec::with_capacity(96);
+    let mut previous: Vec<u8> = Vec::new();
+    for counter in 1..=3u8 {
+        let block = hmac(conversation_key, &[&previous, nonce, &[counter]]);
+        okm.extend_from_slice(&block);
+        previous = block.to_vec();
+    }
+
+    let mut chacha_key = [0u8; 32];
+    let mut chacha_nonce = [0u8; 12];
+    let mut hmac_key = [0u8; 32];
+    chacha_key.copy_from_slice(&okm[..32]);
+    chacha_nonce.copy_from_slice(&okm[32..44]);
+    hmac_key.copy_from_slice(&okm[44..76]);
+    (chacha_key, chacha_nonce, hmac_key)
+}
+
+fn hmac(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    for chunk in data.iter() {
+        engine.input(chunk);
+    }
+    Hmac::<sha256::Hash>::from_engine(engine).into_inner()
+}
+
+fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use keechain_core::bitcoin::secp256k1::SECP256K1;
+
+    use super::*;
+
+    #[test]
+    fn test_encrypt_with_nonce() {
+        let sk1 =
+            SecretKey::from_str("0000000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+        let sk2 =
+            SecretKey::from_str("0000000000000000000000000000000000000000000000000000000000000002")
+                .unwrap();
+        let (pk2, _) = sk2.x_only_public_key(SECP256K1);
+        let conversation_key = conversation_key(&sk1, &pk2);
+
+        let mut nonce = [0u8; 32];
+        nonce[31] = 1;
+
+        let payload = encrypt_with_nonce(&conversation_key, b"a", nonce).unwrap();
+        assert_eq!(payload, "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb");
+
+        let (pk1, _) = sk1.x_only_public_key(SECP256K1);
+        assert_eq!(decrypt(&sk2, &pk1, payload).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_calc_padded_len() {
+        assert_eq!(calc_padded_len(1), 32);
+        assert_eq!(calc_padded_len(32), 32);
+        assert_eq!(calc_padded_len(33), 64);
+        assert_eq!(calc_padded_len(257), 320);
+        assert_eq!(calc_padded_len(65535), 65536);
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright (c) 2022-2023 Coinstr
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+
+use keechain_core::bitcoin::OutPoint;
+use nostr_sdk::EventId;
+
+/// UTXOs selection of a spending proposal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoinControl {
+    /// UTXOs that must be spent
+    pub must_spend: Vec<OutPoint>,
+    /// UTXOs that must not be spent
+    pub do_not_spend: Vec<OutPoint>,
+    /// Spend only the `must_spend` UTXOs
+    pub manually_selected_only: bool,
+}
+
+impl CoinControl {
+    /// Check if `outpoint` can be spent
+    pub fn is_spendable(&self, outpoint: &OutPoint) -> bool {
+        if self.do_not_spend.contains(outpoint) {
+            return false;
+        }
+        if self.manually_selected_only && !self.must_spend.is_empty() {
+            return self.must_spend.contains(outpoint);
+        }
+        true
+    }
+}
+
+/// Relative timelocks of the UTXOs of a policy, checked by the heartbeat
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Blocks left before a relative timelock path activates, for each confirmed UTXO
+    pub blocks_left: HashMap<OutPoint, u32>,
+    /// Proposal that sends the UTXOs under the threshold back to the policy, if made now
+    pub refresh: Option<EventId>,
+}
+
+/// Label of a policy UTXO, shared between the policy members
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoLabel {
+    pub outpoint: OutPoint,
+    pub label: String,
+}
+
+impl UtxoLabel {
+    pub fn new<S>(outpoint: OutPoint, label: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            outpoint,
+            label: label.into(),
+        }
+    }
+
+    /// Deserialize from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize to `JSON` string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
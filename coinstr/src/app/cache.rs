@@ -7,21 +7,24 @@ use std::sync::Arc;
 
 use coinstr_core::bdk::blockchain::Blockchain;
 use coinstr_core::bdk::database::MemoryDatabase;
-use coinstr_core::bdk::{Balance, SyncOptions, TransactionDetails, Wallet};
-use coinstr_core::bitcoin::Network;
+use coinstr_core::bdk::{Balance, LocalUtxo, SyncOptions, TransactionDetails, Wallet};
+use coinstr_core::bitcoin::{Network, OutPoint};
 use coinstr_core::nostr_sdk::{EventId, Result};
 use coinstr_core::policy::Policy;
+use coinstr_core::util;
 use coinstr_core::util::serde::{deserialize, serialize};
 use sled::Tree;
 use tokio::sync::Mutex;
 
 const SHARED_KEYS: &str = "shared_keys";
 const POLICIES: &str = "policies";
+const FROZEN_UTXOS: &str = "frozen_utxos";
 
 #[derive(Debug, Clone)]
 pub struct Cache {
     pub shared_keys: Tree,
     pub policies: Tree,
+    pub frozen_utxos: Tree,
     pub wallets: Arc<Mutex<HashMap<EventId, Wallet<MemoryDatabase>>>>,
 }
 
@@ -34,6 +37,7 @@ impl Cache {
         Self {
             shared_keys: db.open_tree(SHARED_KEYS).expect("Impossible to open tree"),
             policies: db.open_tree(POLICIES).expect("Impossible to open tree"),
+            frozen_utxos: db.open_tree(FROZEN_UTXOS).expect("Impossible to open tree"),
             wallets: Arc::new(Mutex::new(HashMap::new())),
         }
     }
@@ -61,11 +65,38 @@ impl Cache {
         Ok(())
     }
 
+    /// Get the UTXOs of a policy that must not be spent
+    pub fn get_frozen_utxos(&self, policy_id: EventId) -> Result<Vec<OutPoint>> {
+        match self.frozen_utxos.get(serialize(policy_id)?)? {
+            Some(value) => Ok(deserialize(value.to_vec())?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn set_frozen_utxo(
+        &self,
+        policy_id: EventId,
+        outpoint: OutPoint,
+        frozen: bool,
+    ) -> Result<()> {
+        let mut utxos = self.get_frozen_utxos(policy_id)?;
+        utxos.retain(|utxo| *utxo != outpoint);
+        if frozen {
+            utxos.push(outpoint);
+        }
+        self.frozen_utxos
+            .insert(serialize(policy_id)?, serialize(utxos)?)?;
+        log::info!("Set UTXO {outpoint} of policy {policy_id} as frozen: {frozen}");
+        Ok(())
+    }
+
     pub async fn load_wallets(&self, network: Network) -> Result<()> {
         let mut wallets = self.wallets.lock().await;
         for (policy_id, policy) in self.get_policies()?.into_iter() {
+            let (external, internal) =
+                util::split_multipath_descriptor(policy.descriptor.to_string())?;
             let db = MemoryDatabase::new();
-            let wallet = Wallet::new(&policy.descriptor.to_string(), None, network, db)?;
+            let wallet = Wallet::new(&external, internal.as_deref(), network, db)?;
             wallets.insert(policy_id, wallet);
         }
         Ok(())
@@ -89,6 +120,12 @@ impl Cache {
         wallet.get_balance().ok()
     }
 
+    pub async fn get_utxos(&self, policy_id: EventId) -> Option<Vec<LocalUtxo>> {
+        let wallets = self.wallets.lock().await;
+        let wallet = wallets.get(&policy_id)?;
+        wallet.list_unspent().ok()
+    }
+
     pub async fn get_transactions(&self, policy_id: EventId) -> Option<Vec<TransactionDetails>> {
         let wallets = self.wallets.lock().await;
         let wallet = wallets.get(&policy_id)?;
@@ -14,7 +14,7 @@ pub enum Stage {
     Policies,
     AddPolicy,
     Policy(EventId, Policy),
-    Spend(EventId),
+    Spend(EventId, Policy),
     Proposals,
     Proposal(EventId),
     Setting,
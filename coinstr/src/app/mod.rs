@@ -14,7 +14,8 @@ mod sync;
 pub use self::context::{Context, Stage};
 pub use self::message::Message;
 use self::screen::{
-    AddPolicyState, DashboardState, PoliciesState, PolicyState, SettingState, SpendState,
+    AddPolicyState, DashboardState, PoliciesState, PolicyState, ProposalState, ProposalsState,
+    SettingState, SpendState,
 };
 use self::sync::CoinstrSync;
 
@@ -36,9 +37,9 @@ pub fn new_state(ctx: &Context) -> Box<dyn State> {
         Stage::Policies => PoliciesState::new().into(),
         Stage::AddPolicy => AddPolicyState::new().into(),
         Stage::Policy(policy_id, policy) => PolicyState::new(*policy_id, policy.clone()).into(),
-        Stage::Spend(policy_id) => SpendState::new(*policy_id).into(),
-        Stage::Proposals => todo!(),
-        Stage::Proposal(_proposal_id) => todo!(),
+        Stage::Spend(policy_id, policy) => SpendState::new(*policy_id, policy.clone()).into(),
+        Stage::Proposals => ProposalsState::new().into(),
+        Stage::Proposal(proposal_id) => ProposalState::new(*proposal_id).into(),
         Stage::Setting => SettingState::new().into(),
     }
 }
@@ -1,20 +1,73 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
-use iced::widget::{Column, Row, Space};
+use std::fmt;
+use std::str::FromStr;
+
+use coinstr_core::bitcoin::hashes::sha256;
+use coinstr_core::policy::{Locktime, Policy, PolicyTemplate};
+use iced::widget::{Checkbox, Column, PickList, Row, Space};
 use iced::{Alignment, Command, Element, Length};
 
 use crate::app::component::Dashboard;
 use crate::app::{Context, Message, Stage, State};
-use crate::component::{button, Text, TextInput};
+use crate::component::{button, NumericInput, Text, TextInput};
 use crate::constants::APP_NAME;
 use crate::theme::color::DARK_RED;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PolicyMode {
+    #[default]
+    Descriptor,
+    Multisig,
+    Decaying,
+    Inheritance,
+    SocialRecovery,
+}
+
+impl PolicyMode {
+    const ALL: [PolicyMode; 5] = [
+        PolicyMode::Descriptor,
+        PolicyMode::Multisig,
+        PolicyMode::Decaying,
+        PolicyMode::Inheritance,
+        PolicyMode::SocialRecovery,
+    ];
+
+    fn keys_placeholder(&self) -> &str {
+        match self {
+            Self::Inheritance => "Owners keys, then heirs keys (comma separated)",
+            Self::SocialRecovery => "Owner key, then guardians keys (comma separated)",
+            _ => "Keys (comma separated)",
+        }
+    }
+}
+
+impl fmt::Display for PolicyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Descriptor => write!(f, "Descriptor/Policy"),
+            Self::Multisig => write!(f, "Multisig"),
+            Self::Decaying => write!(f, "Decaying multisig"),
+            Self::Inheritance => write!(f, "Inheritance"),
+            Self::SocialRecovery => write!(f, "Social recovery"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AddPolicyMessage {
     NameChanged(String),
     DescriptionChanged(String),
     DescriptorChanged(String),
+    ModeSelected(PolicyMode),
+    KeysChanged(String),
+    ThresholdChanged(Option<u64>),
+    HeirsChanged(Option<u64>),
+    HeirsThresholdChanged(Option<u64>),
+    LocktimesChanged(String),
+    RelativeLocktimeChanged(bool),
+    HashChanged(String),
     ErrorChanged(Option<String>),
     SavePolicy,
 }
@@ -24,6 +77,14 @@ pub struct AddPolicyState {
     name: String,
     description: String,
     descriptor: String,
+    mode: PolicyMode,
+    keys: String,
+    threshold: Option<u64>,
+    heirs: Option<u64>,
+    heirs_threshold: Option<u64>,
+    locktimes: String,
+    relative_locktime: bool,
+    hash: String,
     error: Option<String>,
 }
 
@@ -31,6 +92,63 @@ impl AddPolicyState {
     pub fn new() -> Self {
         Self::default()
     }
+
+    fn template(&self) -> Result<Option<PolicyTemplate>, String> {
+        let threshold = self.threshold.unwrap_or(1) as usize;
+        let mut locktimes: Vec<Locktime> = Vec::new();
+        for value in self.locktimes.split(',').map(|v| v.trim()) {
+            if !value.is_empty() {
+                let value: u32 = value.parse().map_err(|_| "Invalid timelock")?;
+                locktimes.push(if self.relative_locktime {
+                    Locktime::Older(value)
+                } else {
+                    Locktime::After(value)
+                });
+            }
+        }
+
+        Ok(match self.mode {
+            PolicyMode::Descriptor => None,
+            PolicyMode::Multisig => Some(PolicyTemplate::Multisig { threshold }),
+            PolicyMode::Decaying => Some(PolicyTemplate::Decaying {
+                threshold,
+                locktimes,
+            }),
+            PolicyMode::Inheritance => Some(PolicyTemplate::Inheritance {
+                threshold,
+                heirs: self.heirs.unwrap_or(1) as usize,
+                heirs_threshold: self.heirs_threshold.unwrap_or(1) as usize,
+                locktime: locktimes.first().copied().ok_or("Timelock required")?,
+            }),
+            PolicyMode::SocialRecovery => Some(PolicyTemplate::SocialRecovery {
+                guardians_threshold: threshold,
+                hash: sha256::Hash::from_str(&self.hash).map_err(|_| "Invalid hash")?,
+            }),
+        })
+    }
+
+    /// Descriptor (or miniscript policy) to save
+    fn descriptor(&self) -> Result<String, String> {
+        match self.template()? {
+            Some(template) => {
+                let keys: Vec<&str> = self
+                    .keys
+                    .split(',')
+                    .map(|k| k.trim())
+                    .filter(|k| !k.is_empty())
+                    .collect();
+                let policy = Policy::from_template(
+                    self.name.as_str(),
+                    self.description.as_str(),
+                    &template,
+                    &keys,
+                )
+                .map_err(|e| e.to_string())?;
+                Ok(policy.descriptor.to_string())
+            }
+            None => Ok(self.descriptor.clone()),
+        }
+    }
 }
 
 impl State for AddPolicyState {
@@ -44,14 +162,34 @@ impl State for AddPolicyState {
                 AddPolicyMessage::NameChanged(name) => self.name = name,
                 AddPolicyMessage::DescriptionChanged(desc) => self.description = desc,
                 AddPolicyMessage::DescriptorChanged(desc) => self.descriptor = desc,
+                AddPolicyMessage::ModeSelected(mode) => self.mode = mode,
+                AddPolicyMessage::KeysChanged(keys) => self.keys = keys,
+                AddPolicyMessage::ThresholdChanged(threshold) => self.threshold = threshold,
+                AddPolicyMessage::HeirsChanged(heirs) => self.heirs = heirs,
+                AddPolicyMessage::HeirsThresholdChanged(threshold) => {
+                    self.heirs_threshold = threshold
+                }
+                AddPolicyMessage::LocktimesChanged(locktimes) => self.locktimes = locktimes,
+                AddPolicyMessage::RelativeLocktimeChanged(value) => self.relative_locktime = value,
+                AddPolicyMessage::HashChanged(hash) => self.hash = hash,
                 AddPolicyMessage::ErrorChanged(error) => self.error = error,
                 AddPolicyMessage::SavePolicy => {
                     let client = ctx.client.clone();
                     let name = self.name.clone();
                     let description = self.description.clone();
-                    let descriptor = self.descriptor.clone();
+                    let descriptor = match self.descriptor() {
+                        Ok(descriptor) => descriptor,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return Command::none();
+                        }
+                    };
                     return Command::perform(
-                        async move { client.save_policy(name, description, descriptor).await },
+                        async move {
+                            client
+                                .save_policy(name, description, descriptor, None)
+                                .await
+                        },
                         |res| {
                             if let Err(e) = res {
                                 AddPolicyMessage::ErrorChanged(Some(e.to_string())).into()
@@ -80,11 +218,97 @@ impl State for AddPolicyState {
         .placeholder("Policy description")
         .view();
 
-        let descriptor = TextInput::new("Descriptor/Policy", &self.descriptor, |s| {
-            AddPolicyMessage::DescriptorChanged(s).into()
-        })
-        .placeholder("Policy descriptor")
-        .view();
+        let mode = Column::new()
+            .push(Text::new("Type").view())
+            .push(
+                PickList::new(&PolicyMode::ALL[..], Some(self.mode), |mode| {
+                    AddPolicyMessage::ModeSelected(mode).into()
+                })
+                .padding(10)
+                .width(Length::Fill),
+            )
+            .spacing(5);
+
+        let form = match self.mode {
+            PolicyMode::Descriptor => Column::new().push(
+                TextInput::new("Descriptor/Policy", &self.descriptor, |s| {
+                    AddPolicyMessage::DescriptorChanged(s).into()
+                })
+                .placeholder("Policy descriptor")
+                .view(),
+            ),
+            mode => {
+                let keys = TextInput::new("Keys", &self.keys, |s| {
+                    AddPolicyMessage::KeysChanged(s).into()
+                })
+                .placeholder(mode.keys_placeholder())
+                .view();
+
+                let threshold_name = if mode == PolicyMode::SocialRecovery {
+                    "Guardians threshold"
+                } else {
+                    "Threshold"
+                };
+                let threshold = NumericInput::new(threshold_name, self.threshold, |s| {
+                    AddPolicyMessage::ThresholdChanged(s).into()
+                })
+                .placeholder("Signatures required");
+
+                let mut form = Column::new().push(keys).push(threshold).spacing(10);
+
+                if mode == PolicyMode::Inheritance {
+                    form = form
+                        .push(
+                            NumericInput::new("Heirs", self.heirs, |s| {
+                                AddPolicyMessage::HeirsChanged(s).into()
+                            })
+                            .placeholder("Number of heirs (last keys)"),
+                        )
+                        .push(
+                            NumericInput::new("Heirs threshold", self.heirs_threshold, |s| {
+                                AddPolicyMessage::HeirsThresholdChanged(s).into()
+                            })
+                            .placeholder("Signatures required to the heirs"),
+                        );
+                }
+
+                if matches!(mode, PolicyMode::Decaying | PolicyMode::Inheritance) {
+                    let placeholder = if mode == PolicyMode::Decaying {
+                        "Timelocks (comma separated)"
+                    } else {
+                        "Timelock"
+                    };
+                    form = form
+                        .push(
+                            TextInput::new("Timelock", &self.locktimes, |s| {
+                                AddPolicyMessage::LocktimesChanged(s).into()
+                            })
+                            .placeholder(placeholder)
+                            .view(),
+                        )
+                        .push(
+                            Checkbox::new(
+                                "Relative timelock (blocks since the deposit)",
+                                self.relative_locktime,
+                                |value| AddPolicyMessage::RelativeLocktimeChanged(value).into(),
+                            )
+                            .width(Length::Fill),
+                        );
+                }
+
+                if mode == PolicyMode::SocialRecovery {
+                    form = form.push(
+                        TextInput::new("Hash", &self.hash, |s| {
+                            AddPolicyMessage::HashChanged(s).into()
+                        })
+                        .placeholder("SHA256 hash of the recovery secret")
+                        .view(),
+                    );
+                }
+
+                form
+            }
+        };
 
         let error = if let Some(error) = &self.error {
             Row::new().push(Text::new(error).color(DARK_RED).view())
@@ -98,7 +322,8 @@ impl State for AddPolicyState {
         let content = Column::new()
             .push(name)
             .push(description)
-            .push(descriptor)
+            .push(mode)
+            .push(form)
             .push(error)
             .push(Space::with_height(Length::Fixed(15.0)))
             .push(save_policy_btn)
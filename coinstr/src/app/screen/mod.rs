@@ -5,6 +5,8 @@ mod add_policy;
 mod dashboard;
 mod policies;
 mod policy;
+mod proposal;
+mod proposals;
 mod setting;
 mod spend;
 
@@ -12,5 +14,7 @@ pub use self::add_policy::{AddPolicyMessage, AddPolicyState};
 pub use self::dashboard::{DashboardMessage, DashboardState};
 pub use self::policies::{PoliciesMessage, PoliciesState};
 pub use self::policy::{PolicyMessage, PolicyState};
+pub use self::proposal::{ProposalMessage, ProposalState};
+pub use self::proposals::{ProposalsMessage, ProposalsState};
 pub use self::setting::{SettingMessage, SettingState};
 pub use self::spend::{SpendMessage, SpendState};
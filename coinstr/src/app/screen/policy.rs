@@ -5,7 +5,7 @@ use std::time::Duration;
 
 use coinstr_core::bdk::{Balance, TransactionDetails};
 use coinstr_core::nostr_sdk::EventId;
-use coinstr_core::policy::Policy;
+use coinstr_core::policy::{Policy, SpendingPath};
 use coinstr_core::util;
 use iced::alignment::Horizontal;
 use iced::widget::{Column, Container, Row, Space};
@@ -15,6 +15,7 @@ use crate::app::component::{Balances, Dashboard, TransactionsList};
 use crate::app::{Context, Message, Stage, State};
 use crate::component::{button, Text};
 use crate::constants::APP_NAME;
+use crate::theme::color::DARK_RED;
 use crate::theme::icon::{ARROW_DOWN, ARROW_UP};
 
 #[derive(Debug, Clone)]
@@ -22,6 +23,7 @@ pub enum PolicyMessage {
     Send,
     Deposit,
     WalledSynced(Balance, Vec<TransactionDetails>),
+    PathsLoaded(Vec<SpendingPath>),
     Reload,
 }
 
@@ -30,10 +32,10 @@ pub struct PolicyState {
     loading: bool,
     loaded: bool,
     policy_id: EventId,
-    #[allow(dead_code)]
     policy: Policy,
     balance: Balance,
     transactions: Vec<TransactionDetails>,
+    paths: Vec<SpendingPath>,
 }
 
 impl PolicyState {
@@ -45,6 +47,7 @@ impl PolicyState {
             policy,
             balance: Balance::default(),
             transactions: Vec::new(),
+            paths: Vec::new(),
         }
     }
 }
@@ -67,14 +70,35 @@ impl State for PolicyState {
         let cache = ctx.cache.clone();
         let policy_id = self.policy_id;
         self.loading = true;
-        Command::perform(
+        let sync = Command::perform(
             async move {
                 let balance = cache.get_balance(policy_id).await.unwrap();
                 let txs = cache.get_transactions(policy_id).await.unwrap();
                 (balance, txs)
             },
             |(balance, txs)| PolicyMessage::WalledSynced(balance, txs).into(),
-        )
+        );
+
+        if !self.paths.is_empty() {
+            return sync;
+        }
+
+        let client = ctx.client.clone();
+        let policy = self.policy.clone();
+        let paths = Command::perform(
+            async move {
+                match client.analyze_policy(&policy, None).await {
+                    Ok(paths) => paths,
+                    Err(e) => {
+                        log::error!("Impossible to analyze policy: {e}");
+                        Vec::new()
+                    }
+                }
+            },
+            |paths| PolicyMessage::PathsLoaded(paths).into(),
+        );
+
+        Command::batch(vec![sync, paths])
     }
 
     fn update(&mut self, ctx: &mut Context, message: Message) -> Command<Message> {
@@ -86,8 +110,9 @@ impl State for PolicyState {
             match msg {
                 PolicyMessage::Send => {
                     let policy_id = self.policy_id;
+                    let policy = self.policy.clone();
                     return Command::perform(async {}, move |_| {
-                        Message::View(Stage::Spend(policy_id))
+                        Message::View(Stage::Spend(policy_id, policy))
                     });
                 }
                 PolicyMessage::Deposit => (),
@@ -97,6 +122,9 @@ impl State for PolicyState {
                     self.loading = false;
                     self.loaded = true;
                 }
+                PolicyMessage::PathsLoaded(paths) => {
+                    self.paths = paths;
+                }
                 PolicyMessage::Reload => {
                     return self.load(ctx);
                 }
@@ -125,6 +153,48 @@ impl State for PolicyState {
         .push(Text::new(format!("Name: {}", &self.policy.name)).view())
         .push(Text::new(format!("Description: {}", &self.policy.description)).view()); */
 
+        if self.policy.has_unspendable_internal_key() {
+            content = content.push(Text::new("Internal key: provably unspendable (NUMS)").view());
+        } else if self.policy.has_musig2_internal_key() {
+            content = content.push(
+                Text::new(format!(
+                    "Internal key: MuSig2 aggregate of {} keys",
+                    self.policy.musig2_keys.len()
+                ))
+                .view(),
+            );
+        } else if let (true, Some(group)) =
+            (self.policy.has_frost_internal_key(), &self.policy.frost)
+        {
+            content = content.push(
+                Text::new(format!(
+                    "Internal key: FROST group key ({}-of-{})",
+                    group.threshold,
+                    self.policy.members.len()
+                ))
+                .view(),
+            );
+        } else {
+            content = content.push(
+                Text::new("Internal key: not provably unspendable")
+                    .color(DARK_RED)
+                    .view(),
+            );
+        }
+
+        if !self.paths.is_empty() {
+            content = content.push(Text::new("Spending paths").bold().view());
+            for (index, path) in self.paths.iter().enumerate() {
+                let mut text = format!("{}. {path}", index + 1);
+                if let (Some(weight), Some(vsize)) =
+                    (path.satisfaction_weight, path.estimated_vsize())
+                {
+                    text.push_str(&format!(" ({weight} WU, ~{vsize} vB for a typical spend)"));
+                }
+                content = content.push(Text::new(text).view());
+            }
+        }
+
         let send_btn = button::border_text_below_icon(ARROW_UP, "Send")
             .on_press(PolicyMessage::Send.into())
             .width(Length::Fixed(110.0));
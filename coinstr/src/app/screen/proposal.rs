@@ -0,0 +1,194 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use coinstr_core::client::Error;
+use coinstr_core::nostr_sdk::EventId;
+use coinstr_core::proposal::{ApprovalStatus, SpendingProposal};
+use coinstr_core::util::{self, format};
+use iced::widget::{Column, Row, Space};
+use iced::{Command, Element, Length};
+
+use crate::app::component::Dashboard;
+use crate::app::{Context, Message, State};
+use crate::component::{rule, Text};
+use crate::constants::APP_NAME;
+use crate::theme::color::DARK_RED;
+
+#[derive(Debug, Clone)]
+pub enum ProposalMessage {
+    Loaded(SpendingProposal, EventId, ApprovalStatus),
+    ErrorChanged(Option<String>),
+}
+
+#[derive(Debug)]
+pub struct ProposalState {
+    loading: bool,
+    loaded: bool,
+    proposal_id: EventId,
+    proposal: Option<(SpendingProposal, EventId)>,
+    status: Option<ApprovalStatus>,
+    error: Option<String>,
+}
+
+impl ProposalState {
+    pub fn new(proposal_id: EventId) -> Self {
+        Self {
+            loading: false,
+            loaded: false,
+            proposal_id,
+            proposal: None,
+            status: None,
+            error: None,
+        }
+    }
+}
+
+impl State for ProposalState {
+    fn title(&self) -> String {
+        format!(
+            "{APP_NAME} - Proposal #{}",
+            util::cut_event_id(self.proposal_id)
+        )
+    }
+
+    fn load(&mut self, ctx: &Context) -> Command<Message> {
+        self.loading = true;
+        let client = ctx.client.clone();
+        let proposal_id = self.proposal_id;
+        Command::perform(
+            async move {
+                let (proposal, policy_id, _shared_keys) =
+                    client.get_proposal_by_id(proposal_id, None).await?;
+                let status = client.proposal_status(proposal_id, None).await?;
+                Ok::<_, Error>((proposal, policy_id, status))
+            },
+            |res| match res {
+                Ok((proposal, policy_id, status)) => {
+                    ProposalMessage::Loaded(proposal, policy_id, status).into()
+                }
+                Err(e) => ProposalMessage::ErrorChanged(Some(e.to_string())).into(),
+            },
+        )
+    }
+
+    fn update(&mut self, ctx: &mut Context, message: Message) -> Command<Message> {
+        if !self.loaded && !self.loading {
+            return self.load(ctx);
+        }
+
+        if let Message::Proposal(msg) = message {
+            match msg {
+                ProposalMessage::Loaded(proposal, policy_id, status) => {
+                    self.proposal = Some((proposal, policy_id));
+                    self.status = Some(status);
+                    self.loading = false;
+                    self.loaded = true;
+                }
+                ProposalMessage::ErrorChanged(error) => {
+                    self.loading = false;
+                    self.error = error;
+                }
+            }
+        }
+
+        Command::none()
+    }
+
+    fn view(&self, ctx: &Context) -> Element<Message> {
+        let mut content = Column::new().spacing(10).padding(20);
+
+        let title = format!("Proposal #{}", util::cut_event_id(self.proposal_id));
+        content = content
+            .push(Text::new(title).size(40).bold().view())
+            .push(Space::with_height(Length::Fixed(40.0)));
+
+        if let Some((proposal, policy_id)) = &self.proposal {
+            content = content
+                .push(Text::new(format!("Policy ID: {}", util::cut_event_id(*policy_id))).view())
+                .push(Text::new(format!("Memo: {}", proposal.memo)).view())
+                .push(
+                    Text::new(format!(
+                        "Amount: {} sats",
+                        format::number(proposal.amount())
+                    ))
+                    .view(),
+                )
+                .push(
+                    Text::new(match proposal.fee {
+                        Some(fee) => format!("Fee: {} sats", format::number(fee)),
+                        None => String::from("Fee: unknown"),
+                    })
+                    .view(),
+                )
+                .push(
+                    Text::new(match &self.status {
+                        Some(status) => format!("Approvals: {status}"),
+                        None => String::from("Approvals: unknown"),
+                    })
+                    .view(),
+                );
+
+            if let Some(status) = &self.status {
+                if !status.is_satisfied && !status.missing.is_empty() {
+                    let missing: Vec<String> =
+                        status.missing.iter().map(|key| key.to_string()).collect();
+                    content = content.push(
+                        Text::new(format!("Missing approvals: {}", missing.join(", "))).view(),
+                    );
+                }
+            }
+
+            content = content
+                .push(Space::with_height(Length::Fixed(20.0)))
+                .push(
+                    Row::new()
+                        .push(Text::new("Address").bold().width(Length::Fill).view())
+                        .push(Text::new("Amount").bold().width(Length::Fill).view())
+                        .push(Text::new("Label").bold().width(Length::Fill).view())
+                        .spacing(10),
+                )
+                .push(rule::horizontal_bold());
+
+            for recipient in proposal.recipients.iter() {
+                let row = Row::new()
+                    .push(
+                        Text::new(recipient.address.to_string())
+                            .width(Length::Fill)
+                            .view(),
+                    )
+                    .push(
+                        Text::new(format!("{} sats", format::number(recipient.amount)))
+                            .width(Length::Fill)
+                            .view(),
+                    )
+                    .push(
+                        Text::new(recipient.label.clone().unwrap_or_default())
+                            .width(Length::Fill)
+                            .view(),
+                    )
+                    .spacing(10);
+                content = content.push(row).push(rule::horizontal());
+            }
+        } else if self.loading {
+            content = content.push(Text::new("Loading...").view());
+        }
+
+        if let Some(error) = &self.error {
+            content = content.push(Text::new(error).color(DARK_RED).view());
+        }
+
+        Dashboard::new().view(ctx, content, false, false)
+    }
+}
+
+impl From<ProposalState> for Box<dyn State> {
+    fn from(s: ProposalState) -> Box<dyn State> {
+        Box::new(s)
+    }
+}
+
+impl From<ProposalMessage> for Message {
+    fn from(msg: ProposalMessage) -> Self {
+        Self::Proposal(msg)
+    }
+}
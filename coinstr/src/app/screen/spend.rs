@@ -1,24 +1,129 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::fmt;
 use std::str::FromStr;
 
-use coinstr_core::bitcoin::{Address, Network};
+use coinstr_core::bdk::blockchain::{Blockchain, ElectrumBlockchain};
+use coinstr_core::bdk::electrum_client::Client as ElectrumClient;
+use coinstr_core::bdk::{Error as BdkError, FeeRate, LocalUtxo};
+use coinstr_core::bitcoin::{Address, Network, OutPoint};
+use coinstr_core::fee::Fee;
 use coinstr_core::nostr_sdk::EventId;
-use iced::widget::{Column, Row, Space};
+use coinstr_core::policy::{self, Policy, PolicyBranch};
+use coinstr_core::proposal::Recipient;
+use coinstr_core::util::format;
+use coinstr_core::utxo::CoinControl;
+use iced::widget::{Checkbox, Column, PickList, Row, Space};
 use iced::{Alignment, Command, Element, Length};
 
 use crate::app::component::Dashboard;
-use crate::app::{Context, Message, State};
+use crate::app::{Context, Message, Stage, State};
 use crate::component::{button, NumericInput, Text, TextInput};
 use crate::constants::APP_NAME;
 use crate::theme::color::DARK_RED;
+use crate::theme::icon::{PLUS, TRASH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpendingPath {
+    Auto,
+    Branch(usize, PolicyBranch),
+}
+
+impl fmt::Display for SpendingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "Auto (cheapest satisfiable path)"),
+            Self::Branch(index, branch) => write!(f, "{}. {branch}", index + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeMode {
+    Target,
+    Rate,
+    Absolute,
+}
+
+impl FeeMode {
+    const ALL: [FeeMode; 3] = [FeeMode::Target, FeeMode::Rate, FeeMode::Absolute];
+
+    fn placeholder(&self) -> &str {
+        match self {
+            Self::Target => "Confirmation target (blocks)",
+            Self::Rate => "Fee rate (sat/vB)",
+            Self::Absolute => "Fee (sats)",
+        }
+    }
+
+    fn fee(&self, value: &str) -> Option<Fee> {
+        match self {
+            Self::Target if value.is_empty() => Some(Fee::default()),
+            Self::Target => value.parse().ok().map(Fee::Target),
+            Self::Rate => value.parse().ok().map(Fee::Rate),
+            Self::Absolute => value.parse().ok().map(Fee::Absolute),
+        }
+    }
+}
+
+impl fmt::Display for FeeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Target => write!(f, "Confirmation target"),
+            Self::Rate => write!(f, "Fee rate"),
+            Self::Absolute => write!(f, "Absolute fee"),
+        }
+    }
+}
+
+// TODO: get electrum endpoint from config file
+fn bitcoin_endpoint(network: Network) -> Option<&'static str> {
+    match network {
+        Network::Bitcoin => Some("ssl://blockstream.info:700"),
+        Network::Testnet => Some("ssl://blockstream.info:993"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RecipientForm {
+    address: String,
+    amount: Option<u64>,
+    label: String,
+    max: bool,
+}
+
+impl RecipientForm {
+    fn recipient(&self) -> Result<Recipient, String> {
+        let address = Address::from_str(&self.address).map_err(|e| e.to_string())?;
+        let amount = self.amount.ok_or("Invalid amount")?;
+        let recipient = Recipient::new(address, amount);
+        if self.label.is_empty() {
+            Ok(recipient)
+        } else {
+            Ok(recipient.label(&self.label))
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum SpendMessage {
-    AddressChanged(String),
-    AmountChanged(Option<u64>),
+    AddressChanged(usize, String),
+    AmountChanged(usize, Option<u64>),
+    LabelChanged(usize, String),
+    MaxChanged(usize, bool),
+    AddRecipient,
+    RemoveRecipient(usize),
     MemoChanged(String),
+    PathSelected(SpendingPath),
+    FeeModeSelected(FeeMode),
+    FeeChanged(String),
+    FeeRateEstimated(usize, Option<FeeRate>),
+    RbfChanged(bool),
+    UtxosLoaded(Vec<LocalUtxo>, Vec<OutPoint>),
+    PathsAnalyzed(Vec<policy::SpendingPath>),
+    FrozenChanged(OutPoint, bool),
     ErrorChanged(Option<String>),
     SendProposal,
 }
@@ -26,22 +131,71 @@ pub enum SpendMessage {
 #[derive(Debug)]
 pub struct SpendState {
     policy_id: EventId,
-    to_address: String,
-    amount: Option<u64>,
+    policy: Policy,
+    recipients: Vec<RecipientForm>,
     memo: String,
+    paths: Vec<SpendingPath>,
+    path: SpendingPath,
+    analysis: Vec<policy::SpendingPath>,
+    fee_mode: FeeMode,
+    fee: String,
+    /// Fee rate estimated for a confirmation target
+    fee_rate: Option<(usize, FeeRate)>,
+    rbf: bool,
+    utxos: Vec<LocalUtxo>,
+    frozen: Vec<OutPoint>,
     error: Option<String>,
 }
 
 impl SpendState {
-    pub fn new(policy_id: EventId) -> Self {
+    pub fn new(policy_id: EventId, policy: Policy) -> Self {
         Self {
             policy_id,
-            to_address: String::new(),
-            amount: None,
+            policy,
+            recipients: vec![RecipientForm::default()],
             memo: String::new(),
+            paths: vec![SpendingPath::Auto],
+            path: SpendingPath::Auto,
+            analysis: Vec::new(),
+            fee_mode: FeeMode::Target,
+            fee: String::new(),
+            fee_rate: None,
+            rbf: true,
+            utxos: Vec::new(),
+            frozen: Vec::new(),
             error: None,
         }
     }
+
+    /// Estimate the fee rate of the confirmation target, if not already estimated
+    fn estimate_fee_rate(&self, ctx: &Context) -> Command<Message> {
+        let target: usize = match self.fee_mode.fee(&self.fee) {
+            Some(Fee::Target(target)) => target,
+            _ => return Command::none(),
+        };
+        if matches!(self.fee_rate, Some((t, _)) if t == target) {
+            return Command::none();
+        }
+        let endpoint = match bitcoin_endpoint(ctx.coinstr.network()) {
+            Some(endpoint) => endpoint,
+            None => return Command::none(),
+        };
+        Command::perform(
+            async move {
+                let res = ElectrumClient::new(endpoint)
+                    .map_err(BdkError::Electrum)
+                    .and_then(|client| ElectrumBlockchain::from(client).estimate_fee(target));
+                match res {
+                    Ok(fee_rate) => Some(fee_rate),
+                    Err(e) => {
+                        log::error!("Impossible to estimate fee rate: {e}");
+                        None
+                    }
+                }
+            },
+            move |fee_rate| SpendMessage::FeeRateEstimated(target, fee_rate).into(),
+        )
+    }
 }
 
 impl State for SpendState {
@@ -49,35 +203,183 @@ impl State for SpendState {
         format!("{APP_NAME} - Send")
     }
 
+    fn load(&mut self, ctx: &Context) -> Command<Message> {
+        match ctx.client.policy_branches(&self.policy) {
+            Ok(branches) => {
+                self.paths = vec![SpendingPath::Auto];
+                self.paths.extend(
+                    branches
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, branch)| SpendingPath::Branch(index, branch)),
+                );
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+
+        let cache = ctx.cache.clone();
+        let policy_id = self.policy_id;
+        let utxos = Command::perform(
+            async move {
+                let utxos = cache.get_utxos(policy_id).await.unwrap_or_default();
+                let frozen = cache.get_frozen_utxos(policy_id).unwrap_or_default();
+                (utxos, frozen)
+            },
+            |(utxos, frozen)| SpendMessage::UtxosLoaded(utxos, frozen).into(),
+        );
+
+        let client = ctx.client.clone();
+        let policy = self.policy.clone();
+        let analysis = Command::perform(
+            async move {
+                match client.analyze_policy(&policy, None).await {
+                    Ok(paths) => paths,
+                    Err(e) => {
+                        log::error!("Impossible to analyze policy: {e}");
+                        Vec::new()
+                    }
+                }
+            },
+            |paths| SpendMessage::PathsAnalyzed(paths).into(),
+        );
+
+        Command::batch(vec![utxos, analysis, self.estimate_fee_rate(ctx)])
+    }
+
     fn update(&mut self, ctx: &mut Context, message: Message) -> Command<Message> {
         if let Message::Spend(msg) = message {
             match msg {
-                SpendMessage::AddressChanged(value) => self.to_address = value,
-                SpendMessage::AmountChanged(value) => self.amount = value,
+                SpendMessage::AddressChanged(index, value) => {
+                    if let Some(recipient) = self.recipients.get_mut(index) {
+                        recipient.address = value;
+                    }
+                }
+                SpendMessage::AmountChanged(index, value) => {
+                    if let Some(recipient) = self.recipients.get_mut(index) {
+                        recipient.amount = value;
+                    }
+                }
+                SpendMessage::LabelChanged(index, value) => {
+                    if let Some(recipient) = self.recipients.get_mut(index) {
+                        recipient.label = value;
+                    }
+                }
+                SpendMessage::MaxChanged(index, value) => {
+                    for (i, recipient) in self.recipients.iter_mut().enumerate() {
+                        recipient.max = i == index && value;
+                    }
+                }
+                SpendMessage::AddRecipient => self.recipients.push(RecipientForm::default()),
+                SpendMessage::RemoveRecipient(index) => {
+                    if self.recipients.len() > 1 && index < self.recipients.len() {
+                        self.recipients.remove(index);
+                    }
+                }
                 SpendMessage::MemoChanged(value) => self.memo = value,
+                SpendMessage::PathSelected(path) => self.path = path,
+                SpendMessage::FeeModeSelected(mode) => {
+                    self.fee_mode = mode;
+                    self.fee.clear();
+                    return self.estimate_fee_rate(ctx);
+                }
+                SpendMessage::FeeChanged(value) => {
+                    self.fee = value;
+                    return self.estimate_fee_rate(ctx);
+                }
+                SpendMessage::FeeRateEstimated(target, fee_rate) => {
+                    self.fee_rate = fee_rate.map(|fee_rate| (target, fee_rate));
+                }
+                SpendMessage::RbfChanged(value) => self.rbf = value,
+                SpendMessage::UtxosLoaded(utxos, frozen) => {
+                    self.utxos = utxos;
+                    self.frozen = frozen;
+                }
+                SpendMessage::PathsAnalyzed(paths) => self.analysis = paths,
+                SpendMessage::FrozenChanged(outpoint, frozen) => {
+                    match ctx.cache.set_frozen_utxo(self.policy_id, outpoint, frozen) {
+                        Ok(_) => {
+                            self.frozen.retain(|utxo| *utxo != outpoint);
+                            if frozen {
+                                self.frozen.push(outpoint);
+                            }
+                        }
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
                 SpendMessage::ErrorChanged(error) => self.error = error,
                 SpendMessage::SendProposal => {
-                    #[allow(unused_variables)]
-                    match self.amount {
-                        Some(amount) => match Address::from_str(&self.to_address) {
-                            Ok(to_address) => {
-                                let client = ctx.client.clone();
-                                let policy_id = self.policy_id;
-                                let memo = self.memo.clone();
-
-                                // TODO: get electrum endpoint from config file
-                                let bitcoin_endpoint: &str = match ctx.coinstr.network() {
-                                    Network::Bitcoin => "ssl://blockstream.info:700",
-                                    Network::Testnet => "ssl://blockstream.info:993",
-                                    _ => panic!("Endpoints not availabe for this network"),
-                                };
-
-                                // TODO: send proposal
+                    let recipients = match self
+                        .recipients
+                        .iter()
+                        .filter(|r| !r.max)
+                        .map(|r| r.recipient())
+                        .collect::<Result<Vec<Recipient>, String>>()
+                    {
+                        Ok(recipients) => recipients,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return Command::none();
+                        }
+                    };
+                    let drain_to = match self.recipients.iter().find(|r| r.max) {
+                        Some(recipient) => match Address::from_str(&recipient.address) {
+                            Ok(address) => Some(address),
+                            Err(e) => {
+                                self.error = Some(e.to_string());
+                                return Command::none();
                             }
-                            Err(e) => self.error = Some(e.to_string()),
                         },
-                        None => self.error = Some(String::from("Invalid amount")),
-                    }
+                        None => None,
+                    };
+                    let fee = match self.fee_mode.fee(&self.fee) {
+                        Some(fee) => fee,
+                        None => {
+                            self.error = Some(String::from("Invalid fee"));
+                            return Command::none();
+                        }
+                    };
+                    let rbf = self.rbf;
+                    let coin_control = CoinControl {
+                        do_not_spend: self.frozen.clone(),
+                        ..Default::default()
+                    };
+                    let client = ctx.client.clone();
+                    let policy_id = self.policy_id;
+                    let policy = self.policy.clone();
+                    let memo = self.memo.clone();
+                    let policy_path = match &self.path {
+                        SpendingPath::Auto => None,
+                        SpendingPath::Branch(_, branch) => Some(branch.path.clone()),
+                    };
+                    let bitcoin_endpoint: &str = bitcoin_endpoint(ctx.coinstr.network())
+                        .expect("Endpoints not availabe for this network");
+
+                    return Command::perform(
+                        async move {
+                            let electrum_client = ElectrumClient::new(bitcoin_endpoint)
+                                .map_err(BdkError::Electrum)?;
+                            let blockchain = ElectrumBlockchain::from(electrum_client);
+                            client
+                                .spend(
+                                    policy_id,
+                                    recipients,
+                                    drain_to,
+                                    memo,
+                                    fee,
+                                    rbf,
+                                    coin_control,
+                                    policy_path,
+                                    None,
+                                    blockchain,
+                                    None,
+                                )
+                                .await
+                        },
+                        move |res| match res {
+                            Ok(_) => Message::View(Stage::Policy(policy_id, policy)),
+                            Err(e) => SpendMessage::ErrorChanged(Some(e.to_string())).into(),
+                        },
+                    );
                 }
             }
         }
@@ -86,21 +388,150 @@ impl State for SpendState {
     }
 
     fn view(&self, ctx: &Context) -> Element<Message> {
-        let address = TextInput::new("Address", &self.to_address, |s| {
-            SpendMessage::AddressChanged(s).into()
-        })
-        .placeholder("Address")
-        .view();
+        let mut recipients = Column::new().spacing(10);
+        for (index, recipient) in self.recipients.iter().enumerate() {
+            let address = TextInput::new("Address", &recipient.address, move |s| {
+                SpendMessage::AddressChanged(index, s).into()
+            })
+            .placeholder("Address")
+            .view();
 
-        let amount = NumericInput::new("Amount", self.amount, |s| {
-            SpendMessage::AmountChanged(s).into()
-        })
-        .placeholder("Amount (sats)");
+            let amount = if recipient.max {
+                Column::new()
+            } else {
+                Column::new().push(
+                    NumericInput::new("Amount", recipient.amount, move |s| {
+                        SpendMessage::AmountChanged(index, s).into()
+                    })
+                    .placeholder("Amount (sats)"),
+                )
+            };
+
+            let max = Checkbox::new("Send max", recipient.max, move |value| {
+                SpendMessage::MaxChanged(index, value).into()
+            })
+            .width(Length::Fill);
+
+            let label = TextInput::new("Label", &recipient.label, move |s| {
+                SpendMessage::LabelChanged(index, s).into()
+            })
+            .placeholder("Label (optional)")
+            .view();
+
+            let mut remove_btn = button::border_only_icon(TRASH).width(Length::Fixed(40.0));
+            if self.recipients.len() > 1 {
+                remove_btn = remove_btn.on_press(SpendMessage::RemoveRecipient(index).into());
+            }
+
+            recipients = recipients.push(
+                Row::new()
+                    .push(
+                        Column::new()
+                            .push(address)
+                            .push(amount)
+                            .push(max)
+                            .push(label)
+                            .spacing(10)
+                            .width(Length::Fill),
+                    )
+                    .push(remove_btn)
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+            );
+        }
+
+        let add_recipient_btn = button::border_with_icon(PLUS, "Add recipient")
+            .on_press(SpendMessage::AddRecipient.into())
+            .width(Length::Fill);
 
         let memo = TextInput::new("Memo", &self.memo, |s| SpendMessage::MemoChanged(s).into())
             .placeholder("Memo")
             .view();
 
+        let mut path = Column::new()
+            .push(Text::new("Spending path").view())
+            .push(
+                PickList::new(self.paths.clone(), Some(self.path.clone()), |path| {
+                    SpendMessage::PathSelected(path).into()
+                })
+                .padding(10)
+                .width(Length::Fill),
+            )
+            .spacing(5);
+
+        // Estimated cost of a typical spend with the selected path (1 input and 2 outputs)
+        if let SpendingPath::Branch(index, _) = &self.path {
+            if let Some(analysis) = self.analysis.get(*index) {
+                let fee_rate: Option<FeeRate> = match self.fee_mode.fee(&self.fee) {
+                    Some(Fee::Rate(rate)) => Some(FeeRate::from_sat_per_vb(rate)),
+                    Some(Fee::Target(target)) => self
+                        .fee_rate
+                        .filter(|(t, _)| *t == target)
+                        .map(|(_, fee_rate)| fee_rate),
+                    _ => None,
+                };
+                let estimate = match (analysis.estimated_vsize(), fee_rate) {
+                    (Some(vsize), Some(fee_rate)) => analysis.estimated_fee(&fee_rate).map(|fee| {
+                        format!(
+                            "Estimated fee: {} sat ({vsize} vB at {:.1} sat/vB)",
+                            format::number(fee),
+                            fee_rate.as_sat_per_vb()
+                        )
+                    }),
+                    (Some(vsize), None) => Some(format!("Estimated size: {vsize} vB")),
+                    _ => None,
+                };
+                if let Some(estimate) = estimate {
+                    path = path.push(Text::new(estimate).view());
+                }
+            }
+        }
+
+        let fee_mode = Column::new()
+            .push(Text::new("Fee").view())
+            .push(
+                PickList::new(&FeeMode::ALL[..], Some(self.fee_mode), |mode| {
+                    SpendMessage::FeeModeSelected(mode).into()
+                })
+                .padding(10)
+                .width(Length::Fill),
+            )
+            .spacing(5);
+
+        let fee = TextInput::new(self.fee_mode.to_string(), self.fee.clone(), |s| {
+            SpendMessage::FeeChanged(s).into()
+        })
+        .placeholder(self.fee_mode.placeholder())
+        .view();
+
+        let rbf = Checkbox::new("Enable RBF", self.rbf, |value| {
+            SpendMessage::RbfChanged(value).into()
+        })
+        .width(Length::Fill);
+
+        let mut utxos = Column::new().push(Text::new("UTXOs").view()).spacing(5);
+        for utxo in self.utxos.iter() {
+            let outpoint = utxo.outpoint;
+            let frozen = Checkbox::new("Frozen", self.frozen.contains(&outpoint), move |value| {
+                SpendMessage::FrozenChanged(outpoint, value).into()
+            });
+            utxos = utxos.push(
+                Row::new()
+                    .push(
+                        Column::new()
+                            .push(Text::new(outpoint.to_string()).view())
+                            .push(
+                                Text::new(format!("{} sats", format::number(utxo.txout.value)))
+                                    .view(),
+                            )
+                            .width(Length::Fill),
+                    )
+                    .push(frozen)
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+            );
+        }
+
         let error = if let Some(error) = &self.error {
             Row::new().push(Text::new(error).color(DARK_RED).view())
         } else {
@@ -111,9 +542,14 @@ impl State for SpendState {
             button::primary("Send proposal").on_press(SpendMessage::SendProposal.into());
 
         let content = Column::new()
-            .push(address)
-            .push(amount)
+            .push(recipients)
+            .push(add_recipient_btn)
             .push(memo)
+            .push(path)
+            .push(fee_mode)
+            .push(fee)
+            .push(rbf)
+            .push(utxos)
             .push(error)
             .push(Space::with_height(Length::Fixed(15.0)))
             .push(send_porposal_btn)
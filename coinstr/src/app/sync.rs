@@ -10,8 +10,9 @@ use coinstr_core::bdk::blockchain::ElectrumBlockchain;
 use coinstr_core::bdk::electrum_client::Client as ElectrumClient;
 use coinstr_core::bitcoin::Network;
 use coinstr_core::constants::POLICY_KIND;
-use coinstr_core::nostr_sdk::{nips, Event, EventId, Filter, Keys, RelayPoolNotification, Result};
+use coinstr_core::nostr_sdk::{Event, EventId, Filter, Keys, RelayPoolNotification, Result};
 use coinstr_core::policy::Policy;
+use coinstr_core::util::{self, encryption};
 use coinstr_core::CoinstrClient;
 use futures_util::future::{AbortHandle, Abortable};
 use iced::Subscription;
@@ -134,22 +135,25 @@ async fn handle_event(
     shared_keys: &mut HashMap<EventId, Keys>,
     event: Event,
 ) -> Result<()> {
-    if event.kind == POLICY_KIND && !cache.policy_exists(event.id)? {
-        if let Some(shared_key) = shared_keys.get(&event.id) {
-            let content = nips::nip04::decrypt(
-                &shared_key.secret_key()?,
-                &shared_key.public_key(),
-                &event.content,
-            )?;
+    // The rotations of the shared key refer to the original policy event
+    let policy_id = util::extract_first_event_id(&event).unwrap_or(event.id);
+    if event.kind == POLICY_KIND && !cache.policy_exists(policy_id)? {
+        if let Some(shared_key) = shared_keys.get(&policy_id) {
+            if event.pubkey != shared_key.public_key() {
+                log::debug!("Skipping policy event {} of another shared key", event.id);
+                return Ok(());
+            }
+            let content =
+                encryption::decrypt(&shared_key.secret_key()?, &shared_key.public_key(), &event)?;
             let policy = Policy::from_json(content)?;
-            cache.insert_policy(event.id, policy)?;
+            cache.insert_policy(policy_id, policy)?;
         } else {
-            log::info!("Requesting shared key for {}", event.id);
+            log::info!("Requesting shared key for {policy_id}");
             tokio::time::sleep(Duration::from_secs(5)).await;
             let shared_key = client
-                .get_shared_key_by_policy_id(event.id, Some(Duration::from_secs(30)))
+                .get_shared_key_by_policy_id(policy_id, Some(Duration::from_secs(30)))
                 .await?;
-            shared_keys.insert(event.id, shared_key);
+            shared_keys.insert(policy_id, shared_key);
             handle_event(client, cache, shared_keys, event).await?;
         }
     }